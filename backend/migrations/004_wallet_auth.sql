@@ -0,0 +1,25 @@
+-- Sign-In With Sui
+-- An account signs in with an email and password, a Sui wallet, or both.
+ALTER TABLE users
+    ALTER COLUMN email DROP NOT NULL,
+    ALTER COLUMN password_hash DROP NOT NULL,
+    ADD COLUMN wallet_address VARCHAR(66) UNIQUE, -- Lowercased, 0x + 64 hex digits
+    ADD CONSTRAINT users_password_with_email CHECK ((email IS NULL) = (password_hash IS NULL)),
+    ADD CONSTRAINT users_has_credentials CHECK (email IS NOT NULL OR wallet_address IS NOT NULL);
+
+-- Providers created by a wallet sign-in have no email or API key yet
+ALTER TABLE service_providers
+    ALTER COLUMN email DROP NOT NULL,
+    ALTER COLUMN api_key_hash DROP NOT NULL,
+    ADD COLUMN user_id UUID UNIQUE REFERENCES users(id) ON DELETE SET NULL;
+
+-- Wallet Sign-In Nonces
+-- Each nonce is deleted by the sign-in that uses it.
+CREATE TABLE wallet_nonces (
+    nonce VARCHAR(64) PRIMARY KEY,
+    wallet_address VARCHAR(66) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX idx_wallet_nonces_expires_at ON wallet_nonces(expires_at);
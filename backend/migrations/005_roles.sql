@@ -0,0 +1,5 @@
+-- Account Roles
+-- New accounts are developers; a wallet sign-in makes its account a provider.
+ALTER TABLE users
+    ALTER COLUMN role SET DEFAULT 'developer',
+    ADD CONSTRAINT users_role CHECK (role IN ('developer', 'provider', 'admin'));
@@ -0,0 +1,27 @@
+-- Provider API Keys
+-- Only a hash of each key is kept; `prefix` is the start of the key, shown
+-- to tell keys apart.
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    provider_id UUID NOT NULL REFERENCES service_providers(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id) ON DELETE CASCADE, -- NULL for keys carried over below
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(128) UNIQUE NOT NULL,
+    scopes TEXT[] NOT NULL, -- 'read-only', 'services:write', 'stats:read'
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_provider ON api_keys(provider_id);
+
+-- Providers' existing keys keep working with every scope, on behalf of the
+-- provider's account if it has one, until they are revoked
+INSERT INTO api_keys (provider_id, created_by, name, prefix, key_hash, scopes)
+SELECT id, user_id, 'Legacy key', 'legacy', api_key_hash, ARRAY['services:write', 'stats:read']
+FROM service_providers
+WHERE api_key_hash IS NOT NULL;
+
+-- Superseded by api_keys
+ALTER TABLE service_providers DROP COLUMN api_key_hash;
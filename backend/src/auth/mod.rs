@@ -1,3 +1,226 @@
-pub fn generate_token() -> String {
-    "mock_token".to_string()
+//! Passwords and tokens of portal accounts.
+//!
+//! Passwords are hashed with Argon2id. Access tokens are short-lived HS256
+//! JWTs; refresh tokens are random strings the database only knows by their
+//! SHA-256 hash. Wallets sign in with a [`siws`] message instead of a
+//! password. Providers' scripts authenticate with an API key (`imk_…`) in
+//! place of an access token.
+
+pub mod siws;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::models::api_key::ApiKeyScope;
+use crate::models::user::{Role, User};
+use crate::utils::errors::ApiError;
+use crate::AppState;
+
+/// Hash of no account's password, made with the same parameters as real ones.
+static DUMMY_PASSWORD_HASH: Lazy<String> = Lazy::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(b"", &salt)
+        .expect("hashing a password with default parameters")
+        .to_string()
+});
+
+/// Start of every API key, which tells them apart from access tokens.
+pub const API_KEY_PREFIX: &str = "imk_";
+
+/// Claims of an access token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// ID of the user.
+    pub sub: Uuid,
+    pub role: Role,
+    pub provider_id: Option<Uuid>,
+    pub wallet: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// The caller of a protected route, as `require_auth` found it in its
+/// access token. Handlers behind `require_auth` take it as an extractor.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    /// ID of the user, or of the provider for an API key carried over from
+    /// before accounts.
+    pub id: Uuid,
+    pub role: Role,
+    /// The provider record the caller manages, if any.
+    pub provider_id: Option<Uuid>,
+    pub wallet: Option<String>,
+    /// What the caller's API key may do; `None` for access tokens.
+    pub scopes: Option<Vec<ApiKeyScope>>,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    pub fn is_api_key(&self) -> bool {
+        self.scopes.is_some()
+    }
+
+    /// Whether the caller may do what `scope` covers. Access tokens may do
+    /// anything.
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.as_ref().map_or(true, |scopes| scopes.contains(&scope))
+    }
+
+    pub fn require_scope(&self, scope: ApiKeyScope) -> Result<(), ApiError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+}
+
+impl From<Claims> for AuthUser {
+    fn from(claims: Claims) -> Self {
+        Self {
+            id: claims.sub,
+            role: claims.role,
+            provider_id: claims.provider_id,
+            wallet: claims.wallet,
+            scopes: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<AuthUser>().cloned().ok_or(ApiError::Unauthorized)
+    }
+}
+
+/// Hashes `password` with Argon2id and a random salt, off the async runtime.
+pub async fn hash_password(password: String) -> Result<String, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|_| ApiError::InternalServerError)?
+    .map_err(|e| {
+        tracing::error!("Failed to hash password: {}", e);
+        ApiError::InternalServerError
+    })
+}
+
+/// Whether `password` matches `hash`, checked off the async runtime.
+pub async fn verify_password(password: String, hash: String) -> bool {
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&hash)
+            .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Takes as long as [`verify_password`] but always fails, for sign-ins to
+/// accounts that don't exist or have no password, so that the response time
+/// doesn't tell them apart.
+pub async fn reject_password(password: String) {
+    let hash = tokio::task::spawn_blocking(|| DUMMY_PASSWORD_HASH.clone()).await.unwrap_or_default();
+    verify_password(password, hash).await;
+}
+
+pub fn issue_access_token(user: &User, secret: &str, ttl_seconds: i64) -> Result<String, ApiError> {
+    let now = crate::utils::now();
+    let claims = Claims {
+        sub: user.id,
+        role: user.role,
+        provider_id: user.provider_id,
+        wallet: user.wallet_address.clone(),
+        iat: now,
+        exp: now + ttl_seconds,
+    };
+    Ok(encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))?)
+}
+
+pub fn decode_access_token(token: &str, secret: &str) -> Result<Claims, ApiError> {
+    let data = decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &Validation::default())?;
+    Ok(data.claims)
+}
+
+/// A new refresh token: 32 random bytes, base64url-encoded.
+pub fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// A new API key: a refresh-token-like random string after [`API_KEY_PREFIX`].
+pub fn generate_api_key() -> String {
+    format!("{}{}", API_KEY_PREFIX, generate_refresh_token())
+}
+
+/// What the database stores of a refresh token or API key. Both are random,
+/// so a fast hash is enough.
+pub fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Finds the caller from the `Authorization: Bearer` header, which holds
+/// either an access token or an API key. Records the use of API keys.
+pub async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<AuthUser, ApiError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(ApiError::Unauthorized)?;
+
+    if !token.starts_with(API_KEY_PREFIX) {
+        // Keys carried over from before `api_keys` don't have the prefix, but
+        // aren't shaped like a JWT either
+        if token.split('.').count() == 3 {
+            return decode_access_token(token, &state.jwt_secret).map(AuthUser::from);
+        }
+    }
+
+    // A key acts with its creator's role; carried-over keys have no creator
+    // and act as their provider
+    let key = sqlx::query!(
+        r#"
+        WITH used AS (
+            UPDATE api_keys SET last_used_at = NOW()
+            WHERE key_hash = $1 AND revoked_at IS NULL
+            RETURNING created_by, provider_id, scopes
+        )
+        SELECT used.created_by, used.provider_id AS "provider_id!", used.scopes AS "scopes!", users.role AS "role?: Role"
+        FROM used LEFT JOIN users ON users.id = used.created_by
+        "#,
+        token_hash(token)
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(ApiError::Unauthorized)?;
+
+    Ok(AuthUser {
+        id: key.created_by.unwrap_or(key.provider_id),
+        role: key.role.unwrap_or(Role::Provider),
+        provider_id: Some(key.provider_id),
+        wallet: None,
+        scopes: Some(key.scopes.iter().filter_map(|scope| ApiKeyScope::parse(scope)).collect()),
+    })
 }
@@ -0,0 +1,103 @@
+//! Sign-In With Sui messages.
+//!
+//! Modelled on EIP-4361, the wallet signs this text as a Sui personal
+//! message:
+//!
+//! ```text
+//! inframint.io wants you to sign in with your Sui account:
+//! 0x8f5c…
+//!
+//! Sign in to InfraMint.
+//!
+//! Nonce: 3b1f…
+//! Issued At: 2024-03-01T12:00:00Z
+//! Expiration Time: 2024-03-01T12:05:00Z
+//! ```
+
+use std::fmt;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Sui account:";
+const STATEMENT: &str = "Sign in to InfraMint.";
+const NONCE_PREFIX: &str = "Nonce: ";
+const ISSUED_AT_PREFIX: &str = "Issued At: ";
+const EXPIRATION_TIME_PREFIX: &str = "Expiration Time: ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiwsMessage {
+    pub domain: String,
+    /// Normalized as by [`normalize_address`].
+    pub address: String,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: DateTime<Utc>,
+}
+
+impl SiwsMessage {
+    /// Parses a message in the exact layout `Display` writes. Returns `None`
+    /// for anything else, including extra lines.
+    pub fn parse(message: &str) -> Option<Self> {
+        let mut lines = message.split('\n');
+
+        let domain = lines.next()?.strip_suffix(HEADER_SUFFIX)?;
+        let address = normalize_address(lines.next()?)?;
+        if !lines.next()?.is_empty() || lines.next()? != STATEMENT || !lines.next()?.is_empty() {
+            return None;
+        }
+        let nonce = lines.next()?.strip_prefix(NONCE_PREFIX)?;
+        let issued_at = parse_timestamp(lines.next()?.strip_prefix(ISSUED_AT_PREFIX)?)?;
+        let expiration_time = parse_timestamp(lines.next()?.strip_prefix(EXPIRATION_TIME_PREFIX)?)?;
+        if lines.next().is_some() || domain.is_empty() || nonce.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            domain: domain.to_string(),
+            address,
+            nonce: nonce.to_string(),
+            issued_at,
+            expiration_time,
+        })
+    }
+
+    /// Whether `now` falls between the issue and expiration times.
+    pub fn is_current(&self, now: DateTime<Utc>) -> bool {
+        self.issued_at <= now && now < self.expiration_time
+    }
+}
+
+impl fmt::Display for SiwsMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}{}", self.domain, HEADER_SUFFIX)?;
+        writeln!(f, "{}", self.address)?;
+        writeln!(f)?;
+        writeln!(f, "{}", STATEMENT)?;
+        writeln!(f)?;
+        writeln!(f, "{}{}", NONCE_PREFIX, self.nonce)?;
+        writeln!(f, "{}{}", ISSUED_AT_PREFIX, self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true))?;
+        write!(f, "{}{}", EXPIRATION_TIME_PREFIX, self.expiration_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+}
+
+/// A new nonce: 16 random bytes, hex-encoded.
+pub fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Lowercases a Sui address and pads it to its full 32 bytes, the form
+/// `SuiSignature::signer_address` returns. `None` if it isn't an address.
+pub fn normalize_address(address: &str) -> Option<String> {
+    let hex = address.strip_prefix("0x")?;
+    if hex.is_empty() || hex.len() > 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{:0>64}", hex.to_ascii_lowercase()))
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|t| t.with_timezone(&Utc))
+}
@@ -1,5 +1,4 @@
 use serde::Deserialize;
-use std::env;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -27,6 +26,10 @@ pub struct AuthConfig {
     pub jwt_secret: String,
     pub jwt_expiry: i64,
     pub refresh_expiry: i64,
+    /// Domain that Sign-In With Sui messages must name.
+    pub siws_domain: String,
+    /// How long a Sign-In With Sui nonce stays usable, in seconds.
+    pub siws_nonce_ttl: i64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -52,6 +55,8 @@ impl Config {
         builder = builder.set_default("database.max_connections", "10")?;
         builder = builder.set_default("auth.jwt_expiry", "3600")?;
         builder = builder.set_default("auth.refresh_expiry", "86400")?;
+        builder = builder.set_default("auth.siws_domain", "localhost:5173")?;
+        builder = builder.set_default("auth.siws_nonce_ttl", "300")?;
         builder = builder.set_default("validator.url", "http://localhost:50051")?;
         builder = builder.set_default("validator.timeout", "5")?;
 
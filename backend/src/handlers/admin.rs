@@ -2,13 +2,12 @@ use axum::{
     extract::State,
     Json,
     response::IntoResponse,
-    http::StatusCode,
 };
 use crate::AppState;
 use serde_json::json;
 
 pub async fn list_providers(
-    State(state): State<AppState>,
+    State(_state): State<AppState>,
 ) -> impl IntoResponse {
     // In a real app we query: SELECT * FROM users WHERE role = 'provider'
     // For now, return Mock List
@@ -0,0 +1,126 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+    http::StatusCode,
+};
+use crate::auth::{self, AuthUser};
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+use crate::utils::errors::ApiError;
+use crate::AppState;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Characters of a key kept as its prefix: `imk_` and eight more.
+const PREFIX_LENGTH: usize = 12;
+
+#[derive(Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// The provider whose keys `user` manages. Keys are managed from a signed-in
+/// session only, so a leaked key can't mint others.
+fn key_owner(user: &AuthUser) -> Result<Uuid, ApiError> {
+    if user.is_api_key() {
+        return Err(ApiError::Forbidden);
+    }
+    user.provider_id.ok_or(ApiError::Forbidden)
+}
+
+/// Creates a key for the caller's provider. The response is the only time the
+/// key itself is shown.
+pub async fn create(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let provider_id = key_owner(&user)?;
+    payload.validate().map_err(|e| ApiError::ValidationError(e.to_string()))?;
+
+    let mut scopes: Vec<&str> = payload.scopes.iter().map(|scope| scope.as_str()).collect();
+    scopes.sort_unstable();
+    scopes.dedup();
+
+    let key = auth::generate_api_key();
+    let record = sqlx::query!(
+        r#"
+        INSERT INTO api_keys (provider_id, created_by, name, prefix, key_hash, scopes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, created_at
+        "#,
+        provider_id,
+        user.id,
+        payload.name,
+        &key[..PREFIX_LENGTH],
+        auth::token_hash(&key),
+        &scopes as &[&str]
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(json!({
+        "id": record.id,
+        "name": payload.name,
+        "key": key,
+        "prefix": &key[..PREFIX_LENGTH],
+        "scopes": scopes,
+        "created_at": record.created_at,
+    }))))
+}
+
+/// Lists the caller's provider's keys that aren't revoked.
+pub async fn list(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<Json<Value>, ApiError> {
+    let provider_id = key_owner(&user)?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, name, prefix, scopes, last_used_at, created_at
+        FROM api_keys
+        WHERE provider_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at
+        "#,
+        provider_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let api_keys: Vec<ApiKey> = rows.into_iter().map(|row| ApiKey {
+        id: row.id,
+        name: row.name,
+        prefix: row.prefix,
+        scopes: row.scopes.iter().filter_map(|scope| ApiKeyScope::parse(scope)).collect(),
+        last_used_at: row.last_used_at,
+        created_at: row.created_at,
+    }).collect();
+
+    Ok(Json(json!({ "api_keys": api_keys })))
+}
+
+pub async fn revoke(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let provider_id = key_owner(&user)?;
+
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND provider_id = $2 AND revoked_at IS NULL",
+        id,
+        provider_id
+    )
+    .execute(&state.db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,55 +1,341 @@
 use axum::{
     extract::State,
     Json,
-    response::IntoResponse,
     http::StatusCode,
 };
+use crate::auth::{self, siws::{self, SiwsMessage}};
+use crate::models::user::{Role, User};
+use crate::utils::errors::ApiError;
 use crate::AppState;
+use chrono::{Duration, SubsecRound, Utc};
+use inframint_types::signature::SuiSignature;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct AuthRequest {
+    #[validate(email)]
     pub email: String,
-    pub password: String, // In real app, hash this!
+    #[validate(length(min = 8, max = 128))]
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct WalletNonceRequest {
+    pub address: String,
+}
+
+#[derive(Deserialize)]
+pub struct WalletVerifyRequest {
+    /// The Sign-In With Sui message, as signed.
+    pub message: String,
+    /// Base64 Sui serialized signature of `message` as a personal message.
+    pub signature: String,
+}
+
+fn invalid_credentials() -> ApiError {
+    ApiError::AuthError("Invalid credentials".to_string())
+}
+
+fn invalid_refresh_token() -> ApiError {
+    ApiError::AuthError("Invalid refresh token".to_string())
+}
+
+fn invalid_wallet_signature() -> ApiError {
+    ApiError::AuthError("Invalid wallet signature".to_string())
 }
 
 pub async fn register(
     State(state): State<AppState>,
     Json(payload): Json<AuthRequest>,
-) -> impl IntoResponse {
-    // Check if user exists (Mock check)
-    if payload.email == "exists@example.com" {
-         return (StatusCode::CONFLICT, Json(json!({"error": "User already exists"}))).into_response();
-    }
-    
-    // In a real app: Hash password, insert into DB
-    // let user_id = sqlx::query!(...)
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    payload.validate().map_err(|e| ApiError::ValidationError(e.to_string()))?;
+
+    let email = payload.email.trim().to_lowercase();
+    let password_hash = auth::hash_password(payload.password).await?;
 
-    Json(json!({ 
-        "token": "mock_jwt_token_for_new_user",
-        "user": {
-            "email": payload.email,
-            "role": "provider"
-        }
-    })).into_response()
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO users (email, password_hash)
+        VALUES ($1, $2)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id, email, wallet_address, role AS "role: Role", NULL::UUID AS "provider_id?"
+        "#,
+        email,
+        password_hash
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError::Conflict("User already exists".to_string()))?;
+
+    let session = start_session(&state.db, &state, user, Uuid::new_v4()).await?;
+    Ok((StatusCode::CREATED, session))
 }
 
 pub async fn login(
     State(state): State<AppState>,
     Json(payload): Json<AuthRequest>,
-) -> impl IntoResponse {
-    // Mock Login Logic
-    if payload.email == "demo@inframint.com" && payload.password == "password" {
-        Json(json!({ 
-            "token": "mock_jwt_token_valid",
-            "user": {
-                "id": "123",
-                "email": payload.email,
-                "role": "admin"
-            }
-        })).into_response()
-    } else {
-        (StatusCode::UNAUTHORIZED, Json(json!({"error": "Invalid credentials"}))).into_response()
+) -> Result<Json<Value>, ApiError> {
+    let email = payload.email.trim().to_lowercase();
+
+    let user = sqlx::query!(
+        r#"
+        SELECT u.id, u.email, u.wallet_address, u.role AS "role: Role", u.password_hash, sp.id AS "provider_id?"
+        FROM users u
+        LEFT JOIN service_providers sp ON sp.user_id = u.id
+        WHERE u.email = $1
+        "#,
+        email
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    let password_hash = user.as_ref().and_then(|user| user.password_hash.clone());
+    let (Some(user), Some(password_hash)) = (user, password_hash) else {
+        auth::reject_password(payload.password).await;
+        return Err(invalid_credentials());
+    };
+
+    if !auth::verify_password(payload.password, password_hash).await {
+        return Err(invalid_credentials());
+    }
+
+    let user = User {
+        id: user.id,
+        email: user.email,
+        wallet_address: user.wallet_address,
+        role: user.role,
+        provider_id: user.provider_id,
+    };
+    start_session(&state.db, &state, user, Uuid::new_v4()).await
+}
+
+/// Exchanges a refresh token for a new pair of tokens. Each refresh token
+/// works once: presenting one that was already used means a copy leaked, so
+/// the whole family is revoked.
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let token_hash = auth::token_hash(&payload.refresh_token);
+    let mut tx = state.db.begin().await?;
+
+    let token = sqlx::query!(
+        r#"
+        SELECT t.id, t.family_id, t.revoked_at IS NOT NULL AS "revoked!", t.expires_at > NOW() AS "live!",
+               u.id AS user_id, u.email, u.wallet_address, u.role AS "role: Role", sp.id AS "provider_id?"
+        FROM refresh_tokens t
+        JOIN users u ON t.user_id = u.id
+        LEFT JOIN service_providers sp ON sp.user_id = u.id
+        WHERE t.token_hash = $1
+        FOR UPDATE OF t
+        "#,
+        token_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid_refresh_token)?;
+
+    if token.revoked {
+        tracing::warn!("Refresh token reused, revoking its family {}", token.family_id);
+        revoke_family(&mut *tx, token.family_id).await?;
+        tx.commit().await?;
+        return Err(invalid_refresh_token());
     }
+    if !token.live {
+        return Err(invalid_refresh_token());
+    }
+
+    sqlx::query!("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1", token.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let user = User {
+        id: token.user_id,
+        email: token.email,
+        wallet_address: token.wallet_address,
+        role: token.role,
+        provider_id: token.provider_id,
+    };
+    let session = start_session(&mut *tx, &state, user, token.family_id).await?;
+    tx.commit().await?;
+
+    Ok(session)
+}
+
+/// Revokes the session of a refresh token. Access tokens already issued
+/// stay valid until they expire.
+pub async fn logout(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<StatusCode, ApiError> {
+    let token_hash = auth::token_hash(&payload.refresh_token);
+
+    let family_id = sqlx::query_scalar!(
+        "SELECT family_id FROM refresh_tokens WHERE token_hash = $1",
+        token_hash
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    if let Some(family_id) = family_id {
+        revoke_family(&state.db, family_id).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Starts a Sign-In With Sui: issues a nonce for `address` and the message
+/// its wallet should sign.
+pub async fn wallet_nonce(
+    State(state): State<AppState>,
+    Json(payload): Json<WalletNonceRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let address = siws::normalize_address(&payload.address)
+        .ok_or_else(|| ApiError::ValidationError("Invalid Sui address".to_string()))?;
+
+    // Seconds precision, as the message carries them
+    let issued_at = Utc::now().trunc_subsecs(0);
+    let message = SiwsMessage {
+        domain: state.siws_domain.clone(),
+        address,
+        nonce: siws::generate_nonce(),
+        issued_at,
+        expiration_time: issued_at + Duration::seconds(state.siws_nonce_ttl),
+    };
+
+    sqlx::query!("DELETE FROM wallet_nonces WHERE expires_at <= NOW()")
+        .execute(&state.db)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO wallet_nonces (nonce, wallet_address, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        "#,
+        message.nonce,
+        message.address,
+        state.siws_nonce_ttl as f64
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "nonce": message.nonce,
+        "message": message.to_string(),
+        "expires_at": message.expiration_time,
+    })))
+}
+
+/// Completes a Sign-In With Sui: checks the signed message, uses up its
+/// nonce and signs in the account of the wallet, creating it and its provider
+/// record on first sign-in.
+pub async fn wallet_verify(
+    State(state): State<AppState>,
+    Json(payload): Json<WalletVerifyRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let message = SiwsMessage::parse(&payload.message)
+        .ok_or_else(|| ApiError::ValidationError("Malformed sign-in message".to_string()))?;
+    if message.domain != state.siws_domain || !message.is_current(Utc::now()) {
+        return Err(invalid_wallet_signature());
+    }
+
+    let signature = SuiSignature::from_base64(&payload.signature).map_err(|_| invalid_wallet_signature())?;
+    if signature.signer_address() != message.address
+        || !signature.verify_personal_message(payload.message.as_bytes()).unwrap_or(false)
+    {
+        return Err(invalid_wallet_signature());
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query_scalar!(
+        "DELETE FROM wallet_nonces WHERE nonce = $1 AND wallet_address = $2 AND expires_at > NOW() RETURNING nonce",
+        message.nonce,
+        message.address
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid_wallet_signature)?;
+
+    let mut user = sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO users (wallet_address, role)
+        VALUES ($1, 'provider')
+        ON CONFLICT (wallet_address) DO UPDATE SET updated_at = NOW()
+        RETURNING id, email, wallet_address, role AS "role: Role", NULL::UUID AS "provider_id?"
+        "#,
+        message.address
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Links the provider registered with this wallet, or creates one named after it
+    let provider_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO service_providers (name, wallet_address, user_id)
+        VALUES ($1, $1, $2)
+        ON CONFLICT (wallet_address) DO UPDATE SET user_id = EXCLUDED.user_id, updated_at = NOW()
+        RETURNING id
+        "#,
+        message.address,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    user.provider_id = Some(provider_id);
+
+    let session = start_session(&mut *tx, &state, user, Uuid::new_v4()).await?;
+    tx.commit().await?;
+
+    Ok(session)
+}
+
+/// Issues an access token and the next refresh token of `family_id`.
+async fn start_session(
+    executor: impl sqlx::PgExecutor<'_>,
+    state: &AppState,
+    user: User,
+    family_id: Uuid,
+) -> Result<Json<Value>, ApiError> {
+    let access_token = auth::issue_access_token(&user, &state.jwt_secret, state.jwt_expiry)?;
+    let refresh_token = auth::generate_refresh_token();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+        "#,
+        user.id,
+        family_id,
+        auth::token_hash(&refresh_token),
+        state.refresh_expiry as f64
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": state.jwt_expiry,
+        "refresh_token": refresh_token,
+        "user": user,
+    })))
+}
+
+async fn revoke_family(executor: impl sqlx::PgExecutor<'_>, family_id: Uuid) -> Result<(), ApiError> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+        family_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
 }
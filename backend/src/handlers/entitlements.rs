@@ -1,58 +1,80 @@
-use axum::{
-    extract::{Path, State, Query},
-    Json,
-    http::StatusCode,
-    response::IntoResponse,
-};
+use axum::{extract::State, Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
+use inframint_types::Entitlement;
+use inframint_types::proto::SignedRequest;
 use crate::AppState;
-use serde_json::json;
+use crate::utils::errors::ApiError;
+use serde_json::{json, Value};
+
+/// A `SignedRequest` with its encoded payload in base64.
+#[derive(Deserialize)]
+pub struct SignedRequestBody {
+    pub payload: String,
+    pub signature: String,
+}
+
+impl SignedRequestBody {
+    fn decode(self) -> Result<SignedRequest, String> {
+        Ok(SignedRequest {
+            payload: BASE64.decode(&self.payload)
+                .map_err(|_| "Signed request payload is not valid base64".to_string())?,
+            signature: self.signature,
+        })
+    }
+}
 
 #[derive(Deserialize)]
 pub struct ValidateEntitlementRequest {
     pub entitlement_id: String,
+    #[serde(default)]
     pub signature: String,
+    #[serde(default)]
     pub message: String,
+    pub signed_request: Option<SignedRequestBody>,
 }
 
 #[derive(Serialize)]
 pub struct ValidateEntitlementResponse {
     pub valid: bool,
+    pub entitlement: Option<Entitlement>,
     pub error: Option<String>,
 }
 
+/// Refusals are answered with the status of their error code, e.g. 404
+/// for an unknown entitlement or 403 for an expired one.
 pub async fn validate_entitlement(
     State(state): State<AppState>,
     Json(request): Json<ValidateEntitlementRequest>,
-) -> impl IntoResponse {
-    let result = state.validator.validate_entitlement(
+) -> Result<Json<Value>, ApiError> {
+    let signed_request = request.signed_request
+        .map(SignedRequestBody::decode)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    let entitlement = state.validator.validate_entitlement(
         &request.entitlement_id,
         &request.signature,
         &request.message,
-    ).await;
-
-    match result {
-        Ok(valid) => {
-            Json(json!({
-                "valid": valid,
-                "error": None::<String>
-            }))
-        },
-        Err(e) => {
-            Json(json!({
-                "valid": false,
-                "error": format!("Validation failed: {}", e)
-            }))
-        }
-    }
+        signed_request,
+    ).await?;
+
+    Ok(Json(json!({
+        "valid": true,
+        "entitlement": entitlement,
+        "error": None::<String>
+    })))
 }
 
 #[derive(Deserialize)]
 pub struct ConsumeEntitlementRequest {
     pub entitlement_id: String,
     pub amount: u64,
+    #[serde(default)]
     pub signature: String,
+    #[serde(default)]
     pub message: String,
+    pub signed_request: Option<SignedRequestBody>,
 }
 
 #[derive(Serialize)]
@@ -65,30 +87,25 @@ pub struct ConsumeEntitlementResponse {
 pub async fn consume_entitlement(
     State(state): State<AppState>,
     Json(request): Json<ConsumeEntitlementRequest>,
-) -> impl IntoResponse {
-    let result = state.validator.consume_entitlement(
+) -> Result<Json<Value>, ApiError> {
+    let signed_request = request.signed_request
+        .map(SignedRequestBody::decode)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    let remaining = state.validator.consume_entitlement(
         &request.entitlement_id,
         request.amount,
         &request.signature,
         &request.message,
-    ).await;
-
-    match result {
-        Ok(remaining) => {
-            Json(json!({
-                "success": true,
-                "remaining_quota": remaining,
-                "error": None::<String>
-            }))
-        },
-        Err(e) => {
-            Json(json!({
-                "success": false,
-                "remaining_quota": None::<u64>,
-                "error": format!("Consumption failed: {}", e)
-            }))
-        }
-    }
+        signed_request,
+    ).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "remaining_quota": remaining,
+        "error": None::<String>
+    })))
 }
 
 #[derive(Deserialize)]
@@ -107,25 +124,15 @@ pub struct ValidateSignatureResponse {
 pub async fn validate_signature(
     State(state): State<AppState>,
     Json(request): Json<ValidateSignatureRequest>,
-) -> impl IntoResponse {
-    let result = state.validator.validate_signature(
+) -> Result<Json<Value>, ApiError> {
+    let valid = state.validator.validate_signature(
         &request.entitlement_id,
         &request.signature,
         &request.message,
-    ).await;
-
-    match result {
-        Ok(valid) => {
-            Json(json!({
-                "valid": valid,
-                "error": None::<String>
-            }))
-        },
-        Err(e) => {
-            Json(json!({
-                "valid": false,
-                "error": format!("Signature validation failed: {}", e)
-            }))
-        }
-    }
+    ).await?;
+
+    Ok(Json(json!({
+        "valid": valid,
+        "error": (!valid).then_some("Invalid signature")
+    })))
 }
@@ -3,6 +3,7 @@ pub mod auth;
 pub mod admin;
 pub mod entitlements;
 pub mod stats;
+pub mod api_keys;
 
 use axum::{Json, response::IntoResponse};
 use serde_json::json;
@@ -6,8 +6,13 @@ use axum::{
 };
 use crate::AppState;
 use serde_json::json;
-use crate::models::service::Service;
+use crate::auth::AuthUser;
+use crate::models::api_key::ApiKeyScope;
+use crate::utils::errors::ApiError;
+use inframint_types::JsonRpcCostTable;
 use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use std::collections::BTreeMap;
 
 #[derive(Serialize)]
 pub struct ServiceListResponse {
@@ -70,6 +75,7 @@ pub struct ServiceDetailResponse {
     pub type_: String,
     pub status: String,
     pub tags: Vec<String>,
+    pub metadata: serde_json::Value,
     pub pricing_tiers: Vec<PricingTierResponse>,
 }
 
@@ -94,7 +100,7 @@ pub async fn get(
     // Fetch Service
     let service = match sqlx::query!(
         r#"
-        SELECT s.id, s.name, s.description, s.service_type, s.status, s.tags, sp.name as provider_name
+        SELECT s.id, s.name, s.description, s.service_type, s.status, s.tags, s.metadata, sp.name as provider_name
         FROM services s
         JOIN service_providers sp ON s.provider_id = sp.id
         WHERE s.id = $1
@@ -137,6 +143,7 @@ pub async fn get(
         type_: service.service_type,
         status: service.status.unwrap_or_else(|| "active".to_string()),
         tags: service.tags.unwrap_or_default(),
+        metadata: service.metadata,
         pricing_tiers: tiers.into_iter().map(|t| PricingTierResponse {
             id: t.id.to_string(),
             tier_name: t.tier_name,
@@ -149,11 +156,219 @@ pub async fn get(
     Json(response).into_response()
 }
 
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// Web-search style text query (`"json rpc" -testnet`) over name and description.
+    pub q: Option<String>,
+    #[serde(rename = "type")]
+    pub service_type: Option<String>,
+    /// Comma-separated; services must have all of them.
+    pub tags: Option<String>,
+    /// Price bounds and token that at least one pricing tier must match.
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    pub token: Option<String>,
+    /// Whether the provider is verified.
+    pub verified: Option<bool>,
+    /// Defaults to `active`.
+    pub status: Option<String>,
+    /// The `network` of the service's metadata.
+    pub network: Option<String>,
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// Position of the last result of a page, in search order: by rank, then
+/// name, then id.
+#[derive(Serialize, Deserialize)]
+struct SearchCursor {
+    rank: f32,
+    name: String,
+    id: uuid::Uuid,
+}
+
+impl SearchCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ServiceSearchResult {
+    #[serde(flatten)]
+    pub service: ServiceListResponse,
+    pub status: String,
+    pub provider_verified: bool,
+    /// Relevance to the text query; 0 without one.
+    pub rank: f32,
+}
+
+#[derive(Serialize, Default)]
+pub struct SearchFacets {
+    /// Number of matching services per service type and per tag.
+    pub types: BTreeMap<String, i64>,
+    pub tags: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<ServiceSearchResult>,
+    /// Counts over all matches, not just this page.
+    pub facets: SearchFacets,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+/// Searches services by text, ranked with `ts_rank`, and filters. Both
+/// queries filter the same way; the facets ignore the cursor.
 pub async fn search(
     State(state): State<AppState>,
-    Query(params): Query<serde_json::Value>,
-) -> impl IntoResponse {
-    Json(json!({ "results": [] }))
+    Query(params): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let text = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let tags: Option<Vec<String>> = params.tags.as_deref().map(|tags| {
+        tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string).collect()
+    });
+    let status = params.status.as_deref().unwrap_or("active");
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    if let (Some(min), Some(max)) = (params.min_price, params.max_price) {
+        if min > max {
+            return Err(ApiError::ValidationError("min_price is above max_price".to_string()));
+        }
+    }
+    let cursor = params.cursor.as_deref()
+        .map(|cursor| SearchCursor::decode(cursor).ok_or_else(|| ApiError::ValidationError("Invalid cursor".to_string())))
+        .transpose()?;
+
+    // One extra row tells whether there's a next page
+    let page = sqlx::query!(
+        r#"
+        SELECT m.id AS "id!", m.name AS "name!", m.description, m.service_type AS "service_type!", m.tags,
+               m.status, m.provider_name AS "provider_name!", m.verified, m.rank AS "rank!",
+               (SELECT MIN(pt.price_amount) FROM pricing_tiers pt WHERE pt.service_id = m.id) AS min_price
+        FROM (
+            SELECT s.id, s.name, s.description, s.service_type, s.tags, s.status,
+                   sp.name AS provider_name, sp.verified,
+                   (CASE WHEN $1::TEXT IS NULL THEN 0
+                         ELSE ts_rank(to_tsvector('english', s.name || ' ' || COALESCE(s.description, '')),
+                                      websearch_to_tsquery('english', $1))
+                    END)::REAL AS rank
+            FROM services s
+            JOIN service_providers sp ON s.provider_id = sp.id
+            WHERE ($1::TEXT IS NULL
+                   OR to_tsvector('english', s.name || ' ' || COALESCE(s.description, '')) @@ websearch_to_tsquery('english', $1))
+              AND ($2::TEXT IS NULL OR s.service_type = $2)
+              AND ($3::TEXT[] IS NULL OR s.tags @> $3)
+              AND s.status = $4
+              AND ($5::BOOLEAN IS NULL OR sp.verified = $5)
+              AND ($6::TEXT IS NULL OR s.metadata @> jsonb_build_object('network', $6::TEXT))
+              AND (($7::BIGINT IS NULL AND $8::BIGINT IS NULL AND $9::TEXT IS NULL) OR EXISTS (
+                  SELECT 1 FROM pricing_tiers pt
+                  WHERE pt.service_id = s.id
+                    AND ($7::BIGINT IS NULL OR pt.price_amount >= $7)
+                    AND ($8::BIGINT IS NULL OR pt.price_amount <= $8)
+                    AND ($9::TEXT IS NULL OR pt.price_token = $9)))
+        ) m
+        WHERE $10::REAL IS NULL OR m.rank < $10 OR (m.rank = $10 AND (m.name, m.id) > ($11, $12))
+        ORDER BY m.rank DESC, m.name, m.id
+        LIMIT $13
+        "#,
+        text,
+        params.service_type,
+        tags.as_deref(),
+        status,
+        params.verified,
+        params.network,
+        params.min_price,
+        params.max_price,
+        params.token,
+        cursor.as_ref().map(|c| c.rank),
+        cursor.as_ref().map(|c| c.name.clone()),
+        cursor.as_ref().map(|c| c.id),
+        limit + 1
+    )
+    .fetch_all(&state.db);
+
+    let facets = sqlx::query!(
+        r#"
+        WITH matches AS (
+            SELECT s.service_type, s.tags
+            FROM services s
+            JOIN service_providers sp ON s.provider_id = sp.id
+            WHERE ($1::TEXT IS NULL
+                   OR to_tsvector('english', s.name || ' ' || COALESCE(s.description, '')) @@ websearch_to_tsquery('english', $1))
+              AND ($2::TEXT IS NULL OR s.service_type = $2)
+              AND ($3::TEXT[] IS NULL OR s.tags @> $3)
+              AND s.status = $4
+              AND ($5::BOOLEAN IS NULL OR sp.verified = $5)
+              AND ($6::TEXT IS NULL OR s.metadata @> jsonb_build_object('network', $6::TEXT))
+              AND (($7::BIGINT IS NULL AND $8::BIGINT IS NULL AND $9::TEXT IS NULL) OR EXISTS (
+                  SELECT 1 FROM pricing_tiers pt
+                  WHERE pt.service_id = s.id
+                    AND ($7::BIGINT IS NULL OR pt.price_amount >= $7)
+                    AND ($8::BIGINT IS NULL OR pt.price_amount <= $8)
+                    AND ($9::TEXT IS NULL OR pt.price_token = $9)))
+        )
+        SELECT 'type' AS "facet!", service_type AS "value!", COUNT(*) AS "count!" FROM matches GROUP BY service_type
+        UNION ALL
+        SELECT 'tag', tag, COUNT(*) FROM matches, unnest(tags) AS tag GROUP BY tag
+        "#,
+        text,
+        params.service_type,
+        tags.as_deref(),
+        status,
+        params.verified,
+        params.network,
+        params.min_price,
+        params.max_price,
+        params.token
+    )
+    .fetch_all(&state.db);
+
+    let (mut rows, facet_rows) = tokio::try_join!(page, facets)?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| SearchCursor { rank: row.rank, name: row.name.clone(), id: row.id }.encode())
+    } else {
+        None
+    };
+
+    let mut facets = SearchFacets::default();
+    for row in facet_rows {
+        match row.facet.as_str() {
+            "type" => facets.types.insert(row.value, row.count),
+            _ => facets.tags.insert(row.value, row.count),
+        };
+    }
+    // Every service has exactly one type
+    let total = facets.types.values().sum();
+
+    let results = rows.into_iter().map(|row| ServiceSearchResult {
+        service: ServiceListResponse {
+            id: row.id.to_string(),
+            name: row.name,
+            provider: row.provider_name,
+            type_: row.service_type,
+            description: row.description.unwrap_or_default(),
+            price: row.min_price.unwrap_or(0) as f64,
+            tags: row.tags.unwrap_or_default(),
+        },
+        status: row.status.unwrap_or_default(),
+        provider_verified: row.verified.unwrap_or(false),
+        rank: row.rank,
+    }).collect();
+
+    Ok(Json(SearchResponse { results, facets, total, next_cursor }))
 }
 
 #[derive(Deserialize)]
@@ -163,7 +378,18 @@ pub struct CreateServiceRequest {
     pub service_type: String,
     pub tags: Vec<String>,
     pub tiers: Vec<CreateTierRequest>,
-    pub provider_id: Option<String>, // Should come from Auth token in real app
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Rejects metadata whose JSON-RPC cost table the gateway could not load.
+fn validate_metadata(metadata: &serde_json::Value) -> Result<(), String> {
+    if !metadata.is_object() {
+        return Err("metadata must be an object".to_string());
+    }
+    JsonRpcCostTable::from_metadata(metadata)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid jsonrpc_costs: {}", e))
 }
 
 #[derive(Deserialize)]
@@ -173,12 +399,37 @@ pub struct CreateTierRequest {
     pub requests: i32,
 }
 
+/// Checks that `user` may change the service: its provider or an admin.
+async fn authorize_service_change(state: &AppState, user: &AuthUser, service_id: uuid::Uuid) -> Result<(), ApiError> {
+    user.require_scope(ApiKeyScope::ServicesWrite)?;
+    let provider_id = sqlx::query_scalar!("SELECT provider_id FROM services WHERE id = $1", service_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if user.is_admin() || (provider_id.is_some() && provider_id == user.provider_id) {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden)
+    }
+}
+
 pub async fn create(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<CreateServiceRequest>,
 ) -> impl IntoResponse {
-    let provider_id = payload.provider_id.unwrap_or_else(|| "00000000-0000-0000-0000-000000000000".to_string()); // Mock Default
-    let provider_uuid = uuid::Uuid::parse_str(&provider_id).unwrap_or_default();
+    // Services are listed under the caller's own provider record
+    let Some(provider_uuid) = user.provider_id else {
+        return ApiError::Forbidden.into_response();
+    };
+    if let Err(e) = user.require_scope(ApiKeyScope::ServicesWrite) {
+        return e.into_response();
+    }
+    let metadata = payload.metadata.unwrap_or_else(|| json!({}));
+    if let Err(e) = validate_metadata(&metadata) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response();
+    }
 
     // Start transaction
     let mut tx = match state.db.begin().await {
@@ -189,15 +440,16 @@ pub async fn create(
     // Insert Service
     let service_id = match sqlx::query!(
         r#"
-        INSERT INTO services (provider_id, name, description, service_type, tags)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO services (provider_id, name, description, service_type, tags, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6)
         RETURNING id
         "#,
         provider_uuid,
         payload.name,
         payload.description,
         payload.service_type,
-        &payload.tags
+        &payload.tags,
+        metadata
     )
     .fetch_one(&mut *tx)
     .await {
@@ -240,18 +492,26 @@ pub async fn create(
 pub async fn update(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     let service_id = match uuid::Uuid::parse_str(&id) {
         Ok(uid) => uid,
         Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID"}))).into_response(),
     };
+    if let Err(e) = authorize_service_change(&state, &user, service_id).await {
+        return e.into_response();
+    }
 
     // Parse Update Check (Partial Update)
     let name = payload.get("name").and_then(|v| v.as_str());
     let description = payload.get("description").and_then(|v| v.as_str());
     let status = payload.get("status").and_then(|v| v.as_str());
     let service_type = payload.get("type").and_then(|v| v.as_str()); // Frontend sends 'type'
+    let metadata = payload.get("metadata").cloned();
+    if let Some(Err(e)) = metadata.as_ref().map(validate_metadata) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response();
+    }
 
     let result = sqlx::query!(
         r#"
@@ -261,14 +521,16 @@ pub async fn update(
             description = COALESCE($2, description),
             status = COALESCE($3, status),
             service_type = COALESCE($4, service_type),
+            metadata = COALESCE($5, metadata),
             updated_at = NOW()
-        WHERE id = $5
+        WHERE id = $6
         RETURNING id
         "#,
         name,
         description,
         status,
         service_type,
+        metadata,
         service_id
     )
     .fetch_optional(&state.db)
@@ -287,11 +549,15 @@ pub async fn update(
 pub async fn delete(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    user: AuthUser,
 ) -> impl IntoResponse {
     let service_id = match uuid::Uuid::parse_str(&id) {
         Ok(uid) => uid,
         Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID"}))).into_response(),
     };
+    if let Err(e) = authorize_service_change(&state, &user, service_id).await {
+        return e.into_response();
+    }
 
     // Soft delete (set status to 'archived') or Hard Delete?
     // Let's do Soft Delete for now as it's safer for infrastructure
@@ -0,0 +1,25 @@
+pub mod config;
+pub mod routes;
+pub mod handlers;
+pub mod models;
+pub mod db;
+pub mod auth;
+pub mod middleware;
+pub mod utils;
+pub mod validator;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: sqlx::PgPool,
+    pub jwt_secret: String,
+    /// Lifetime of access tokens, in seconds.
+    pub jwt_expiry: i64,
+    /// Lifetime of refresh tokens, in seconds.
+    pub refresh_expiry: i64,
+    /// Domain that Sign-In With Sui messages must name.
+    pub siws_domain: String,
+    /// Lifetime of Sign-In With Sui nonces, in seconds.
+    pub siws_nonce_ttl: i64,
+    pub redis_url: String,
+    pub validator: validator::ValidatorClient,
+}
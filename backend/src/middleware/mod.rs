@@ -1,19 +1,43 @@
 use axum::{
     middleware::Next,
-    response::Response,
-    extract::Request,
+    response::{IntoResponse, Response},
+    extract::{Request, State},
+    http::Method,
 };
 
+use crate::auth::{authenticate, AuthUser};
+use crate::models::api_key::ApiKeyScope;
+use crate::utils::errors::ApiError;
+use crate::AppState;
+
 pub mod auth {
     use super::*;
 
-    pub async fn require_auth(req: Request, next: Next) -> Response {
-        // TODO: Validate JWT
+    /// Rejects requests without a valid access token or API key, and passes
+    /// the caller on as an [`AuthUser`] extension. Read-only API keys may
+    /// only make GET requests.
+    pub async fn require_auth(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+        let user = match authenticate(&state, req.headers()).await {
+            Ok(user) => user,
+            Err(e) => return e.into_response(),
+        };
+
+        let is_read = matches!(*req.method(), Method::GET | Method::HEAD);
+        if !is_read && user.is_api_key() && user.has_scope(ApiKeyScope::ReadOnly) {
+            return ApiError::Forbidden.into_response();
+        }
+
+        req.extensions_mut().insert(user);
         next.run(req).await
     }
 
-    pub async fn require_admin(req: Request, next: Next) -> Response {
-        // TODO: Validate Admin Role
+    /// Rejects callers that aren't admins signed in with an access token;
+    /// API keys never reach admin routes, even an admin's. Goes inside
+    /// `require_auth`.
+    pub async fn require_admin(user: AuthUser, req: Request, next: Next) -> Response {
+        if !user.is_admin() || user.is_api_key() {
+            return ApiError::Forbidden.into_response();
+        }
         next.run(req).await
     }
 }
@@ -0,0 +1,50 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What an API key may do. Browser sessions aren't scoped and may do
+/// anything their role allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    /// Limits the key to GET requests, whatever its other scopes.
+    #[serde(rename = "read-only")]
+    ReadOnly,
+    /// Create, update and archive the provider's services.
+    #[serde(rename = "services:write")]
+    ServicesWrite,
+    /// Read the provider's dashboard stats.
+    #[serde(rename = "stats:read")]
+    StatsRead,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiKeyScope::ReadOnly => "read-only",
+            ApiKeyScope::ServicesWrite => "services:write",
+            ApiKeyScope::StatsRead => "stats:read",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read-only" => Some(ApiKeyScope::ReadOnly),
+            "services:write" => Some(ApiKeyScope::ServicesWrite),
+            "stats:read" => Some(ApiKeyScope::StatsRead),
+            _ => None,
+        }
+    }
+}
+
+/// An API key as its provider sees it: the key itself is only shown once,
+/// when it's created.
+#[derive(Debug, Serialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// The first characters of the key.
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
@@ -1,5 +1,6 @@
 pub mod user;
 pub mod service;
+pub mod api_key;
 
 use serde::{Deserialize, Serialize};
 
@@ -25,5 +25,29 @@ pub struct PricingTier {
     pub price_amount: i64,
     pub price_token: String,
     pub quota_requests: Option<i32>,
+    pub quota_period_days: Option<i32>,
+    pub rate_limit_per_second: Option<i32>,
     pub created_at: Option<DateTime<Utc>>,
 }
+
+impl PricingTier {
+    /// The tier as registered on-chain. Only SUI-priced tiers with a quota
+    /// and a validity period can be sold through the entitlements contract.
+    pub fn to_on_chain(&self) -> Result<inframint_types::PricingTier, String> {
+        if self.price_token != "SUI" {
+            return Err(format!("Tier {} is priced in {}, not SUI", self.tier_name, self.price_token));
+        }
+        let quota_requests = self.quota_requests
+            .ok_or_else(|| format!("Tier {} has no request quota", self.tier_name))?;
+        let quota_period_days = self.quota_period_days
+            .ok_or_else(|| format!("Tier {} has no quota period", self.tier_name))?;
+
+        Ok(inframint_types::PricingTier {
+            price_sui: u64::try_from(self.price_amount).map_err(|e| e.to_string())?,
+            quota_requests: u64::try_from(quota_requests).map_err(|e| e.to_string())?,
+            validity_period_ms: u64::try_from(quota_period_days).map_err(|e| e.to_string())? * 86_400_000,
+            rate_limit_per_second: u32::try_from(self.rate_limit_per_second.unwrap_or(0)).map_err(|e| e.to_string())?,
+            active: true,
+        })
+    }
+}
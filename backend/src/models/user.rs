@@ -1,8 +1,23 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What an account may do. Developers buy access to services, providers
+/// list their own, and admins may manage any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum Role {
+    Developer,
+    Provider,
+    Admin,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
-    pub id: String,
-    pub email: String,
-    pub role: String,
+    pub id: Uuid,
+    pub email: Option<String>,
+    pub wallet_address: Option<String>,
+    pub role: Role,
+    /// The provider record the account manages, if any.
+    pub provider_id: Option<Uuid>,
 }
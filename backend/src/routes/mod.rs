@@ -1,2 +1,61 @@
-// Routes are currently defined in main.rs, this file is a placeholder
-pub fn configure() {}
+use axum::{
+    routing::{get, post, put, delete},
+    Router,
+};
+use tower_http::cors::{CorsLayer, Any};
+
+use crate::{handlers, middleware, AppState};
+
+pub fn router(state: AppState) -> Router {
+    // Public Routes
+    let public_routes = Router::new()
+        .route("/health", get(handlers::health_check))
+        .route("/api/v1/auth/register", post(handlers::auth::register))
+        .route("/api/v1/auth/login", post(handlers::auth::login))
+        .route("/api/v1/auth/refresh", post(handlers::auth::refresh))
+        .route("/api/v1/auth/logout", post(handlers::auth::logout))
+        .route("/api/v1/auth/wallet/nonce", post(handlers::auth::wallet_nonce))
+        .route("/api/v1/auth/wallet/verify", post(handlers::auth::wallet_verify))
+        .route("/api/v1/services", get(handlers::services::list))
+        .route("/api/v1/services/:id", get(handlers::services::get))
+        .route("/api/v1/services/search", get(handlers::services::search))
+        .route("/api/v1/stats/global", get(handlers::stats::get_global_stats));
+
+    // Admin Routes
+    let admin_routes = Router::new()
+        .route("/api/v1/admin/providers", get(handlers::admin::list_providers))
+        .route_layer(axum::middleware::from_fn(middleware::auth::require_admin));
+
+    // Protected Routes
+    let protected_routes = Router::new()
+        .route("/api/v1/services", post(handlers::services::create))
+        .route("/api/v1/services/:id", put(handlers::services::update))
+        .route("/api/v1/services/:id", delete(handlers::services::delete))
+        .route("/api/v1/entitlements/validate", post(handlers::entitlements::validate_entitlement))
+        .route("/api/v1/entitlements/consume", post(handlers::entitlements::consume_entitlement))
+        .route("/api/v1/entitlements/signature", post(handlers::entitlements::validate_signature))
+        .route("/api/v1/api-keys", get(handlers::api_keys::list))
+        .route("/api/v1/api-keys", post(handlers::api_keys::create))
+        .route("/api/v1/api-keys/:id", delete(handlers::api_keys::revoke))
+        .route("/api/v1/stats/provider", get(handlers::stats::get_provider_stats))
+        .merge(admin_routes)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), middleware::auth::require_auth));
+
+    Router::new()
+        .merge(public_routes)
+        .merge(protected_routes)
+        // CORS
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods([
+                    axum::http::Method::GET,
+                    axum::http::Method::POST,
+                    axum::http::Method::PUT,
+                    axum::http::Method::DELETE,
+                    axum::http::Method::OPTIONS,
+                ])
+                .allow_headers(Any)
+        )
+        .with_state(state)
+}
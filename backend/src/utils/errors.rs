@@ -3,8 +3,11 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use inframint_types::proto::ErrorCode;
 use serde_json::json;
 
+use crate::validator::ValidatorClientError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error("Not found")]
@@ -19,6 +22,9 @@ pub enum ApiError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Database error: {0}")]
     DatabaseError(String),
 
@@ -28,6 +34,20 @@ pub enum ApiError {
     #[error("Quota exceeded")]
     QuotaExceeded,
 
+    #[error("Rate limit exceeded")]
+    RateLimited,
+
+    /// The entitlement exists but cannot be used, e.g. it expired.
+    #[error("Entitlement refused: {0}")]
+    EntitlementRefused(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// A dependency, such as the validator, failed in a way we can't explain.
+    #[error("Bad gateway: {0}")]
+    BadGateway(String),
+
     #[error("Internal server error")]
     InternalServerError,
 }
@@ -35,16 +55,21 @@ pub enum ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
-            ApiError::NotFound => (StatusCode::NOT_FOUND, "Not found"),
-            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            ApiError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            ApiError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden".to_string()),
             ApiError::ValidationError(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
             ApiError::DatabaseError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             ApiError::AuthError(msg) => (StatusCode::UNAUTHORIZED, msg),
-            ApiError::QuotaExceeded => (StatusCode::TOO_MANY_REQUESTS, "Quota exceeded"),
+            ApiError::QuotaExceeded => (StatusCode::TOO_MANY_REQUESTS, "Quota exceeded".to_string()),
+            ApiError::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()),
+            ApiError::EntitlementRefused(msg) => (StatusCode::FORBIDDEN, msg),
+            ApiError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            ApiError::BadGateway(msg) => (StatusCode::BAD_GATEWAY, msg),
             ApiError::InternalServerError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal server error",
+                "Internal server error".to_string(),
             ),
         };
 
@@ -70,3 +95,31 @@ impl From<jsonwebtoken::errors::Error> for ApiError {
         ApiError::AuthError(err.to_string())
     }
 }
+
+impl From<ValidatorClientError> for ApiError {
+    fn from(err: ValidatorClientError) -> Self {
+        match err {
+            ValidatorClientError::Refused { code, message } => match code {
+                ErrorCode::NotFound => ApiError::NotFound,
+                ErrorCode::QuotaExceeded => ApiError::QuotaExceeded,
+                ErrorCode::RateLimited => ApiError::RateLimited,
+                ErrorCode::BadSignature | ErrorCode::WrongService => ApiError::AuthError(message),
+                ErrorCode::UpstreamUnavailable => ApiError::ServiceUnavailable(message),
+                ErrorCode::Expired | ErrorCode::Inactive => ApiError::EntitlementRefused(message),
+                ErrorCode::Unspecified => {
+                    tracing::error!("Validator refused without a known error code: {}", message);
+                    ApiError::BadGateway(message)
+                }
+            },
+            ValidatorClientError::InvalidUrl(_) => ApiError::InternalServerError,
+            ValidatorClientError::Connect(e) => ApiError::ServiceUnavailable(e.to_string()),
+            ValidatorClientError::Call(status) if status.code() == tonic::Code::Unavailable => {
+                ApiError::ServiceUnavailable(status.message().to_string())
+            }
+            ValidatorClientError::Call(status) => {
+                tracing::error!("Validator call failed: {}", status);
+                ApiError::InternalServerError
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod errors;
+
 pub fn now() -> i64 {
     chrono::Utc::now().timestamp()
 }
@@ -1,30 +1,80 @@
+use tonic::codegen::http::uri::InvalidUri;
 use tonic::transport::Channel;
-use std::error::Error;
 use std::time::Duration;
-use tonic::Request;
-use tracing::{info, error, debug};
+use tonic::{Request, Status};
+use tracing::{info, debug};
 
-mod proto {
-    tonic::include_proto!("validator");
-}
-
-use proto::{
+use inframint_types::{error_code, Entitlement};
+use inframint_types::proto::{
     validator_service_client::ValidatorServiceClient,
-    ValidateEntitlementRequest, ValidateEntitlementResponse,
-    ConsumeEntitlementRequest, ConsumeEntitlementResponse,
-    ValidateSignatureRequest, ValidateSignatureResponse,
+    ValidateEntitlementRequest, ConsumeEntitlementRequest, ValidateSignatureRequest,
+    ValidateEntitlementResponse, ConsumeEntitlementResponse,
+    BatchValidateEntitlementsRequest, BatchConsumeEntitlementsRequest,
+    SignedRequest, ErrorCode,
 };
 
+#[derive(Debug, thiserror::Error)]
+pub enum ValidatorClientError {
+    #[error("Invalid validator URL: {0}")]
+    InvalidUrl(#[from] InvalidUri),
+
+    #[error("Could not connect to the validator: {0}")]
+    Connect(#[from] tonic::transport::Error),
+
+    /// The validator turned the request down for the reason in `code`.
+    #[error("{message}")]
+    Refused { code: ErrorCode, message: String },
+
+    /// A failed call whose status carries no error code. Boxed, as a
+    /// `Status` is several times the size of the other variants.
+    #[error("Validator call failed: {0}")]
+    Call(Box<Status>),
+}
+
+impl ValidatorClientError {
+    fn refused(code: i32, message: String) -> Self {
+        ValidatorClientError::Refused {
+            code: ErrorCode::try_from(code).unwrap_or(ErrorCode::Unspecified),
+            message,
+        }
+    }
+}
+
+/// The entitlement of a validation, or why it was refused.
+fn validated(response: ValidateEntitlementResponse) -> Result<Option<Entitlement>, ValidatorClientError> {
+    if !response.valid {
+        return Err(ValidatorClientError::refused(response.error_code, response.error));
+    }
+    Ok(response.entitlement.map(Entitlement::from))
+}
+
+/// The remaining quota after a consumption, or why it was refused.
+fn consumed(response: ConsumeEntitlementResponse) -> Result<u64, ValidatorClientError> {
+    if !response.success {
+        return Err(ValidatorClientError::refused(response.error_code, response.error));
+    }
+    Ok(response.remaining_quota)
+}
+
+impl From<Status> for ValidatorClientError {
+    fn from(status: Status) -> Self {
+        match error_code(&status) {
+            Some(code) => ValidatorClientError::Refused { code, message: status.message().to_string() },
+            None => ValidatorClientError::Call(Box::new(status)),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ValidatorClient {
     client: ValidatorServiceClient<Channel>,
 }
 
 impl ValidatorClient {
-    pub async fn new(validator_url: &str) -> Result<Self, Box<dyn Error>> {
+    pub async fn new(validator_url: &str) -> Result<Self, ValidatorClientError> {
         info!("🔗 Connecting to validator service at {}", validator_url);
 
-        let channel = Channel::from_shared(validator_url.to_string())
+        let channel = Channel::from_shared(validator_url.to_string())?
             .timeout(Duration::from_secs(5))
             .connect()
             .await?;
@@ -34,24 +84,42 @@ impl ValidatorClient {
         })
     }
 
+    /// Like `new`, but connects on first use, so the validator may start
+    /// later.
+    pub fn connect_lazy(validator_url: &str) -> Result<Self, ValidatorClientError> {
+        let channel = Channel::from_shared(validator_url.to_string())?
+            .timeout(Duration::from_secs(5))
+            .connect_lazy();
+
+        Ok(Self {
+            client: ValidatorServiceClient::new(channel),
+        })
+    }
+
+    /// Returns the entitlement if it is valid and the validator reported it.
+    /// An entitlement that cannot be used is `Err(Refused { .. })`.
     pub async fn validate_entitlement(
         &self,
         entitlement_id: &str,
         signature: &str,
         message: &str,
-    ) -> Result<bool, Box<dyn Error>> {
+        signed_request: Option<SignedRequest>,
+    ) -> Result<Option<Entitlement>, ValidatorClientError> {
         debug!("Validating entitlement: {}", entitlement_id);
 
         let request = Request::new(ValidateEntitlementRequest {
             entitlement_id: entitlement_id.to_string(),
             signature: signature.to_string(),
             message: message.to_string(),
+            signed_request,
+            access_token: String::new(),
+            service_id: String::new(),
         });
 
         let mut client = self.client.clone();
-        let response = client.validate_entitlement(request).await?;
+        let response = client.validate_entitlement(request).await?.into_inner();
 
-        Ok(response.into_inner().valid)
+        validated(response)
     }
 
     pub async fn consume_entitlement(
@@ -60,7 +128,8 @@ impl ValidatorClient {
         amount: u64,
         signature: &str,
         message: &str,
-    ) -> Result<u64, Box<dyn Error>> {
+        signed_request: Option<SignedRequest>,
+    ) -> Result<u64, ValidatorClientError> {
         debug!("Consuming {} from entitlement: {}", amount, entitlement_id);
 
         let request = Request::new(ConsumeEntitlementRequest {
@@ -68,17 +137,13 @@ impl ValidatorClient {
             amount,
             signature: signature.to_string(),
             message: message.to_string(),
+            signed_request,
         });
 
         let mut client = self.client.clone();
         let response = client.consume_entitlement(request).await?;
 
-        let result = response.into_inner();
-        if !result.success {
-            return Err(format!("Failed to consume entitlement: {}", result.error).into());
-        }
-
-        Ok(result.remaining_quota)
+        consumed(response.into_inner())
     }
 
     pub async fn validate_signature(
@@ -86,7 +151,7 @@ impl ValidatorClient {
         entitlement_id: &str,
         signature: &str,
         message: &str,
-    ) -> Result<bool, Box<dyn Error>> {
+    ) -> Result<bool, ValidatorClientError> {
         debug!("Validating signature for entitlement: {}", entitlement_id);
 
         let request = Request::new(ValidateSignatureRequest {
@@ -100,4 +165,36 @@ impl ValidatorClient {
 
         Ok(response.into_inner().valid)
     }
+
+    /// Validates several entitlements in one call. Results are in request
+    /// order; the outer error is for the batch as a whole.
+    pub async fn batch_validate_entitlements(
+        &self,
+        requests: Vec<ValidateEntitlementRequest>,
+    ) -> Result<Vec<Result<Option<Entitlement>, ValidatorClientError>>, ValidatorClientError> {
+        debug!("Validating a batch of {} entitlements", requests.len());
+
+        let mut client = self.client.clone();
+        let response = client
+            .batch_validate_entitlements(Request::new(BatchValidateEntitlementsRequest { requests }))
+            .await?;
+
+        Ok(response.into_inner().responses.into_iter().map(validated).collect())
+    }
+
+    /// Consumes from several entitlements in one call. Results are in
+    /// request order; the outer error is for the batch as a whole.
+    pub async fn batch_consume_entitlements(
+        &self,
+        requests: Vec<ConsumeEntitlementRequest>,
+    ) -> Result<Vec<Result<u64, ValidatorClientError>>, ValidatorClientError> {
+        debug!("Consuming from a batch of {} entitlements", requests.len());
+
+        let mut client = self.client.clone();
+        let response = client
+            .batch_consume_entitlements(Request::new(BatchConsumeEntitlementsRequest { requests }))
+            .await?;
+
+        Ok(response.into_inner().responses.into_iter().map(consumed).collect())
+    }
 }
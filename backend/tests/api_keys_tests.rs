@@ -0,0 +1,298 @@
+//! Runs against the Postgres at `DATABASE_URL`; each test gets a fresh,
+//! migrated database.
+
+use inframint_backend::{
+    auth::{issue_access_token, token_hash},
+    models::user::{Role, User},
+    routes,
+    validator::ValidatorClient,
+    AppState,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const JWT_SECRET: &str = "test-secret";
+
+/// Serves the API on a free port and returns its base URL.
+async fn serve(db: PgPool) -> String {
+    let state = AppState {
+        db,
+        jwt_secret: JWT_SECRET.to_string(),
+        jwt_expiry: 900,
+        refresh_expiry: 3600,
+        siws_domain: "inframint.test".to_string(),
+        siws_nonce_ttl: 300,
+        redis_url: "redis://localhost:6379".to_string(),
+        validator: ValidatorClient::connect_lazy("http://localhost:50051").unwrap(),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, routes::router(state)).await.unwrap() });
+    url
+}
+
+/// Creates a wallet account with its provider record and returns the
+/// provider's id and an access token of the account.
+async fn provider_session(db: &PgPool, wallet: &str) -> (Uuid, String) {
+    let wallet_address = format!("0x{:0>64}", wallet);
+    let user_id: Uuid = sqlx::query_scalar("INSERT INTO users (wallet_address, role) VALUES ($1, 'provider') RETURNING id")
+        .bind(&wallet_address)
+        .fetch_one(db)
+        .await
+        .unwrap();
+    let provider_id: Uuid = sqlx::query_scalar("INSERT INTO service_providers (name, wallet_address, user_id) VALUES ($1, $1, $2) RETURNING id")
+        .bind(&wallet_address)
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+        .unwrap();
+
+    let user = User {
+        id: user_id,
+        email: None,
+        wallet_address: Some(wallet_address),
+        role: Role::Provider,
+        provider_id: Some(provider_id),
+    };
+    (provider_id, issue_access_token(&user, JWT_SECRET, 900).unwrap())
+}
+
+async fn create_key(api: &str, token: &str, scopes: Value) -> (StatusCode, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/api-keys", api))
+        .bearer_auth(token)
+        .json(&json!({ "name": "CI", "scopes": scopes }))
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+fn service_body() -> Value {
+    json!({
+        "name": "Acme RPC",
+        "description": "Mainnet fullnodes",
+        "service_type": "rpc",
+        "tags": [],
+        "tiers": [],
+    })
+}
+
+#[sqlx::test]
+async fn test_create_list_and_revoke_api_key(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let (_, token) = provider_session(&db, "c1").await;
+    let (_, other_token) = provider_session(&db, "c2").await;
+    let client = reqwest::Client::new();
+
+    let (status, created) = create_key(&api, &token, json!(["services:write", "stats:read"])).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with("imk_"));
+    assert_eq!(created["prefix"], &key[..12]);
+
+    let stored_hash: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE created_by IS NOT NULL")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(stored_hash.len(), 64);
+    assert!(!stored_hash.contains(&key[4..]));
+
+    let listed: Value = client.get(format!("{}/api/v1/api-keys", api))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let keys = listed["api_keys"].as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0]["prefix"], &key[..12]);
+    assert_eq!(keys[0]["scopes"], json!(["services:write", "stats:read"]));
+    assert!(keys[0].get("key").is_none());
+
+    // Another provider can neither see nor revoke it
+    let url = format!("{}/api/v1/api-keys/{}", api, created["id"].as_str().unwrap());
+    let response = client.delete(&url).bearer_auth(&other_token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.delete(&url).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = client.delete(&url).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.post(format!("{}/api/v1/services", api)).bearer_auth(key).json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_api_key_publishes_service_updates(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let (provider_id, token) = provider_session(&db, "c1").await;
+    let (_, created) = create_key(&api, &token, json!(["services:write"])).await;
+    let key = created["key"].as_str().unwrap();
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/api/v1/services", api)).bearer_auth(key).json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let service_id = response.json::<Value>().await.unwrap()["service_id"].as_str().unwrap().to_string();
+
+    let response = client.put(format!("{}/api/v1/services/{}", api, service_id))
+        .bearer_auth(key)
+        .json(&json!({ "description": "Mainnet and testnet fullnodes" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let owner: Option<Uuid> = sqlx::query_scalar("SELECT provider_id FROM services WHERE id = $1::uuid")
+        .bind(&service_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(owner, Some(provider_id));
+
+    let last_used: Option<chrono::NaiveDateTime> = sqlx::query_scalar("SELECT last_used_at FROM api_keys WHERE created_by IS NOT NULL")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert!(last_used.is_some());
+
+    let response = client.post(format!("{}/api/v1/services", api)).bearer_auth("imk_unknown").json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_api_key_scopes(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let (provider_id, token) = provider_session(&db, "c1").await;
+    let client = reqwest::Client::new();
+    let stats_url = format!("{}/api/v1/stats/provider", api);
+    let services_url = format!("{}/api/v1/services", api);
+
+    // Stats are only ever the caller's own
+    let response = client.get(format!("{}?provider_id={}", stats_url, provider_id)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let (_, created) = create_key(&api, &token, json!(["stats:read"])).await;
+    let stats_key = created["key"].as_str().unwrap().to_string();
+    let response = client.get(&stats_url).bearer_auth(&stats_key).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().await.unwrap()["total_services"], 0);
+    let response = client.post(&services_url).bearer_auth(&stats_key).json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let (_, created) = create_key(&api, &token, json!(["services:write"])).await;
+    let write_key = created["key"].as_str().unwrap().to_string();
+    let response = client.get(&stats_url).bearer_auth(&write_key).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // read-only overrides the key's other scopes
+    let (_, created) = create_key(&api, &token, json!(["read-only", "services:write"])).await;
+    let read_only_key = created["key"].as_str().unwrap().to_string();
+    let response = client.post(&services_url).bearer_auth(&read_only_key).json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Keys can't manage keys
+    let response = client.get(format!("{}/api/v1/api-keys", api)).bearer_auth(&write_key).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_create_api_key_requires_provider_and_scopes(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let (_, token) = provider_session(&db, "c1").await;
+    let developer = User {
+        id: Uuid::new_v4(),
+        email: Some("dev@example.com".to_string()),
+        wallet_address: None,
+        role: Role::Developer,
+        provider_id: None,
+    };
+    let developer_token = issue_access_token(&developer, JWT_SECRET, 900).unwrap();
+
+    let (status, _) = create_key(&api, &developer_token, json!(["stats:read"])).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = create_key(&api, &token, json!([])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = create_key(&api, &token, json!(["services:delete"])).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+/// Stores a key for `provider_id` as `created_by` (if any) would have, with
+/// every scope.
+async fn insert_key(db: &PgPool, provider_id: Uuid, created_by: Option<Uuid>, key: &str) {
+    sqlx::query("INSERT INTO api_keys (provider_id, created_by, name, prefix, key_hash, scopes) VALUES ($1, $2, 'CI', 'ci', $3, ARRAY['services:write', 'stats:read'])")
+        .bind(provider_id)
+        .bind(created_by)
+        .bind(token_hash(key))
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn test_api_key_acts_with_creators_role(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let (provider_id, _) = provider_session(&db, "c1").await;
+    let (other_provider_id, _) = provider_session(&db, "c2").await;
+    let admin_id: Uuid = sqlx::query_scalar("INSERT INTO users (wallet_address, role) VALUES ($1, 'admin') RETURNING id")
+        .bind(format!("0x{:0>64}", "c3"))
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    let provider_user_id: Uuid = sqlx::query_scalar("SELECT user_id FROM service_providers WHERE id = $1")
+        .bind(provider_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    insert_key(&db, provider_id, Some(admin_id), "imk_admin").await;
+    insert_key(&db, provider_id, Some(provider_user_id), "imk_provider").await;
+
+    let service_id: Uuid = sqlx::query_scalar("INSERT INTO services (provider_id, name, service_type) VALUES ($1, 'Acme RPC', 'rpc') RETURNING id")
+        .bind(other_provider_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    let url = format!("{}/api/v1/services/{}", api, service_id);
+    let client = reqwest::Client::new();
+
+    let response = client.put(&url).bearer_auth("imk_provider").json(&json!({ "name": "Mine now" })).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client.put(&url).bearer_auth("imk_admin").json(&json!({ "name": "Acme RPC v2" })).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Admin routes take a signed-in admin, not a key
+    let response = client.get(format!("{}/api/v1/admin/providers", api)).bearer_auth("imk_admin").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_carried_over_api_key_acts_as_its_provider(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let provider_id: Uuid = sqlx::query_scalar("INSERT INTO service_providers (name, wallet_address) VALUES ('Acme', 'Acme') RETURNING id")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    // Keys from before api_keys have no prefix and no creator
+    insert_key(&db, provider_id, None, "legacy-secret").await;
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/api/v1/services", api)).bearer_auth("legacy-secret").json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let service_id = response.json::<Value>().await.unwrap()["service_id"].as_str().unwrap().to_string();
+    let owner: Option<Uuid> = sqlx::query_scalar("SELECT provider_id FROM services WHERE id = $1::uuid")
+        .bind(&service_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(owner, Some(provider_id));
+
+    let response = client.post(format!("{}/api/v1/services", api)).bearer_auth("not-a-key").json(&service_body()).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,270 @@
+//! Runs against the Postgres at `DATABASE_URL`; each test gets a fresh,
+//! migrated database.
+
+use inframint_backend::{routes, validator::ValidatorClient, AppState};
+use inframint_types::signature::{SignatureScheme, SuiKeyPair};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const SIWS_DOMAIN: &str = "inframint.test";
+
+/// Serves the API on a free port and returns its base URL.
+async fn serve(db: PgPool) -> String {
+    let state = AppState {
+        db,
+        jwt_secret: "test-secret".to_string(),
+        jwt_expiry: 900,
+        refresh_expiry: 3600,
+        siws_domain: SIWS_DOMAIN.to_string(),
+        siws_nonce_ttl: 300,
+        redis_url: "redis://localhost:6379".to_string(),
+        validator: ValidatorClient::connect_lazy("http://localhost:50051").unwrap(),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, routes::router(state)).await.unwrap() });
+    url
+}
+
+async fn post(url: &str, body: Value) -> (StatusCode, Value) {
+    let response = reqwest::Client::new().post(url).json(&body).send().await.unwrap();
+    let status = response.status();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+async fn register(api: &str, email: &str) -> Value {
+    let (status, session) = post(
+        &format!("{}/api/v1/auth/register", api),
+        json!({ "email": email, "password": "correct horse battery" }),
+    ).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", session);
+    session
+}
+
+/// Requests a sign-in message for `keypair`'s wallet and returns it with its
+/// signature.
+async fn wallet_challenge(api: &str, keypair: &SuiKeyPair) -> (String, String) {
+    let (status, challenge) = post(
+        &format!("{}/api/v1/auth/wallet/nonce", api),
+        json!({ "address": keypair.address() }),
+    ).await;
+    assert_eq!(status, StatusCode::OK, "{}", challenge);
+
+    let message = challenge["message"].as_str().unwrap().to_string();
+    let signature = keypair.sign_personal_message(message.as_bytes()).to_base64();
+    (message, signature)
+}
+
+async fn wallet_verify(api: &str, message: &str, signature: &str) -> (StatusCode, Value) {
+    post(
+        &format!("{}/api/v1/auth/wallet/verify", api),
+        json!({ "message": message, "signature": signature }),
+    ).await
+}
+
+#[sqlx::test]
+async fn test_register_and_login(db: PgPool) {
+    let api = serve(db.clone()).await;
+
+    let session = register(&api, "Alice@Example.com").await;
+    assert_eq!(session["user"]["email"], "alice@example.com");
+    assert_eq!(session["user"]["role"], "developer");
+    assert_eq!(session["token_type"], "Bearer");
+
+    let password_hash: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE email = 'alice@example.com'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert!(password_hash.starts_with("$argon2id$"));
+
+    let (status, _) = post(
+        &format!("{}/api/v1/auth/register", api),
+        json!({ "email": "alice@example.com", "password": "another password" }),
+    ).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let login = format!("{}/api/v1/auth/login", api);
+    let (status, _) = post(&login, json!({ "email": "alice@example.com", "password": "wrong password" })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post(&login, json!({ "email": "bob@example.com", "password": "correct horse battery" })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, session) = post(&login, json!({ "email": "alice@example.com", "password": "correct horse battery" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(session["user"]["email"], "alice@example.com");
+    assert!(session["refresh_token"].is_string());
+}
+
+#[sqlx::test]
+async fn test_register_rejects_invalid_credentials(db: PgPool) {
+    let api = serve(db).await;
+    let url = format!("{}/api/v1/auth/register", api);
+
+    let (status, _) = post(&url, json!({ "email": "not-an-email", "password": "correct horse battery" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post(&url, json!({ "email": "alice@example.com", "password": "short" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn test_protected_routes_require_access_token(db: PgPool) {
+    let api = serve(db).await;
+    let url = format!("{}/api/v1/admin/providers", api);
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.get(&url).bearer_auth("not-a-jwt").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Past authentication, on to the role check
+    let session = register(&api, "alice@example.com").await;
+    let access_token = session["access_token"].as_str().unwrap();
+    let response = client.get(&url).bearer_auth(access_token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_refresh_rotates_tokens(db: PgPool) {
+    let api = serve(db).await;
+    let url = format!("{}/api/v1/auth/refresh", api);
+    let session = register(&api, "alice@example.com").await;
+    let first = session["refresh_token"].clone();
+
+    let (status, rotated) = post(&url, json!({ "refresh_token": first })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rotated["user"]["email"], "alice@example.com");
+    assert_ne!(rotated["refresh_token"], first);
+    let second = rotated["refresh_token"].clone();
+
+    // Reusing a rotated token revokes the tokens issued after it
+    let (status, _) = post(&url, json!({ "refresh_token": first })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post(&url, json!({ "refresh_token": second })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = post(&url, json!({ "refresh_token": "unknown" })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_logout_revokes_refresh_token(db: PgPool) {
+    let api = serve(db).await;
+    let first = register(&api, "alice@example.com").await["refresh_token"].clone();
+    let second = register(&api, "bob@example.com").await["refresh_token"].clone();
+
+    let (status, _) = post(&format!("{}/api/v1/auth/logout", api), json!({ "refresh_token": first })).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let url = format!("{}/api/v1/auth/refresh", api);
+    let (status, _) = post(&url, json!({ "refresh_token": first })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Other sessions are unaffected
+    let (status, _) = post(&url, json!({ "refresh_token": second })).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_wallet_sign_in_creates_account_and_provider(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+
+    let (message, signature) = wallet_challenge(&api, &keypair).await;
+    assert!(message.starts_with("inframint.test wants you to sign in with your Sui account:\n"));
+    assert!(message.contains(&keypair.address()));
+
+    let (status, session) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::OK, "{}", session);
+    assert_eq!(session["user"]["wallet_address"], keypair.address().as_str());
+    assert_eq!(session["user"]["email"], Value::Null);
+    assert_eq!(session["user"]["role"], "provider");
+
+    let user_id: Uuid = session["user"]["id"].as_str().unwrap().parse().unwrap();
+    let (provider_id, provider_user_id): (Uuid, Option<Uuid>) =
+        sqlx::query_as("SELECT id, user_id FROM service_providers WHERE wallet_address = $1")
+            .bind(keypair.address())
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(provider_user_id, Some(user_id));
+    assert_eq!(session["user"]["provider_id"], provider_id.to_string());
+
+    // Signing in again reuses the account
+    let (message, signature) = wallet_challenge(&api, &keypair).await;
+    let (status, session) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(session["user"]["id"], user_id.to_string());
+}
+
+#[sqlx::test]
+async fn test_wallet_sign_in_links_existing_provider(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &[0x22; 32]).unwrap();
+    sqlx::query("INSERT INTO service_providers (name, email, wallet_address) VALUES ('Acme RPC', 'ops@acme.test', $1)")
+        .bind(keypair.address())
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let (message, signature) = wallet_challenge(&api, &keypair).await;
+    let (status, session) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::OK, "{}", session);
+
+    let (name, user_id): (String, Option<Uuid>) = sqlx::query_as("SELECT name, user_id FROM service_providers WHERE wallet_address = $1")
+        .bind(keypair.address())
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(name, "Acme RPC");
+    assert_eq!(user_id.map(|id| id.to_string()), session["user"]["id"].as_str().map(str::to_string));
+}
+
+#[sqlx::test]
+async fn test_wallet_nonce_is_single_use(db: PgPool) {
+    let api = serve(db).await;
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+    let (message, signature) = wallet_challenge(&api, &keypair).await;
+
+    let (status, _) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_wallet_sign_in_rejects_bad_signatures(db: PgPool) {
+    let api = serve(db).await;
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+    let stranger = SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256r1, &[0x33; 32]).unwrap();
+    let (message, signature) = wallet_challenge(&api, &keypair).await;
+
+    // Signed by another wallet
+    let forged = stranger.sign_personal_message(message.as_bytes()).to_base64();
+    let (status, _) = wallet_verify(&api, &message, &forged).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Signed, but for another site
+    let phished = message.replacen(SIWS_DOMAIN, "evil.test", 1);
+    let phished_signature = keypair.sign_personal_message(phished.as_bytes()).to_base64();
+    let (status, _) = wallet_verify(&api, &phished, &phished_signature).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Altered after signing
+    let expiration = message.rfind("Expiration Time: ").unwrap();
+    let extended = format!("{}Expiration Time: 2099-01-01T00:00:00Z", &message[..expiration]);
+    let (status, _) = wallet_verify(&api, &extended, &signature).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = wallet_verify(&api, "Sign in please", &signature).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post(&format!("{}/api/v1/auth/wallet/nonce", api), json!({ "address": "alice" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The nonce is still usable by its wallet
+    let (status, _) = wallet_verify(&api, &message, &signature).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,277 @@
+//! Runs against the Postgres at `DATABASE_URL`; each test gets a fresh,
+//! migrated database.
+
+use inframint_backend::{
+    auth::issue_access_token,
+    models::user::{Role, User},
+    routes,
+    validator::ValidatorClient,
+    AppState,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const JWT_SECRET: &str = "test-secret";
+
+/// Serves the API on a free port and returns its base URL.
+async fn serve(db: PgPool) -> String {
+    let state = AppState {
+        db,
+        jwt_secret: JWT_SECRET.to_string(),
+        jwt_expiry: 900,
+        refresh_expiry: 3600,
+        siws_domain: "inframint.test".to_string(),
+        siws_nonce_ttl: 300,
+        redis_url: "redis://localhost:6379".to_string(),
+        validator: ValidatorClient::connect_lazy("http://localhost:50051").unwrap(),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, routes::router(state)).await.unwrap() });
+    url
+}
+
+fn access_token(role: Role, provider_id: Option<Uuid>) -> String {
+    let user = User {
+        id: Uuid::new_v4(),
+        email: None,
+        wallet_address: None,
+        role,
+        provider_id,
+    };
+    issue_access_token(&user, JWT_SECRET, 900).unwrap()
+}
+
+async fn insert_provider(db: &PgPool, wallet: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO service_providers (name, wallet_address) VALUES ($1, $1) RETURNING id")
+        .bind(format!("0x{:0>64}", wallet))
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+async fn insert_service(db: &PgPool, provider_id: Uuid) -> Uuid {
+    sqlx::query_scalar("INSERT INTO services (provider_id, name, service_type) VALUES ($1, 'Acme RPC', 'rpc') RETURNING id")
+        .bind(provider_id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+/// Adds two listings of an unverified provider next to the two seeded ones
+/// (Sui Mainnet RPC and Ethereum Indexer).
+async fn insert_search_fixtures(db: &PgPool) {
+    let provider_id = insert_provider(db, "a1").await;
+    let listings = [
+        ("Sui Testnet RPC", "Free RPC for the Sui testnet.", "rpc", vec!["sui", "testnet", "rpc"], "testnet", 10, "USDC"),
+        ("Walrus Storage", "Blob storage on Sui.", "storage", vec!["sui", "storage"], "mainnet", 500, "SUI"),
+    ];
+
+    for (name, description, service_type, tags, network, price, token) in listings {
+        let service_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO services (provider_id, name, description, service_type, tags, metadata) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+        )
+        .bind(provider_id)
+        .bind(name)
+        .bind(description)
+        .bind(service_type)
+        .bind(tags)
+        .bind(json!({ "network": network }))
+        .fetch_one(db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO pricing_tiers (service_id, tier_name, price_amount, price_token) VALUES ($1, 'Basic', $2, $3)")
+            .bind(service_id)
+            .bind(price as i64)
+            .bind(token)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+}
+
+async fn search(api: &str, query: &str) -> Value {
+    let response = reqwest::get(format!("{}/api/v1/services/search?{}", api, query)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "{}", query);
+    response.json().await.unwrap()
+}
+
+fn names(results: &Value) -> Vec<&str> {
+    let mut names: Vec<&str> = results["results"].as_array().unwrap().iter().map(|r| r["name"].as_str().unwrap()).collect();
+    names.sort_unstable();
+    names
+}
+
+#[sqlx::test]
+async fn test_create_service_lists_it_under_callers_provider(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let provider_id = insert_provider(&db, "a1").await;
+    let url = format!("{}/api/v1/services", api);
+    let client = reqwest::Client::new();
+    // A provider_id in the body is not honoured
+    let body = json!({
+        "name": "Acme RPC",
+        "description": "Mainnet fullnodes",
+        "service_type": "rpc",
+        "tags": ["mainnet"],
+        "tiers": [{ "name": "Basic", "price": 1000, "requests": 10000 }],
+        "provider_id": "00000000-0000-0000-0000-000000000000",
+    });
+
+    let response = client.post(&url).json(&body).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.post(&url).bearer_auth(access_token(Role::Developer, None)).json(&body).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client.post(&url)
+        .bearer_auth(access_token(Role::Provider, Some(provider_id)))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let created: serde_json::Value = response.json().await.unwrap();
+    let service_id: Uuid = created["service_id"].as_str().unwrap().parse().unwrap();
+
+    let owner: Option<Uuid> = sqlx::query_scalar("SELECT provider_id FROM services WHERE id = $1")
+        .bind(service_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(owner, Some(provider_id));
+}
+
+#[sqlx::test]
+async fn test_only_owner_or_admin_changes_service(db: PgPool) {
+    let api = serve(db.clone()).await;
+    let owner = insert_provider(&db, "a1").await;
+    let stranger = insert_provider(&db, "a2").await;
+    let service_id = insert_service(&db, owner).await;
+    let url = format!("{}/api/v1/services/{}", api, service_id);
+    let client = reqwest::Client::new();
+
+    let stranger_token = access_token(Role::Provider, Some(stranger));
+    let response = client.put(&url).bearer_auth(&stranger_token).json(&json!({ "name": "Mine now" })).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client.delete(&url).bearer_auth(&stranger_token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client.put(&url)
+        .bearer_auth(access_token(Role::Provider, Some(owner)))
+        .json(&json!({ "name": "Acme RPC v2" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.delete(&url).bearer_auth(access_token(Role::Admin, None)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (name, status): (String, Option<String>) = sqlx::query_as("SELECT name, status FROM services WHERE id = $1")
+        .bind(service_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(name, "Acme RPC v2");
+    assert_eq!(status.as_deref(), Some("archived"));
+
+    let response = client.delete(format!("{}/api/v1/services/{}", api, Uuid::new_v4()))
+        .bearer_auth(access_token(Role::Admin, None))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_admin_routes_require_admin_role(db: PgPool) {
+    let api = serve(db).await;
+    let url = format!("{}/api/v1/admin/providers", api);
+    let client = reqwest::Client::new();
+
+    for role in [Role::Developer, Role::Provider] {
+        let response = client.get(&url).bearer_auth(access_token(role, None)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    let response = client.get(&url).bearer_auth(access_token(Role::Admin, None)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_search_ranks_text_matches(db: PgPool) {
+    let api = serve(db.clone()).await;
+    insert_search_fixtures(&db).await;
+
+    let results = search(&api, "q=sui%20rpc").await;
+    assert_eq!(names(&results), ["Sui Mainnet RPC", "Sui Testnet RPC"]);
+    let ranks: Vec<f64> = results["results"].as_array().unwrap().iter().map(|r| r["rank"].as_f64().unwrap()).collect();
+    assert!(ranks.iter().all(|rank| *rank > 0.0));
+    assert!(ranks.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    let results = search(&api, "q=blob").await;
+    assert_eq!(names(&results), ["Walrus Storage"]);
+    assert_eq!(results["results"][0]["provider_verified"], false);
+    assert_eq!(results["results"][0]["price"], 500.0);
+
+    let results = search(&api, "q=solana").await;
+    assert_eq!(results["total"], 0);
+    assert_eq!(results["facets"], json!({ "types": {}, "tags": {} }));
+}
+
+#[sqlx::test]
+async fn test_search_filters(db: PgPool) {
+    let api = serve(db.clone()).await;
+    insert_search_fixtures(&db).await;
+
+    assert_eq!(names(&search(&api, "type=rpc").await), ["Sui Mainnet RPC", "Sui Testnet RPC"]);
+    assert_eq!(names(&search(&api, "tags=sui,testnet").await), ["Sui Testnet RPC"]);
+    assert_eq!(names(&search(&api, "min_price=100&max_price=160").await), ["Sui Mainnet RPC"]);
+    assert_eq!(names(&search(&api, "token=USDC").await), ["Sui Testnet RPC"]);
+    assert_eq!(names(&search(&api, "min_price=400&token=SUI").await), ["Walrus Storage"]);
+    assert_eq!(names(&search(&api, "verified=false").await), ["Sui Testnet RPC", "Walrus Storage"]);
+    assert_eq!(names(&search(&api, "network=mainnet").await), ["Walrus Storage"]);
+    assert_eq!(names(&search(&api, "q=sui&type=rpc&verified=true").await), ["Sui Mainnet RPC"]);
+
+    sqlx::query("UPDATE services SET status = 'archived' WHERE name = 'Ethereum Indexer'")
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(names(&search(&api, "type=indexer").await), Vec::<&str>::new());
+    assert_eq!(names(&search(&api, "status=archived").await), ["Ethereum Indexer"]);
+
+    let response = reqwest::get(format!("{}/api/v1/services/search?min_price=10&max_price=5", api)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn test_search_facets_and_pagination(db: PgPool) {
+    let api = serve(db.clone()).await;
+    insert_search_fixtures(&db).await;
+
+    let first = search(&api, "limit=3").await;
+    assert_eq!(first["total"], 4);
+    assert_eq!(first["facets"]["types"], json!({ "indexer": 1, "rpc": 2, "storage": 1 }));
+    assert_eq!(first["facets"]["tags"]["sui"], 3);
+    assert_eq!(first["facets"]["tags"]["testnet"], 1);
+    assert_eq!(first["results"].as_array().unwrap().len(), 3);
+
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = search(&api, &format!("limit=3&cursor={}", cursor)).await;
+    assert_eq!(second["results"].as_array().unwrap().len(), 1);
+    assert_eq!(second["next_cursor"], Value::Null);
+    assert_eq!(second["total"], 4);
+
+    // Without a text query, results come by name
+    let all: Vec<&str> = first["results"].as_array().unwrap().iter()
+        .chain(second["results"].as_array().unwrap())
+        .map(|r| r["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(all, ["Ethereum Indexer", "Sui Mainnet RPC", "Sui Testnet RPC", "Walrus Storage"]);
+
+    let response = reqwest::get(format!("{}/api/v1/services/search?cursor=not-a-cursor", api)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
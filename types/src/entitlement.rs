@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::proto;
+use crate::time::UnixMillis;
+
+/// An `inframint::entitlements::Entitlement` object.
+///
+/// `id` and `buyer` are `0x`-prefixed hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entitlement {
+    pub id: String,
+    /// See [`service_id_from_bytes`](crate::service_id_from_bytes).
+    pub service_id: String,
+    pub buyer: String,
+    pub tier_id: u64,
+    pub quota_requests: u64,
+    pub quota_used: u64,
+    pub purchased_at: UnixMillis,
+    pub expires_at: UnixMillis,
+    pub active: bool,
+}
+
+impl Entitlement {
+    pub fn remaining_quota(&self) -> u64 {
+        self.quota_requests.saturating_sub(self.quota_used)
+    }
+
+    /// Whether the contract would reject the entitlement as expired at
+    /// `now`, i.e. `now >= expires_at`.
+    pub fn is_expired_at(&self, now: UnixMillis) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl From<Entitlement> for proto::Entitlement {
+    fn from(entitlement: Entitlement) -> Self {
+        Self {
+            id: entitlement.id,
+            service_id: entitlement.service_id,
+            buyer: entitlement.buyer,
+            tier_id: entitlement.tier_id,
+            quota_requests: entitlement.quota_requests,
+            quota_used: entitlement.quota_used,
+            purchased_at: entitlement.purchased_at.as_millis(),
+            expires_at: entitlement.expires_at.as_millis(),
+            active: entitlement.active,
+        }
+    }
+}
+
+impl From<proto::Entitlement> for Entitlement {
+    fn from(entitlement: proto::Entitlement) -> Self {
+        Self {
+            id: entitlement.id,
+            service_id: entitlement.service_id,
+            buyer: entitlement.buyer,
+            tier_id: entitlement.tier_id,
+            quota_requests: entitlement.quota_requests,
+            quota_used: entitlement.quota_used,
+            purchased_at: UnixMillis(entitlement.purchased_at),
+            expires_at: UnixMillis(entitlement.expires_at),
+            active: entitlement.active,
+        }
+    }
+}
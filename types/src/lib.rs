@@ -0,0 +1,31 @@
+//! Domain types shared by the validator and the backend.
+//!
+//! The on-chain objects of `inframint::entitlements` are the source of truth;
+//! [`move_layout`] mirrors their Move layout as fullnodes render it, and
+//! [`proto`] is the wire format between the backend and the validator.
+//! [`signature`] verifies what Sui wallets sign.
+
+pub mod entitlement;
+pub mod metering;
+pub mod move_layout;
+pub mod service;
+pub mod signature;
+pub mod status;
+pub mod time;
+pub mod proto {
+    tonic::include_proto!("validator");
+
+    /// Encoded descriptors of `validator.proto`, for gRPC server reflection.
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("validator_descriptor");
+
+    /// `google.rpc.Status`, the payload of gRPC error details.
+    pub mod rpc {
+        tonic::include_proto!("google.rpc");
+    }
+}
+
+pub use crate::entitlement::Entitlement;
+pub use crate::metering::JsonRpcCostTable;
+pub use crate::service::{service_id_bytes, service_id_from_bytes, PricingTier};
+pub use crate::status::{error_code, status_with_error_code};
+pub use crate::time::{UnixMillis, UnixSeconds};
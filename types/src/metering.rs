@@ -0,0 +1,40 @@
+//! How providers price requests to their services.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Key of the [`JsonRpcCostTable`] in `services.metadata`.
+pub const JSONRPC_COSTS_METADATA_KEY: &str = "jsonrpc_costs";
+
+/// Compute units charged per JSON-RPC method, for services of type `rpc`.
+/// A batch costs the sum of its calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonRpcCostTable {
+    /// Units for methods not in `methods`.
+    pub default_cost: u64,
+    #[serde(default)]
+    pub methods: BTreeMap<String, u64>,
+}
+
+impl Default for JsonRpcCostTable {
+    fn default() -> Self {
+        Self {
+            default_cost: 1,
+            methods: BTreeMap::new(),
+        }
+    }
+}
+
+impl JsonRpcCostTable {
+    pub fn cost(&self, method: &str) -> u64 {
+        self.methods.get(method).copied().unwrap_or(self.default_cost)
+    }
+
+    /// The table in a service's `metadata`, if it has one.
+    pub fn from_metadata(metadata: &serde_json::Value) -> Result<Option<Self>, serde_json::Error> {
+        metadata
+            .get(JSONRPC_COSTS_METADATA_KEY)
+            .map(Self::deserialize)
+            .transpose()
+    }
+}
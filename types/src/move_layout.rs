@@ -0,0 +1,123 @@
+//! Move structs as fullnodes render them with `showContent`: `u64` values
+//! are strings, `UID`s are `{ "id": ... }` and `vector<u8>` is an array of
+//! numbers.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{service_id_bytes, service_id_from_bytes, Entitlement, PricingTier, UnixMillis};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UidField {
+    pub id: String,
+}
+
+/// Fields of `inframint::entitlements::Entitlement`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntitlementFields {
+    pub id: UidField,
+    pub service_id: Vec<u8>,
+    pub buyer: String,
+    #[serde(with = "u64_string")]
+    pub tier_id: u64,
+    #[serde(with = "u64_string")]
+    pub quota_requests: u64,
+    #[serde(with = "u64_string")]
+    pub quota_used: u64,
+    #[serde(with = "u64_string")]
+    pub purchased_at: u64,
+    #[serde(with = "u64_string")]
+    pub expires_at: u64,
+    pub active: bool,
+}
+
+impl From<EntitlementFields> for Entitlement {
+    fn from(fields: EntitlementFields) -> Self {
+        Self {
+            id: fields.id.id,
+            service_id: service_id_from_bytes(fields.service_id),
+            buyer: fields.buyer,
+            tier_id: fields.tier_id,
+            quota_requests: fields.quota_requests,
+            quota_used: fields.quota_used,
+            purchased_at: UnixMillis(fields.purchased_at),
+            expires_at: UnixMillis(fields.expires_at),
+            active: fields.active,
+        }
+    }
+}
+
+impl From<Entitlement> for EntitlementFields {
+    fn from(entitlement: Entitlement) -> Self {
+        Self {
+            id: UidField { id: entitlement.id },
+            service_id: service_id_bytes(&entitlement.service_id),
+            buyer: entitlement.buyer,
+            tier_id: entitlement.tier_id,
+            quota_requests: entitlement.quota_requests,
+            quota_used: entitlement.quota_used,
+            purchased_at: entitlement.purchased_at.as_millis(),
+            expires_at: entitlement.expires_at.as_millis(),
+            active: entitlement.active,
+        }
+    }
+}
+
+/// Fields of `inframint::entitlements::PricingTier`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PricingTierFields {
+    #[serde(with = "u64_string")]
+    pub price_sui: u64,
+    #[serde(with = "u64_string")]
+    pub quota_requests: u64,
+    #[serde(with = "u64_string")]
+    pub validity_period_ms: u64,
+    pub rate_limit_per_second: u32,
+    pub active: bool,
+}
+
+impl From<PricingTierFields> for PricingTier {
+    fn from(fields: PricingTierFields) -> Self {
+        Self {
+            price_sui: fields.price_sui,
+            quota_requests: fields.quota_requests,
+            validity_period_ms: fields.validity_period_ms,
+            rate_limit_per_second: fields.rate_limit_per_second,
+            active: fields.active,
+        }
+    }
+}
+
+impl From<PricingTier> for PricingTierFields {
+    fn from(tier: PricingTier) -> Self {
+        Self {
+            price_sui: tier.price_sui,
+            quota_requests: tier.quota_requests,
+            validity_period_ms: tier.validity_period_ms,
+            rate_limit_per_second: tier.rate_limit_per_second,
+            active: tier.active,
+        }
+    }
+}
+
+/// Serializes `u64` as a string and accepts strings or numbers.
+mod u64_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        String(String),
+        Number(u64),
+    }
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::String(s) => s.parse().map_err(serde::de::Error::custom),
+            Repr::Number(n) => Ok(n),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// `inframint::entitlements::PricingTier`.
+///
+/// Fields are in Move declaration order, so the BCS encoding of this type is
+/// that of the Move value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PricingTier {
+    /// Price in MIST (1 SUI = 10^9 MIST).
+    pub price_sui: u64,
+    pub quota_requests: u64,
+    pub validity_period_ms: u64,
+    pub rate_limit_per_second: u32,
+    pub active: bool,
+}
+
+/// Service IDs are registered as UTF-8 bytes (the backend registers its
+/// service UUIDs); anything else is rendered as `0x`-prefixed hex.
+pub fn service_id_from_bytes(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| format!("0x{}", hex::encode(e.into_bytes())))
+}
+
+/// Recovers the registry key from a service ID, undoing the hex fallback of
+/// [`service_id_from_bytes`].
+pub fn service_id_bytes(service_id: &str) -> Vec<u8> {
+    if let Some(bytes) = service_id.strip_prefix("0x").and_then(|h| hex::decode(h).ok()) {
+        if std::str::from_utf8(&bytes).is_err() {
+            return bytes;
+        }
+    }
+    service_id.as_bytes().to_vec()
+}
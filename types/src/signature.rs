@@ -0,0 +1,312 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use ed25519_dalek::Signer as _;
+use thiserror::Error;
+
+type Blake2b256 = Blake2b<U32>;
+
+/// Intent prefixes (scope, version, app id) for `TransactionData` and
+/// `PersonalMessage` signatures.
+const TRANSACTION_DATA_INTENT: [u8; 3] = [0, 0, 0];
+const PERSONAL_MESSAGE_INTENT: [u8; 3] = [3, 0, 0];
+
+const SIGNATURE_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("Signature is not valid base64")]
+    InvalidEncoding,
+
+    #[error("Unsupported signature scheme flag: {0:#04x}")]
+    UnsupportedScheme(u8),
+
+    #[error("Invalid serialized signature length: {0}")]
+    InvalidLength(usize),
+
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+
+    #[error("Invalid private key")]
+    InvalidPrivateKey,
+
+    #[error("Keystore error: {0}")]
+    KeystoreError(String),
+}
+
+/// Key schemes accepted by Sui wallets, identified by their flag byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    Ed25519,
+    Secp256k1,
+    Secp256r1,
+}
+
+impl SignatureScheme {
+    pub fn flag(self) -> u8 {
+        match self {
+            SignatureScheme::Ed25519 => 0x00,
+            SignatureScheme::Secp256k1 => 0x01,
+            SignatureScheme::Secp256r1 => 0x02,
+        }
+    }
+
+    pub fn from_flag(flag: u8) -> Result<Self, SignatureError> {
+        match flag {
+            0x00 => Ok(SignatureScheme::Ed25519),
+            0x01 => Ok(SignatureScheme::Secp256k1),
+            0x02 => Ok(SignatureScheme::Secp256r1),
+            other => Err(SignatureError::UnsupportedScheme(other)),
+        }
+    }
+
+    fn public_key_length(self) -> usize {
+        match self {
+            SignatureScheme::Ed25519 => 32,
+            SignatureScheme::Secp256k1 | SignatureScheme::Secp256r1 => 33,
+        }
+    }
+}
+
+/// A Sui serialized signature: `flag || signature || public key`.
+#[derive(Debug, Clone)]
+pub struct SuiSignature {
+    scheme: SignatureScheme,
+    signature: [u8; SIGNATURE_LENGTH],
+    public_key: Vec<u8>,
+}
+
+impl SuiSignature {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignatureError> {
+        let (&flag, rest) = bytes.split_first()
+            .ok_or(SignatureError::InvalidLength(0))?;
+        let scheme = SignatureScheme::from_flag(flag)?;
+
+        if rest.len() != SIGNATURE_LENGTH + scheme.public_key_length() {
+            return Err(SignatureError::InvalidLength(bytes.len()));
+        }
+
+        let (signature, public_key) = rest.split_at(SIGNATURE_LENGTH);
+        let mut sig = [0u8; SIGNATURE_LENGTH];
+        sig.copy_from_slice(signature);
+
+        Ok(Self {
+            scheme,
+            signature: sig,
+            public_key: public_key.to_vec(),
+        })
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, SignatureError> {
+        let bytes = BASE64.decode(encoded.trim())
+            .map_err(|_| SignatureError::InvalidEncoding)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_base64(&self) -> String {
+        let mut bytes = Vec::with_capacity(1 + SIGNATURE_LENGTH + self.public_key.len());
+        bytes.push(self.scheme.flag());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&self.public_key);
+        BASE64.encode(bytes)
+    }
+
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Sui address of the embedded public key.
+    pub fn signer_address(&self) -> String {
+        sui_address(self.scheme, &self.public_key)
+    }
+
+    /// Verifies the signature over a personal message as produced by
+    /// `signPersonalMessage` in Sui wallets.
+    pub fn verify_personal_message(&self, message: &[u8]) -> Result<bool, SignatureError> {
+        self.verify_digest(&personal_message_digest(message))
+    }
+
+    /// Verifies the signature over an intent message digest. Ed25519 signs the
+    /// digest directly; the ECDSA schemes sign its SHA-256 hash.
+    pub fn verify_digest(&self, digest: &[u8; 32]) -> Result<bool, SignatureError> {
+        match self.scheme {
+            SignatureScheme::Ed25519 => {
+                let public_key: [u8; 32] = self.public_key.as_slice().try_into()
+                    .map_err(|_| SignatureError::InvalidPublicKey)?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&public_key)
+                    .map_err(|_| SignatureError::InvalidPublicKey)?;
+                let signature = ed25519_dalek::Signature::from_bytes(&self.signature);
+                Ok(key.verify_strict(digest, &signature).is_ok())
+            }
+            SignatureScheme::Secp256k1 => {
+                use k256::ecdsa::signature::Verifier;
+
+                let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&self.public_key)
+                    .map_err(|_| SignatureError::InvalidPublicKey)?;
+                let Ok(signature) = k256::ecdsa::Signature::from_slice(&self.signature) else {
+                    return Ok(false);
+                };
+                Ok(key.verify(digest, &signature).is_ok())
+            }
+            SignatureScheme::Secp256r1 => {
+                use p256::ecdsa::signature::Verifier;
+
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&self.public_key)
+                    .map_err(|_| SignatureError::InvalidPublicKey)?;
+                let Ok(signature) = p256::ecdsa::Signature::from_slice(&self.signature) else {
+                    return Ok(false);
+                };
+                // Sui only accepts low-S signatures, matching secp256k1.
+                if signature.normalize_s().is_some() {
+                    return Ok(false);
+                }
+                Ok(key.verify(digest, &signature).is_ok())
+            }
+        }
+    }
+}
+
+/// A signing key for one of the Sui key schemes.
+pub enum SuiKeyPair {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+    Secp256r1(p256::ecdsa::SigningKey),
+}
+
+impl SuiKeyPair {
+    pub fn from_secret_bytes(scheme: SignatureScheme, secret: &[u8]) -> Result<Self, SignatureError> {
+        match scheme {
+            SignatureScheme::Ed25519 => {
+                let secret: [u8; 32] = secret.try_into()
+                    .map_err(|_| SignatureError::InvalidPrivateKey)?;
+                Ok(SuiKeyPair::Ed25519(ed25519_dalek::SigningKey::from_bytes(&secret)))
+            }
+            SignatureScheme::Secp256k1 => k256::ecdsa::SigningKey::from_slice(secret)
+                .map(SuiKeyPair::Secp256k1)
+                .map_err(|_| SignatureError::InvalidPrivateKey),
+            SignatureScheme::Secp256r1 => p256::ecdsa::SigningKey::from_slice(secret)
+                .map(SuiKeyPair::Secp256r1)
+                .map_err(|_| SignatureError::InvalidPrivateKey),
+        }
+    }
+
+    /// Decodes a `sui.keystore` entry: base64 of `flag || private key`.
+    pub fn from_keystore_entry(encoded: &str) -> Result<Self, SignatureError> {
+        let bytes = BASE64.decode(encoded.trim())
+            .map_err(|_| SignatureError::InvalidEncoding)?;
+        let (&flag, secret) = bytes.split_first()
+            .ok_or(SignatureError::InvalidPrivateKey)?;
+
+        Self::from_secret_bytes(SignatureScheme::from_flag(flag)?, secret)
+    }
+
+    /// Loads a key from a `sui.keystore` file (a JSON array of entries). Picks
+    /// the key for `address` when given, otherwise the first entry.
+    pub fn from_keystore_file(path: &str, address: Option<&str>) -> Result<Self, SignatureError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SignatureError::KeystoreError(format!("{}: {}", path, e)))?;
+        let entries: Vec<String> = serde_json::from_str(&contents)
+            .map_err(|e| SignatureError::KeystoreError(format!("{}: {}", path, e)))?;
+
+        for entry in entries {
+            let keypair = Self::from_keystore_entry(&entry)?;
+            match address {
+                Some(address) if !keypair.address().eq_ignore_ascii_case(address) => continue,
+                _ => return Ok(keypair),
+            }
+        }
+
+        Err(SignatureError::KeystoreError(match address {
+            Some(address) => format!("{}: no key for address {}", path, address),
+            None => format!("{}: keystore is empty", path),
+        }))
+    }
+
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            SuiKeyPair::Ed25519(_) => SignatureScheme::Ed25519,
+            SuiKeyPair::Secp256k1(_) => SignatureScheme::Secp256k1,
+            SuiKeyPair::Secp256r1(_) => SignatureScheme::Secp256r1,
+        }
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        match self {
+            SuiKeyPair::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+            SuiKeyPair::Secp256k1(key) => key.verifying_key().to_encoded_point(true).as_bytes().to_vec(),
+            SuiKeyPair::Secp256r1(key) => key.verifying_key().to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        sui_address(self.scheme(), &self.public_key())
+    }
+
+    pub fn sign_personal_message(&self, message: &[u8]) -> SuiSignature {
+        self.sign_digest(&personal_message_digest(message))
+    }
+
+    pub fn sign_transaction(&self, tx_bytes: &[u8]) -> SuiSignature {
+        self.sign_digest(&transaction_digest(tx_bytes))
+    }
+
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> SuiSignature {
+        let signature: [u8; SIGNATURE_LENGTH] = match self {
+            SuiKeyPair::Ed25519(key) => key.sign(digest).to_bytes(),
+            SuiKeyPair::Secp256k1(key) => {
+                use k256::ecdsa::signature::Signer;
+                let signature: k256::ecdsa::Signature = key.sign(digest);
+                signature.normalize_s().unwrap_or(signature).to_bytes().into()
+            }
+            SuiKeyPair::Secp256r1(key) => {
+                use p256::ecdsa::signature::Signer;
+                let signature: p256::ecdsa::Signature = key.sign(digest);
+                signature.normalize_s().unwrap_or(signature).to_bytes().into()
+            }
+        };
+
+        SuiSignature {
+            scheme: self.scheme(),
+            signature,
+            public_key: self.public_key(),
+        }
+    }
+}
+
+/// Derives a Sui address: `Blake2b-256(flag || public key)`.
+pub fn sui_address(scheme: SignatureScheme, public_key: &[u8]) -> String {
+    let mut hasher = Blake2b256::new();
+    hasher.update([scheme.flag()]);
+    hasher.update(public_key);
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Digest signed for a personal message: `Blake2b-256(intent || bcs(message))`,
+/// where the message is BCS-encoded as a `vector<u8>`.
+pub fn personal_message_digest(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b256::new();
+    hasher.update(PERSONAL_MESSAGE_INTENT);
+    hasher.update(uleb128(message.len()));
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Digest signed for a transaction: `Blake2b-256(intent || bcs(TransactionData))`.
+pub fn transaction_digest(tx_bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b256::new();
+    hasher.update(TRANSACTION_DATA_INTENT);
+    hasher.update(tx_bytes);
+    hasher.finalize().into()
+}
+
+fn uleb128(mut value: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
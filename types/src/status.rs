@@ -0,0 +1,41 @@
+//! Error codes carried in the details of a gRPC status.
+//!
+//! A failed validator call puts a `google.rpc.Status` with one
+//! [`ErrorDetail`] in its details, so clients can tell why it failed without
+//! parsing the message.
+
+use prost::Message;
+use tonic::{Code, Status};
+
+use crate::proto::rpc;
+use crate::proto::{ErrorCode, ErrorDetail};
+
+/// Type URL of an [`ErrorDetail`] packed in a `google.protobuf.Any`.
+pub const ERROR_DETAIL_TYPE_URL: &str = "type.googleapis.com/validator.ErrorDetail";
+
+/// A status whose details carry `error_code`.
+pub fn status_with_error_code(code: Code, message: impl Into<String>, error_code: ErrorCode) -> Status {
+    let message = message.into();
+    let detail = ErrorDetail { code: error_code as i32 };
+    let details = rpc::Status {
+        code: code as i32,
+        message: message.clone(),
+        details: vec![prost_types::Any {
+            type_url: ERROR_DETAIL_TYPE_URL.to_string(),
+            value: detail.encode_to_vec(),
+        }],
+    };
+    Status::with_details(code, message, details.encode_to_vec().into())
+}
+
+/// The error code in the details of `status`, if it carries one.
+pub fn error_code(status: &Status) -> Option<ErrorCode> {
+    let details = rpc::Status::decode(status.details()).ok()?;
+    details
+        .details
+        .iter()
+        .filter(|any| any.type_url == ERROR_DETAIL_TYPE_URL)
+        .find_map(|any| ErrorDetail::decode(any.value.as_slice()).ok())
+        .and_then(|detail| ErrorCode::try_from(detail.code).ok())
+        .filter(|code| *code != ErrorCode::Unspecified)
+}
@@ -0,0 +1,58 @@
+//! Timestamps that carry their unit. Sui's `clock::timestamp_ms`, and with
+//! it every timestamp stored by the contract, is in milliseconds.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Milliseconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnixMillis(pub u64);
+
+/// Seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnixSeconds(pub u64);
+
+impl UnixMillis {
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self(since_epoch.as_millis() as u64)
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        Self(self.0.saturating_add(duration.as_millis() as u64))
+    }
+
+    pub fn saturating_sub(self, duration: Duration) -> Self {
+        Self(self.0.saturating_sub(duration.as_millis() as u64))
+    }
+
+    /// Time elapsed from `earlier` to `self`; zero if `earlier` is later.
+    pub fn duration_since(self, earlier: UnixMillis) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl UnixSeconds {
+    pub const fn as_secs(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<UnixSeconds> for UnixMillis {
+    fn from(seconds: UnixSeconds) -> Self {
+        Self(seconds.0.saturating_mul(1000))
+    }
+}
+
+/// Truncates to whole seconds.
+impl From<UnixMillis> for UnixSeconds {
+    fn from(millis: UnixMillis) -> Self {
+        Self(millis.0 / 1000)
+    }
+}
@@ -0,0 +1,31 @@
+use inframint_types::JsonRpcCostTable;
+use serde_json::json;
+
+#[test]
+fn test_cost_table_from_service_metadata() {
+    let metadata = json!({
+        "region": "eu-west",
+        "jsonrpc_costs": {
+            "default_cost": 2,
+            "methods": { "sui_getObject": 1, "suix_queryEvents": 20 }
+        }
+    });
+
+    let table = JsonRpcCostTable::from_metadata(&metadata).unwrap().unwrap();
+    assert_eq!(table.cost("sui_getObject"), 1);
+    assert_eq!(table.cost("suix_queryEvents"), 20);
+    assert_eq!(table.cost("sui_executeTransactionBlock"), 2);
+}
+
+#[test]
+fn test_metadata_without_cost_table() {
+    assert_eq!(JsonRpcCostTable::from_metadata(&json!({})).unwrap(), None);
+    assert_eq!(JsonRpcCostTable::default().cost("anything"), 1);
+}
+
+#[test]
+fn test_rejects_malformed_cost_table() {
+    for table in [json!({ "methods": {} }), json!({ "default_cost": -1 }), json!({ "default_cost": 1, "methods": { "eth_call": "5" } })] {
+        assert!(JsonRpcCostTable::from_metadata(&json!({ "jsonrpc_costs": table })).is_err());
+    }
+}
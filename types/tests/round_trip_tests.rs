@@ -0,0 +1,113 @@
+use serde_json::{json, Value};
+use inframint_types::move_layout::{EntitlementFields, PricingTierFields};
+use inframint_types::{proto, service_id_bytes, service_id_from_bytes, Entitlement, PricingTier, UnixMillis};
+
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const BUYER: &str = "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207";
+const SERVICE_ID: &str = "11111111-1111-1111-1111-111111111111";
+
+fn entitlement() -> Entitlement {
+    Entitlement {
+        id: ENTITLEMENT_ID.to_string(),
+        service_id: SERVICE_ID.to_string(),
+        buyer: BUYER.to_string(),
+        tier_id: 1,
+        quota_requests: 1000,
+        quota_used: 25,
+        purchased_at: UnixMillis(1729166400000),
+        expires_at: UnixMillis(4102444800000),
+        active: true,
+    }
+}
+
+/// `showContent` fields of the entitlement above, as a fullnode renders them.
+fn entitlement_content() -> Value {
+    json!({
+        "active": true,
+        "buyer": BUYER,
+        "expires_at": "4102444800000",
+        "id": { "id": ENTITLEMENT_ID },
+        "purchased_at": "1729166400000",
+        "quota_requests": "1000",
+        "quota_used": "25",
+        "service_id": SERVICE_ID.as_bytes(),
+        "tier_id": "1",
+    })
+}
+
+#[test]
+fn test_entitlement_proto_round_trip() {
+    let message = proto::Entitlement::from(entitlement());
+    assert_eq!(message.id, ENTITLEMENT_ID);
+    assert_eq!(message.quota_used, 25);
+
+    assert_eq!(Entitlement::from(message), entitlement());
+}
+
+#[test]
+fn test_entitlement_move_round_trip() {
+    let fields: EntitlementFields = serde_json::from_value(entitlement_content()).unwrap();
+    let decoded = Entitlement::from(fields);
+    assert_eq!(decoded, entitlement());
+
+    let encoded = serde_json::to_value(EntitlementFields::from(decoded)).unwrap();
+    assert_eq!(encoded, entitlement_content());
+}
+
+#[test]
+fn test_entitlement_accepts_numeric_u64() {
+    let mut content = entitlement_content();
+    content["quota_used"] = json!(25);
+
+    let fields: EntitlementFields = serde_json::from_value(content).unwrap();
+    assert_eq!(fields.quota_used, 25);
+}
+
+#[test]
+fn test_binary_service_id_round_trip() {
+    let bytes = vec![0xff, 0x00, 0x7f];
+    let service_id = service_id_from_bytes(bytes.clone());
+    assert_eq!(service_id, "0xff007f");
+    assert_eq!(service_id_bytes(&service_id), bytes);
+
+    let mut content = entitlement_content();
+    content["service_id"] = json!(bytes);
+    let fields: EntitlementFields = serde_json::from_value(content.clone()).unwrap();
+    let encoded = serde_json::to_value(EntitlementFields::from(Entitlement::from(fields))).unwrap();
+    assert_eq!(encoded, content);
+}
+
+#[test]
+fn test_utf8_service_id_that_looks_like_hex() {
+    // Valid UTF-8 is always kept as text
+    assert_eq!(service_id_from_bytes(b"0x4142".to_vec()), "0x4142");
+    assert_eq!(service_id_bytes("0x4142"), b"0x4142".to_vec());
+}
+
+#[test]
+fn test_pricing_tier_bcs_matches_move_layout() {
+    let tier = PricingTier {
+        price_sui: 1_000_000_000,
+        quota_requests: 1000,
+        validity_period_ms: 2_592_000_000,
+        rate_limit_per_second: 5,
+        active: true,
+    };
+
+    // `get_tier_info` returns the fields as a tuple in declaration order
+    let tuple = (1_000_000_000u64, 1000u64, 2_592_000_000u64, 5u32, true);
+    let bytes = bcs::to_bytes(&tuple).unwrap();
+    assert_eq!(bcs::to_bytes(&tier).unwrap(), bytes);
+    assert_eq!(bcs::from_bytes::<PricingTier>(&bytes).unwrap(), tier);
+
+    let content = json!({
+        "price_sui": "1000000000",
+        "quota_requests": "1000",
+        "validity_period_ms": "2592000000",
+        "rate_limit_per_second": 5,
+        "active": true,
+    });
+    let fields: PricingTierFields = serde_json::from_value(content.clone()).unwrap();
+    assert_eq!(PricingTier::from(fields), tier);
+    assert_eq!(serde_json::to_value(PricingTierFields::from(tier)).unwrap(), content);
+}
@@ -0,0 +1,74 @@
+use inframint_types::signature::{
+    personal_message_digest, SignatureError, SignatureScheme, SuiKeyPair, SuiSignature,
+};
+
+const MESSAGE: &[u8] = b"inframint:access:0x4b7e1f2a";
+
+fn keypairs() -> Vec<SuiKeyPair> {
+    vec![
+        SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap(),
+        SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &[0x22; 32]).unwrap(),
+        SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256r1, &[0x33; 32]).unwrap(),
+    ]
+}
+
+#[test]
+fn test_personal_message_roundtrip_for_all_schemes() {
+    for keypair in keypairs() {
+        let encoded = keypair.sign_personal_message(MESSAGE).to_base64();
+
+        let signature = SuiSignature::from_base64(&encoded).unwrap();
+        assert_eq!(signature.scheme(), keypair.scheme());
+        assert_eq!(signature.signer_address(), keypair.address());
+        assert!(signature.verify_personal_message(MESSAGE).unwrap());
+    }
+}
+
+#[test]
+fn test_rejects_signature_over_different_message() {
+    for keypair in keypairs() {
+        let signature = keypair.sign_personal_message(MESSAGE);
+        assert!(!signature.verify_personal_message(b"inframint:access:0xdeadbeef").unwrap());
+    }
+}
+
+#[test]
+fn test_rejects_signature_without_personal_message_intent() {
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+
+    // A signature over anything but the intent digest must not verify.
+    let raw = [0x42u8; 32];
+    let signature = keypair.sign_digest(&raw);
+
+    assert_ne!(raw, personal_message_digest(MESSAGE));
+    assert!(!signature.verify_personal_message(MESSAGE).unwrap());
+}
+
+#[test]
+fn test_address_is_blake2b_of_flag_and_public_key() {
+    let addresses: Vec<String> = keypairs().iter().map(|k| k.address()).collect();
+
+    for address in &addresses {
+        assert_eq!(address.len(), 66);
+        assert!(address.starts_with("0x"));
+    }
+    assert_ne!(addresses[0], addresses[1]);
+    assert_ne!(addresses[1], addresses[2]);
+}
+
+#[test]
+fn test_rejects_malformed_serialized_signatures() {
+    assert!(matches!(
+        SuiSignature::from_base64("not base64!"),
+        Err(SignatureError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        SuiSignature::from_bytes(&[0x05; 97]),
+        Err(SignatureError::UnsupportedScheme(0x05))
+    ));
+    assert!(matches!(
+        SuiSignature::from_bytes(&[0x00; 64]),
+        Err(SignatureError::InvalidLength(64))
+    ));
+}
+
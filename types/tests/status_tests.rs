@@ -0,0 +1,25 @@
+use inframint_types::proto::ErrorCode;
+use inframint_types::{error_code, status_with_error_code};
+use tonic::codegen::http;
+use tonic::{Code, Status};
+
+#[test]
+fn test_error_code_survives_status_details() {
+    let status = status_with_error_code(Code::ResourceExhausted, "Quota exceeded", ErrorCode::QuotaExceeded);
+
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.message(), "Quota exceeded");
+    assert_eq!(error_code(&status), Some(ErrorCode::QuotaExceeded));
+
+    // As a client sees it, after the details went through the trailers.
+    let mut trailers = http::HeaderMap::new();
+    status.add_header(&mut trailers).unwrap();
+    let received = Status::from_header_map(&trailers).unwrap();
+    assert_eq!(error_code(&received), Some(ErrorCode::QuotaExceeded));
+}
+
+#[test]
+fn test_status_without_error_code() {
+    assert_eq!(error_code(&Status::internal("boom")), None);
+    assert_eq!(error_code(&Status::with_details(Code::Internal, "boom", b"garbage".to_vec().into())), None);
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, UNIX_EPOCH};
+use inframint_types::{Entitlement, UnixMillis, UnixSeconds};
+
+fn entitlement(expires_at: UnixMillis) -> Entitlement {
+    Entitlement {
+        id: "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f".to_string(),
+        service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+        buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+        tier_id: 1,
+        quota_requests: 1000,
+        quota_used: 0,
+        purchased_at: UnixMillis(1729166400000),
+        expires_at,
+        active: true,
+    }
+}
+
+#[test]
+fn test_expiry_matches_contract() {
+    let entitlement = entitlement(UnixMillis(1760000000000));
+
+    assert!(!entitlement.is_expired_at(UnixMillis(1759999999999)));
+    // The contract requires `now < expires_at`
+    assert!(entitlement.is_expired_at(UnixMillis(1760000000000)));
+}
+
+#[test]
+fn test_seconds_are_not_mistaken_for_millis() {
+    // Expired a second ago, but still in the future when read as seconds
+    let entitlement = entitlement(UnixMillis(1759999999000));
+    let now = UnixSeconds(1760000000);
+
+    assert!(entitlement.is_expired_at(now.into()));
+    assert_eq!(UnixSeconds::from(entitlement.expires_at), UnixSeconds(1759999999));
+}
+
+#[test]
+fn test_millis_arithmetic() {
+    let now = UnixMillis(1760000000000);
+
+    assert_eq!(now.saturating_add(Duration::from_secs(30)), UnixMillis(1760000030000));
+    assert_eq!(now.saturating_sub(Duration::from_millis(1)), UnixMillis(1759999999999));
+    assert_eq!(UnixMillis(5).saturating_sub(Duration::from_secs(1)), UnixMillis(0));
+    assert_eq!(now.duration_since(UnixMillis(1759999998500)), Duration::from_millis(1500));
+    assert_eq!(UnixMillis(0).duration_since(now), Duration::ZERO);
+
+    let time = UNIX_EPOCH + Duration::from_millis(1760000000123);
+    assert_eq!(UnixMillis::from_system_time(time), UnixMillis(1760000000123));
+}
+
+#[test]
+fn test_serializes_as_plain_number() {
+    let json = serde_json::to_value(entitlement(UnixMillis(1760000000000))).unwrap();
+    assert_eq!(json["expires_at"], 1760000000000u64);
+    assert_eq!(json["purchased_at"], 1729166400000u64);
+}
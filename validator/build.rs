@@ -1,9 +0,0 @@
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::configure()
-        .build_server(true)
-        .build_client(true)
-        .out_dir("src/proto")
-        .compile(&["proto/validator.proto"], &["proto"])?;
-
-    Ok(())
-}
@@ -0,0 +1,146 @@
+//! Short-lived access tokens, so that clients sign with their wallet once
+//! rather than on every request.
+//!
+//! Tokens are JWTs signed with Ed25519 (`alg: EdDSA`) by a key shared by all
+//! replicas of a deployment. They carry everything needed to serve a request,
+//! the tier's rate limit included, and are verified without a chain lookup;
+//! the price is that a deactivated entitlement's tokens stay valid until they
+//! expire.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use ed25519_dalek::{Signer as _, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::cache::CachedEntitlement;
+use crate::clock::{UnixMillis, UnixSeconds};
+use crate::signature::SuiKeyPair;
+
+const ALGORITHM: &str = "EdDSA";
+
+#[derive(Error, Debug, Clone)]
+pub enum AccessTokenError {
+    #[error("Access tokens must be signed with an Ed25519 key")]
+    UnsupportedKey,
+
+    #[error("Access token is malformed")]
+    Malformed,
+
+    #[error("Unsupported access token algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("Invalid access token signature")]
+    InvalidSignature,
+
+    #[error("Access token was issued by {0}")]
+    WrongIssuer(String),
+
+    #[error("Access token expired")]
+    Expired,
+
+    #[error("Access token was issued for service {0}")]
+    WrongService(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    typ: String,
+}
+
+/// What a token grants: access to one entitlement until `exp`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    /// `validator_id` of the issuing deployment.
+    pub iss: String,
+    /// The entitlement's buyer.
+    pub sub: String,
+    /// Entitlement id.
+    pub ent: String,
+    /// Service id.
+    pub svc: String,
+    /// The tier's `rate_limit_per_second`; 0 for the global default.
+    pub rps: u32,
+    pub iat: UnixSeconds,
+    pub exp: UnixSeconds,
+}
+
+pub struct AccessTokenIssuer {
+    key: SigningKey,
+    issuer: String,
+}
+
+impl AccessTokenIssuer {
+    pub fn new(keypair: SuiKeyPair, issuer: &str) -> Result<Self, AccessTokenError> {
+        match keypair {
+            SuiKeyPair::Ed25519(key) => Ok(Self {
+                key,
+                issuer: issuer.to_string(),
+            }),
+            _ => Err(AccessTokenError::UnsupportedKey),
+        }
+    }
+
+    /// Issues a token for `entitlement` valid for `ttl` from `now`, but never
+    /// past the entitlement's own expiry.
+    pub fn issue(&self, entitlement: &CachedEntitlement, now: UnixMillis, ttl: Duration) -> (String, AccessTokenClaims) {
+        let expires_at = now.saturating_add(ttl).min(entitlement.entitlement.expires_at);
+        let claims = AccessTokenClaims {
+            iss: self.issuer.clone(),
+            sub: entitlement.entitlement.buyer.clone(),
+            ent: entitlement.entitlement.id.clone(),
+            svc: entitlement.entitlement.service_id.clone(),
+            rps: entitlement.rate_limit_per_second,
+            iat: now.into(),
+            exp: expires_at.into(),
+        };
+
+        let header = Header {
+            alg: ALGORITHM.to_string(),
+            typ: "JWT".to_string(),
+        };
+        let signing_input = format!("{}.{}", encode_json(&header), encode_json(&claims));
+        let signature = self.key.sign(signing_input.as_bytes());
+
+        (format!("{}.{}", signing_input, BASE64_URL.encode(signature.to_bytes())), claims)
+    }
+
+    /// Checks the token's signature, issuer and expiry at `now`.
+    pub fn verify(&self, token: &str, now: UnixMillis) -> Result<AccessTokenClaims, AccessTokenError> {
+        let (signing_input, signature) = token.rsplit_once('.').ok_or(AccessTokenError::Malformed)?;
+        let (header, claims) = signing_input.split_once('.').ok_or(AccessTokenError::Malformed)?;
+
+        let header: Header = decode_json(header)?;
+        if header.alg != ALGORITHM {
+            return Err(AccessTokenError::UnsupportedAlgorithm(header.alg));
+        }
+
+        let signature: [u8; 64] = BASE64_URL.decode(signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(AccessTokenError::Malformed)?;
+        self.key.verifying_key()
+            .verify_strict(signing_input.as_bytes(), &ed25519_dalek::Signature::from_bytes(&signature))
+            .map_err(|_| AccessTokenError::InvalidSignature)?;
+
+        let claims: AccessTokenClaims = decode_json(claims)?;
+        if claims.iss != self.issuer {
+            return Err(AccessTokenError::WrongIssuer(claims.iss));
+        }
+        if UnixSeconds::from(now) >= claims.exp {
+            return Err(AccessTokenError::Expired);
+        }
+
+        Ok(claims)
+    }
+}
+
+fn encode_json<T: Serialize>(value: &T) -> String {
+    BASE64_URL.encode(serde_json::to_vec(value).expect("token parts serialize"))
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(segment: &str) -> Result<T, AccessTokenError> {
+    let bytes = BASE64_URL.decode(segment).map_err(|_| AccessTokenError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| AccessTokenError::Malformed)
+}
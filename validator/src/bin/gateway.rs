@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use dotenvy::dotenv;
+
+use validator::{
+    ValidatorServiceImpl,
+    ValidatorConfig,
+    catalog::CatalogClient,
+    config::GatewayConfig,
+    gateway::Gateway,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    tracing_subscriber::fmt()
+        .with_env_filter("gateway=debug,validator=debug")
+        .init();
+
+    // Load environment variables
+    dotenv().ok();
+    info!("🔐 Starting InfraMint Gateway...");
+
+    // Load configuration
+    let config = ValidatorConfig::from_env()?;
+    let gateway_config = GatewayConfig::from_env()?;
+    info!("📋 Configuration loaded");
+
+    // Entitlements are enforced in-process
+    let service = ValidatorServiceImpl::new(config).await?;
+
+    // Settle metered usage on-chain in the background
+    if service.config().keystore_path.is_some() {
+        tokio::spawn(service.settler().run());
+        info!("⛓️  Usage settlement every {}s", service.config().settle_interval);
+    } else {
+        warn!("No keystore configured, metered usage will not be settled on-chain");
+    }
+
+    // Apply on-chain entitlement changes to the cache as they happen
+    tokio::spawn(service.event_subscriber().run());
+    info!("📡 Polling entitlement events every {}s", service.config().event_poll_interval);
+
+    let mut gateway = Gateway::new(service, &gateway_config)?;
+
+    // Meter JSON-RPC services per method, with the costs from their listing
+    if let Some(catalog_url) = &gateway_config.catalog_url {
+        let service_id = &gateway_config.service_id;
+        let catalog = CatalogClient::new(catalog_url);
+        let listing = catalog.get_service(service_id).await?;
+        if listing.is_jsonrpc() {
+            let costs = Arc::new(RwLock::new(listing.jsonrpc_costs()?));
+            gateway = gateway.with_jsonrpc_costs(costs.clone());
+            let interval = Duration::from_secs(gateway_config.catalog_refresh_interval);
+            tokio::spawn(catalog.refresh_costs(service_id.clone(), costs, interval));
+            info!("🧮 Metering JSON-RPC calls to {} per method", service_id);
+        }
+    }
+
+    // Start HTTP server
+    let addr = format!("[::]:{}", gateway_config.listen_port).parse()?;
+    info!("🌐 Proxying {} on {}", gateway_config.upstream_url, addr);
+
+    axum::Server::bind(&addr)
+        .serve(gateway.router().into_make_service())
+        .await?;
+
+    Ok(())
+}
@@ -1,20 +1,55 @@
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use ethers::providers::{Provider, Http};
-use ethers::types::{Address, U256};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::DeserializeOwned;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use prometheus::HistogramVec;
+use std::sync::atomic::{AtomicU64, Ordering};
 use thiserror::Error;
+use tokio::sync::OnceCell;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Entitlement {
-    pub id: String,
-    pub service_id: String,
-    pub buyer: String,
-    pub tier_id: u64,
-    pub quota_requests: u64,
-    pub quota_used: u64,
-    pub purchased_at: u64,
-    pub expires_at: u64,
-    pub active: bool,
+use inframint_types::move_layout::EntitlementFields;
+
+use crate::signature::{SignatureError, SuiKeyPair, SuiSignature};
+use crate::transaction::{
+    self, Argument, CallArg, Command, GasData, ObjectArg, ObjectRef, ProgrammableMoveCall,
+    ProgrammableTransaction,
+};
+
+pub use inframint_types::{service_id_bytes, Entitlement, PricingTier};
+
+/// Position in the event stream, as used by `suix_queryEvents` cursors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventId {
+    pub tx_digest: String,
+    pub event_seq: String,
+}
+
+/// Events emitted by `inframint::entitlements` that change an entitlement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntitlementEvent {
+    Purchased { entitlement_id: String, buyer: String, tier_id: u64, amount_paid: u64 },
+    Consumed { entitlement_id: String, amount: u64, remaining: u64 },
+    Deactivated { entitlement_id: String, reason: String },
+}
+
+impl EntitlementEvent {
+    pub fn entitlement_id(&self) -> &str {
+        match self {
+            Self::Purchased { entitlement_id, .. }
+            | Self::Consumed { entitlement_id, .. }
+            | Self::Deactivated { entitlement_id, .. } => entitlement_id,
+        }
+    }
+}
+
+/// One page of [`SuiBlockchainClient::query_entitlement_events`]. Other
+/// events of the module are skipped but still advance `next_cursor`.
+#[derive(Debug, Clone)]
+pub struct EventPage {
+    pub events: Vec<EntitlementEvent>,
+    pub next_cursor: Option<EventId>,
+    pub has_next_page: bool,
 }
 
 #[derive(Error, Debug)]
@@ -30,91 +65,695 @@ pub enum BlockchainError {
 
     #[error("Entitlement not found")]
     EntitlementNotFound,
+
+    #[error("RPC error {code}: {message}")]
+    RpcError { code: i64, message: String },
+
+    #[error("Unexpected object type: {0}")]
+    UnexpectedObjectType(String),
+
+    #[error("Signature error: {0}")]
+    SignatureError(#[from] SignatureError),
+
+    #[error("Transaction signer is not configured")]
+    SignerNotConfigured,
+
+    #[error("Service registry is not configured")]
+    RegistryNotConfigured,
+
+    #[error("No gas coin with at least {0} MIST")]
+    InsufficientGas(u64),
+
+    #[error("Move abort in {module} with code {code}")]
+    MoveAbort { module: String, code: u64 },
+
+    #[error("Transaction failed: {0}")]
+    TransactionFailed(String),
+}
+
+// Abort codes raised by `inframint::entitlements`.
+pub const E_NOT_SERVICE_OWNER: u64 = 1;
+pub const E_TIER_NOT_ACTIVE: u64 = 2;
+pub const E_SERVICE_NOT_REGISTERED: u64 = 3;
+pub const E_INSUFFICIENT_PAYMENT: u64 = 4;
+pub const E_ENTITLEMENT_EXPIRED: u64 = 5;
+pub const E_QUOTA_EXCEEDED: u64 = 6;
+pub const E_NOT_AUTHORIZED: u64 = 7;
+pub const E_ENTITLEMENT_INACTIVE: u64 = 8;
+
+pub const ENTITLEMENT_MODULE: &str = "entitlements";
+/// Most objects a fullnode returns from one `sui_multiGetObjects` call.
+pub const MULTI_GET_OBJECTS_LIMIT: usize = 50;
+const ENTITLEMENT_STRUCT: &str = "Entitlement";
+const CONSUME_FUNCTION: &str = "consume_entitlement";
+
+const CLOCK_OBJECT_ID: &str = "0x6";
+const CLOCK_INITIAL_SHARED_VERSION: u64 = 1;
+const SUI_COIN_TYPE: &str = "0x2::sui::SUI";
+const TIER_INFO_FUNCTION: &str = "get_tier_info";
+
+/// Sender for read-only `devInspect` calls; no signature or gas is involved.
+const INSPECT_SENDER: &str = "0x0";
+const PURCHASED_EVENT: &str = "EntitlementPurchased";
+const CONSUMED_EVENT: &str = "EntitlementConsumed";
+const DEACTIVATED_EVENT: &str = "EntitlementDeactivated";
+
+/// Key and `ValidatorCap` used to submit `consume_entitlement` transactions.
+pub struct ValidatorSigner {
+    pub keypair: SuiKeyPair,
+    pub validator_cap_id: String,
+    pub gas_budget: u64,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SuiObjectResponse {
+    data: Option<SuiObjectData>,
+    error: Option<SuiObjectResponseError>,
+}
+
+#[derive(Deserialize)]
+struct SuiObjectResponseError {
+    code: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuiObjectData {
+    object_id: String,
+    version: Option<String>,
+    digest: Option<String>,
+    owner: Option<Value>,
+    content: Option<SuiParsedData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CoinPage {
+    data: Vec<Coin>,
+    next_cursor: Option<String>,
+    has_next_page: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Coin {
+    coin_object_id: String,
+    #[serde(deserialize_with = "deserialize_u64")]
+    version: u64,
+    digest: String,
+    #[serde(deserialize_with = "deserialize_u64")]
+    balance: u64,
+}
+
+#[derive(Deserialize)]
+struct TransactionBlockResponse {
+    digest: String,
+    effects: Option<TransactionEffects>,
+}
+
+#[derive(Deserialize)]
+struct TransactionEffects {
+    status: ExecutionStatus,
+}
+
+#[derive(Deserialize)]
+struct ExecutionStatus {
+    status: String,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DevInspectResults {
+    effects: TransactionEffects,
+    #[serde(default)]
+    results: Vec<DevInspectResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevInspectResult {
+    #[serde(default)]
+    return_values: Vec<(Vec<u8>, String)>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuiEventPage {
+    data: Vec<SuiEvent>,
+    next_cursor: Option<EventId>,
+    has_next_page: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuiEvent {
+    id: EventId,
+    #[serde(rename = "type")]
+    type_: String,
+    parsed_json: Value,
+}
+
+#[derive(Deserialize)]
+struct PurchasedFields {
+    entitlement_id: String,
+    buyer: String,
+    #[serde(deserialize_with = "deserialize_u64")]
+    tier_id: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    amount_paid: u64,
+}
+
+#[derive(Deserialize)]
+struct ConsumedFields {
+    entitlement_id: String,
+    #[serde(deserialize_with = "deserialize_u64")]
+    amount: u64,
+    #[serde(deserialize_with = "deserialize_u64")]
+    remaining: u64,
+}
+
+#[derive(Deserialize)]
+struct DeactivatedFields {
+    entitlement_id: String,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "dataType", rename_all = "camelCase")]
+enum SuiParsedData {
+    MoveObject {
+        #[serde(rename = "type")]
+        type_: String,
+        fields: Value,
+    },
+    #[serde(other)]
+    Package,
+}
+
+/// Builds the transaction input for an object from its `showOwner` response.
+fn object_arg(data: &SuiObjectData, mutable: bool) -> Result<ObjectArg, BlockchainError> {
+    let shared_version = data.owner.as_ref()
+        .and_then(|owner| owner.get("Shared"))
+        .and_then(|shared| shared.get("initial_shared_version"));
+
+    if let Some(version) = shared_version {
+        return Ok(ObjectArg::SharedObject {
+            id: transaction::parse_object_id(&data.object_id)?,
+            initial_shared_version: deserialize_u64(version.clone())
+                .map_err(|_| BlockchainError::InvalidResponseFormat)?,
+            mutable,
+        });
+    }
+
+    let version = data.version.as_deref()
+        .and_then(|v| v.parse().ok())
+        .ok_or(BlockchainError::InvalidResponseFormat)?;
+    let digest = data.digest.as_deref().ok_or(BlockchainError::InvalidResponseFormat)?;
+    Ok(ObjectArg::ImmOrOwnedObject(ObjectRef::parse(&data.object_id, version, digest)?))
+}
+
+fn bcs_value<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BlockchainError> {
+    bcs::from_bytes(bytes).map_err(|_| BlockchainError::InvalidResponseFormat)
+}
+
+/// Sui renders `u64` Move values as JSON strings; accept plain numbers too.
+fn deserialize_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        Value::Number(n) => n.as_u64().ok_or_else(|| serde::de::Error::custom("expected u64")),
+        other => Err(serde::de::Error::custom(format!("expected u64, got {}", other))),
+    }
+}
+
+/// Extracts the module and abort code from an execution error such as
+/// `MoveAbort(MoveLocation { module: ModuleId { .., name: Identifier("entitlements") }, .. }, 6) in command 0`.
+pub fn parse_move_abort(error: &str) -> Option<(String, u64)> {
+    let abort = &error[error.find("MoveAbort(")?..];
+    let module = abort.split("Identifier(\"").nth(1)?.split('"').next()?.to_string();
+    let end = abort.find(") in command").or_else(|| abort.rfind(')'))?;
+    let code = abort[..end].rsplit(',').next()?.trim().parse().ok()?;
+
+    Some((module, code))
+}
+
+/// Normalizes a Sui address or object ID to `0x` + 64 lowercase hex characters.
+pub fn normalize_sui_address(address: &str) -> Option<String> {
+    let hex_part = address.strip_prefix("0x").unwrap_or(address);
+    if hex_part.is_empty() || hex_part.len() > 64 || !hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(format!("0x{:0>64}", hex_part.to_ascii_lowercase()))
 }
 
 pub struct SuiBlockchainClient {
-    provider: Arc<Provider<Http>>,
-    contract_address: Address,
+    http: reqwest::Client,
+    rpc_url: String,
+    package_id: String,
+    signer: Option<ValidatorSigner>,
+    registry_id: Option<String>,
+    registry_arg: OnceCell<ObjectArg>,
+    next_request_id: AtomicU64,
+    rpc_duration: Option<HistogramVec>,
 }
 
 impl SuiBlockchainClient {
     pub fn new(rpc_url: &str, contract_address: &str) -> Result<Self, BlockchainError> {
-        let provider = Provider::<Http>::try_from(rpc_url)
-            .map_err(|e| BlockchainError::ProviderError(e.to_string()))?;
+        let package_id = normalize_sui_address(contract_address)
+            .ok_or_else(|| BlockchainError::ProviderError(format!("Invalid package ID: {}", contract_address)))?;
 
-        let contract_address = contract_address.parse::<Address>()
+        let http = reqwest::Client::builder()
+            .build()
             .map_err(|e| BlockchainError::ProviderError(e.to_string()))?;
 
         Ok(Self {
-            provider: Arc::new(provider),
-            contract_address,
+            http,
+            rpc_url: rpc_url.to_string(),
+            package_id,
+            signer: None,
+            registry_id: None,
+            registry_arg: OnceCell::new(),
+            next_request_id: AtomicU64::new(1),
+            rpc_duration: None,
         })
     }
 
+    pub fn with_signer(mut self, signer: ValidatorSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Sets the shared `ServiceRegistry` object used for tier lookups.
+    pub fn with_registry(mut self, registry_id: &str) -> Self {
+        self.registry_id = Some(registry_id.to_string());
+        self
+    }
+
+    /// Times each JSON-RPC call in `histogram`, labelled by method.
+    pub fn with_rpc_duration(mut self, histogram: HistogramVec) -> Self {
+        self.rpc_duration = Some(histogram);
+        self
+    }
+
+    pub fn package_id(&self) -> &str {
+        &self.package_id
+    }
+
     pub async fn get_entitlement(&self, entitlement_id: &str) -> Result<Entitlement, BlockchainError> {
-        // Convert entitlement_id to proper format for Sui
-        let entitlement_address = entitlement_id.parse::<Address>()
-            .map_err(|_| BlockchainError::EntitlementNotFound)?;
-
-        // Call Sui JSON-RPC to get the entitlement object
-        let response = self.provider.get_storage_at(
-            self.contract_address,
-            U256::from(entitlement_address),
-            None
-        ).await
-        .map_err(|e| BlockchainError::ContractCallError(e.to_string()))?;
-
-        // Parse the response into Entitlement struct
-        let entitlement_data = serde_json::from_value(response)
-            .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+        let object_id = normalize_sui_address(entitlement_id)
+            .ok_or(BlockchainError::EntitlementNotFound)?;
 
-        Ok(entitlement_data)
+        let response: SuiObjectResponse = self.call(
+            "sui_getObject",
+            json!([object_id, { "showContent": true, "showType": true }]),
+        ).await?;
+
+        self.decode_entitlement(response)
     }
 
-    pub async fn consume_entitlement(&self, entitlement_id: &str, amount: u64) -> Result<(), BlockchainError> {
-        // Prepare transaction data
-        let tx_data = serde_json::json!({
-            "method": "consume_entitlement",
-            "params": {
-                "entitlement_id": entitlement_id,
-                "amount": amount
+    /// Fetches several entitlements with a single `sui_multiGetObjects` call
+    /// per [`MULTI_GET_OBJECTS_LIMIT`] IDs.
+    ///
+    /// Results are returned in request order; per-object failures (unknown or
+    /// malformed IDs, wrong object type) are reported in place.
+    pub async fn get_entitlements(
+        &self,
+        entitlement_ids: &[String],
+    ) -> Result<Vec<Result<Entitlement, BlockchainError>>, BlockchainError> {
+        let object_ids: Vec<Option<String>> = entitlement_ids.iter()
+            .map(|id| normalize_sui_address(id))
+            .collect();
+        let valid_ids: Vec<&String> = object_ids.iter().flatten().collect();
+
+        let mut responses = Vec::with_capacity(valid_ids.len());
+        for chunk in valid_ids.chunks(MULTI_GET_OBJECTS_LIMIT) {
+            let chunk_responses: Vec<SuiObjectResponse> = self.call(
+                "sui_multiGetObjects",
+                json!([chunk, { "showContent": true, "showType": true }]),
+            ).await?;
+
+            if chunk_responses.len() != chunk.len() {
+                return Err(BlockchainError::InvalidResponseFormat);
             }
-        });
+            responses.extend(chunk_responses);
+        }
+        let mut responses = responses.into_iter();
 
-        // Send transaction to Sui network
-        let tx_hash = self.provider.send_transaction(tx_data)
-            .await
-            .map_err(|e| BlockchainError::ContractCallError(e.to_string()))?;
+        Ok(object_ids.iter()
+            .map(|id| match id {
+                Some(_) => responses.next()
+                    .ok_or(BlockchainError::InvalidResponseFormat)
+                    .and_then(|response| self.decode_entitlement(response)),
+                None => Err(BlockchainError::EntitlementNotFound),
+            })
+            .collect())
+    }
 
-        // Wait for transaction confirmation
-        let receipt = self.provider.get_transaction_receipt(tx_hash)
-            .await
-            .map_err(|e| BlockchainError::ContractCallError(e.to_string()))?;
+    /// Calls `consume_entitlement(&ValidatorCap, &mut Entitlement, amount, &Clock)`
+    /// in a signed programmable transaction and returns its digest.
+    ///
+    /// The entitlement must be usable by the validator's address, i.e. shared
+    /// or owned by it; the fullnode rejects the transaction otherwise.
+    pub async fn consume_entitlement(&self, entitlement_id: &str, amount: u64) -> Result<String, BlockchainError> {
+        let signer = self.signer.as_ref().ok_or(BlockchainError::SignerNotConfigured)?;
+        let sender = signer.keypair.address();
+
+        let object_ids = vec![
+            normalize_sui_address(&signer.validator_cap_id).ok_or(BlockchainError::InvalidResponseFormat)?,
+            normalize_sui_address(entitlement_id).ok_or(BlockchainError::EntitlementNotFound)?,
+        ];
+        let objects: Vec<SuiObjectResponse> = self.call(
+            "sui_multiGetObjects",
+            json!([object_ids, { "showOwner": true }]),
+        ).await?;
+        let [cap, entitlement]: [SuiObjectResponse; 2] = objects.try_into()
+            .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+        let cap = cap.data.ok_or_else(|| BlockchainError::ContractCallError(
+            format!("ValidatorCap {} not found", signer.validator_cap_id)
+        ))?;
+        let entitlement = entitlement.data.ok_or(BlockchainError::EntitlementNotFound)?;
+
+        let programmable = ProgrammableTransaction {
+            inputs: vec![
+                CallArg::Object(object_arg(&cap, false)?),
+                CallArg::Object(object_arg(&entitlement, true)?),
+                CallArg::pure_u64(amount),
+                CallArg::Object(ObjectArg::SharedObject {
+                    id: transaction::parse_object_id(CLOCK_OBJECT_ID)?,
+                    initial_shared_version: CLOCK_INITIAL_SHARED_VERSION,
+                    mutable: false,
+                }),
+            ],
+            commands: vec![Command::MoveCall(Box::new(ProgrammableMoveCall {
+                package: transaction::parse_object_id(&self.package_id)?,
+                module: ENTITLEMENT_MODULE.to_string(),
+                function: CONSUME_FUNCTION.to_string(),
+                type_arguments: vec![],
+                arguments: (0..4).map(Argument::Input).collect(),
+            }))],
+        };
+
+        let gas_data = GasData {
+            payment: vec![self.select_gas_coin(&sender, signer.gas_budget).await?],
+            owner: transaction::parse_object_id(&sender)?,
+            price: self.reference_gas_price().await?,
+            budget: signer.gas_budget,
+        };
+
+        let tx_bytes = transaction::transaction_bytes(
+            programmable,
+            transaction::parse_object_id(&sender)?,
+            gas_data,
+        )?;
+        let signature = signer.keypair.sign_transaction(&tx_bytes);
 
-        if !receipt.status.is_success() {
-            return Err(BlockchainError::ContractCallError(
-                receipt.status.error_message().unwrap_or("Transaction failed").to_string()
-            ));
+        let response: TransactionBlockResponse = self.call(
+            "sui_executeTransactionBlock",
+            json!([
+                BASE64.encode(&tx_bytes),
+                [signature.to_base64()],
+                { "showEffects": true },
+                "WaitForLocalExecution",
+            ]),
+        ).await?;
+
+        let status = response.effects
+            .ok_or(BlockchainError::InvalidResponseFormat)?
+            .status;
+        if status.status != "success" {
+            let error = status.error.unwrap_or(status.status);
+            return Err(match parse_move_abort(&error) {
+                Some((module, code)) => BlockchainError::MoveAbort { module, code },
+                None => BlockchainError::TransactionFailed(error),
+            });
         }
 
-        Ok(())
+        Ok(response.digest)
     }
 
+    /// Reads a pricing tier through `get_tier_info` with a `devInspect` call,
+    /// which executes the function without submitting a transaction.
+    pub async fn get_tier_info(&self, service_id: &[u8], tier_id: u64) -> Result<PricingTier, BlockchainError> {
+        let registry = self.registry_arg().await?;
+
+        let programmable = ProgrammableTransaction {
+            inputs: vec![
+                CallArg::Object(registry),
+                CallArg::pure_bytes(service_id)?,
+                CallArg::pure_u64(tier_id),
+            ],
+            commands: vec![Command::MoveCall(Box::new(ProgrammableMoveCall {
+                package: transaction::parse_object_id(&self.package_id)?,
+                module: ENTITLEMENT_MODULE.to_string(),
+                function: TIER_INFO_FUNCTION.to_string(),
+                type_arguments: vec![],
+                arguments: (0..3).map(Argument::Input).collect(),
+            }))],
+        };
+        let tx_bytes = transaction::transaction_kind_bytes(programmable)?;
+
+        let inspection: DevInspectResults = self.call(
+            "sui_devInspectTransactionBlock",
+            json!([
+                normalize_sui_address(INSPECT_SENDER),
+                BASE64.encode(&tx_bytes),
+                null,
+                null,
+            ]),
+        ).await?;
+
+        let status = inspection.effects.status;
+        if status.status != "success" {
+            let error = status.error.unwrap_or(status.status);
+            return Err(match parse_move_abort(&error) {
+                Some((module, code)) => BlockchainError::MoveAbort { module, code },
+                None => BlockchainError::TransactionFailed(error),
+            });
+        }
+
+        let values = inspection.results.into_iter()
+            .next()
+            .map(|result| result.return_values)
+            .unwrap_or_default();
+        let [price, quota, validity, rate_limit, active]: [(Vec<u8>, String); 5] = values.try_into()
+            .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+
+        Ok(PricingTier {
+            price_sui: bcs_value(&price.0)?,
+            quota_requests: bcs_value(&quota.0)?,
+            validity_period_ms: bcs_value(&validity.0)?,
+            rate_limit_per_second: bcs_value(&rate_limit.0)?,
+            active: bcs_value(&active.0)?,
+        })
+    }
+
+    /// Checks that `signature` is a Sui serialized signature over the personal
+    /// message `message`, produced by the entitlement's buyer.
     pub async fn validate_entitlement_signature(
         &self,
         entitlement_id: &str,
         signature: &str,
         message: &str
     ) -> Result<bool, BlockchainError> {
-        // Verify the signature against the entitlement owner's address
+        let signature = SuiSignature::from_base64(signature)?;
         let entitlement = self.get_entitlement(entitlement_id).await?;
-        let owner_address = entitlement.buyer.parse::<Address>()
+
+        let owner_address = normalize_sui_address(&entitlement.buyer)
+            .ok_or(BlockchainError::InvalidResponseFormat)?;
+        if signature.signer_address() != owner_address {
+            return Ok(false);
+        }
+
+        Ok(signature.verify_personal_message(message.as_bytes())?)
+    }
+
+    fn entitlement_type(&self) -> String {
+        format!("{}::{}::{}", self.package_id, ENTITLEMENT_MODULE, ENTITLEMENT_STRUCT)
+    }
+
+    fn decode_entitlement(&self, response: SuiObjectResponse) -> Result<Entitlement, BlockchainError> {
+        if let Some(error) = response.error {
+            return match error.code.as_str() {
+                "notExists" | "deleted" => Err(BlockchainError::EntitlementNotFound),
+                other => Err(BlockchainError::ContractCallError(format!("Object error: {}", other))),
+            };
+        }
+
+        let data = response.data.ok_or(BlockchainError::EntitlementNotFound)?;
+        let (type_, fields) = match data.content {
+            Some(SuiParsedData::MoveObject { type_, fields }) => (type_, fields),
+            _ => return Err(BlockchainError::InvalidResponseFormat),
+        };
+
+        if type_ != self.entitlement_type() {
+            return Err(BlockchainError::UnexpectedObjectType(type_));
+        }
+
+        let fields: EntitlementFields = serde_json::from_value(fields)
             .map_err(|_| BlockchainError::InvalidResponseFormat)?;
 
-        // Use ethers-rs to verify the signature
-        let recovered_address = ethers::utils::verify_message(message, signature)
-            .map_err(|e| BlockchainError::ContractCallError(e.to_string()))?;
+        if normalize_sui_address(&fields.id.id) != normalize_sui_address(&data.object_id) {
+            return Err(BlockchainError::InvalidResponseFormat);
+        }
+
+        Ok(Entitlement {
+            id: data.object_id,
+            ..fields.into()
+        })
+    }
+
+    async fn registry_arg(&self) -> Result<ObjectArg, BlockchainError> {
+        let registry_id = self.registry_id.as_deref().ok_or(BlockchainError::RegistryNotConfigured)?;
+
+        // The registry is shared once at publish time, so its version never changes.
+        self.registry_arg.get_or_try_init(|| async {
+            let object_id = normalize_sui_address(registry_id)
+                .ok_or(BlockchainError::InvalidResponseFormat)?;
+            let response: SuiObjectResponse = self.call(
+                "sui_getObject",
+                json!([object_id, { "showOwner": true }]),
+            ).await?;
+            let data = response.data.ok_or_else(|| BlockchainError::ContractCallError(
+                format!("ServiceRegistry {} not found", registry_id)
+            ))?;
+
+            object_arg(&data, false)
+        }).await.cloned()
+    }
+
+    async fn select_gas_coin(&self, owner: &str, budget: u64) -> Result<ObjectRef, BlockchainError> {
+        let mut cursor: Option<String> = None;
+        loop {
+            let page: CoinPage = self.call(
+                "suix_getCoins",
+                json!([owner, SUI_COIN_TYPE, cursor, null]),
+            ).await?;
+
+            if let Some(coin) = page.data.into_iter().find(|coin| coin.balance >= budget) {
+                return ObjectRef::parse(&coin.coin_object_id, coin.version, &coin.digest);
+            }
+
+            match page.next_cursor {
+                Some(next) if page.has_next_page => cursor = Some(next),
+                _ => return Err(BlockchainError::InsufficientGas(budget)),
+            }
+        }
+    }
+
+    /// The chain the fullnode serves, e.g. `35834a8a` for mainnet.
+    pub async fn chain_identifier(&self) -> Result<String, BlockchainError> {
+        self.call("sui_getChainIdentifier", json!([])).await
+    }
+
+    /// Events of the entitlements module after `cursor`, oldest first.
+    pub async fn query_entitlement_events(
+        &self,
+        cursor: Option<&EventId>,
+        limit: usize,
+    ) -> Result<EventPage, BlockchainError> {
+        let page = self.query_events(cursor, limit, false).await?;
+
+        let events = page.data.iter()
+            .map(|event| self.decode_event(event))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EventPage {
+            events: events.into_iter().flatten().collect(),
+            next_cursor: page.next_cursor,
+            has_next_page: page.has_next_page,
+        })
+    }
+
+    /// The most recent event of the entitlements module, if there is any.
+    pub async fn latest_entitlement_event(&self) -> Result<Option<EventId>, BlockchainError> {
+        let page = self.query_events(None, 1, true).await?;
+        Ok(page.data.into_iter().next().map(|event| event.id))
+    }
+
+    async fn query_events(
+        &self,
+        cursor: Option<&EventId>,
+        limit: usize,
+        descending: bool,
+    ) -> Result<SuiEventPage, BlockchainError> {
+        let filter = json!({
+            "MoveEventModule": { "package": self.package_id, "module": ENTITLEMENT_MODULE },
+        });
+        self.call("suix_queryEvents", json!([filter, cursor, limit, descending])).await
+    }
+
+    /// Decodes the events the validator acts on; `None` for the rest.
+    fn decode_event(&self, event: &SuiEvent) -> Result<Option<EntitlementEvent>, BlockchainError> {
+        let prefix = format!("{}::{}::", self.package_id, ENTITLEMENT_MODULE);
+        let Some(name) = event.type_.strip_prefix(&prefix) else {
+            return Ok(None);
+        };
+
+        let fields = event.parsed_json.clone();
+        let decoded = match name {
+            PURCHASED_EVENT => serde_json::from_value(fields).map(|f: PurchasedFields| EntitlementEvent::Purchased {
+                entitlement_id: f.entitlement_id,
+                buyer: f.buyer,
+                tier_id: f.tier_id,
+                amount_paid: f.amount_paid,
+            }),
+            CONSUMED_EVENT => serde_json::from_value(fields).map(|f: ConsumedFields| EntitlementEvent::Consumed {
+                entitlement_id: f.entitlement_id,
+                amount: f.amount,
+                remaining: f.remaining,
+            }),
+            DEACTIVATED_EVENT => serde_json::from_value(fields).map(|f: DeactivatedFields| EntitlementEvent::Deactivated {
+                entitlement_id: f.entitlement_id,
+                reason: f.reason,
+            }),
+            _ => return Ok(None),
+        };
+
+        decoded.map(Some).map_err(|_| BlockchainError::InvalidResponseFormat)
+    }
+
+    async fn reference_gas_price(&self) -> Result<u64, BlockchainError> {
+        let price: Value = self.call("suix_getReferenceGasPrice", json!([])).await?;
+        deserialize_u64(price).map_err(|_| BlockchainError::InvalidResponseFormat)
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, BlockchainError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_request_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let _timer = self.rpc_duration.as_ref().map(|h| h.with_label_values(&[method]).start_timer());
+
+        let response = self.http.post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| BlockchainError::ProviderError(e.to_string()))?
+            .error_for_status()
+            .map_err(|e| BlockchainError::ProviderError(e.to_string()))?;
+
+        let body: JsonRpcResponse<T> = response.json()
+            .await
+            .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+
+        if let Some(error) = body.error {
+            return Err(BlockchainError::RpcError { code: error.code, message: error.message });
+        }
 
-        Ok(recovered_address == owner_address)
+        body.result.ok_or(BlockchainError::InvalidResponseFormat)
     }
 }
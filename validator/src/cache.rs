@@ -1,59 +1,359 @@
+//! Entitlement cache in two layers: a small in-process LRU with a short TTL
+//! in front of Redis, which is shared by every replica.
+//!
+//! The local layer saves a Redis round trip and a JSON decode on hot
+//! entitlements. Its TTL bounds how long a replica may serve an entry that
+//! another replica has invalidated; quota usage is read from the metering
+//! counters, not from cached entries, so staleness only delays status
+//! changes.
+
+use lru::LruCache;
+use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+use crate::blockchain::Entitlement;
+use crate::clock::UnixMillis;
+use crate::error::ValidatorError;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An entitlement together with its tier's rate limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CachedEntitlement {
-    pub id: String,
-    pub service_id: String,
-    pub buyer: String,
-    pub quota_requests: u64,
-    pub quota_used: u64,
-    pub expires_at: u64,
-    pub active: bool,
+    #[serde(flatten)]
+    pub entitlement: Entitlement,
+    /// `rate_limit_per_second` of the entitlement's tier; 0 if unknown.
+    #[serde(default)]
     pub rate_limit_per_second: u32,
 }
 
+impl From<Entitlement> for CachedEntitlement {
+    fn from(entitlement: Entitlement) -> Self {
+        Self {
+            entitlement,
+            rate_limit_per_second: 0,
+        }
+    }
+}
+
+impl From<CachedEntitlement> for Entitlement {
+    fn from(cached: CachedEntitlement) -> Self {
+        cached.entitlement
+    }
+}
+
+/// Checks and debits quota in one step so concurrent consumers, including
+/// other validator replicas, cannot overdraw an entitlement.
+///
+/// KEYS: used counter, pending counter, dirty set.
+/// ARGV: entitlement id, on-chain `quota_used`, `quota_requests`, time
+/// until which the used counter is kept (ms), amount.
+///
+/// The counter is read back with the on-chain usage as fallback, so a keep
+/// time already past, which expires it at once, cannot fail the debit.
+///
+/// Returns `{1, remaining, pending}` on success and `{0, remaining, pending}`
+/// when the debit would exceed the quota.
+const DEBIT_QUOTA_SCRIPT: &str = r"
+redis.call('SET', KEYS[1], ARGV[2], 'NX')
+redis.call('PEXPIREAT', KEYS[1], ARGV[4])
+
+local used = tonumber(redis.call('GET', KEYS[1]) or ARGV[2])
+local quota = tonumber(ARGV[3])
+local amount = tonumber(ARGV[5])
+
+if used + amount > quota then
+    local pending = tonumber(redis.call('GET', KEYS[2]) or '0')
+    return {0, math.max(quota - used, 0), pending}
+end
+
+used = redis.call('INCRBY', KEYS[1], amount)
+local pending = redis.call('INCRBY', KEYS[2], amount)
+redis.call('SADD', KEYS[3], ARGV[1])
+return {1, quota - used, pending}
+";
+
+/// Result of [`EntitlementCache::debit_quota`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaDebit {
+    Debited { remaining: u64, pending: u64 },
+    Exceeded { remaining: u64 },
+}
+
+/// Hit and miss counts since the cache was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub local_hits: u64,
+    pub redis_hits: u64,
+    pub misses: u64,
+    /// Lookups that went to the loader; at most one per key at a time.
+    pub loads: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    local_hits: AtomicU64,
+    redis_hits: AtomicU64,
+    misses: AtomicU64,
+    loads: AtomicU64,
+}
+
+struct LocalEntry {
+    entitlement: CachedEntitlement,
+    expires: Instant,
+}
+
+type Load = Arc<OnceCell<Option<CachedEntitlement>>>;
+
+#[derive(Clone)]
 pub struct EntitlementCache {
-    client: redis::Client,
+    conn: ConnectionManager,
     ttl: Duration,
+    local: Arc<Mutex<LruCache<String, LocalEntry>>>,
+    local_ttl: Duration,
+    /// Loads in progress, so concurrent misses share one lookup.
+    loads: Arc<Mutex<HashMap<String, Load>>>,
+    counters: Arc<Counters>,
+    debit_script: redis::Script,
 }
 
 impl EntitlementCache {
+    pub const DEFAULT_LOCAL_CAPACITY: usize = 10_000;
+    pub const DEFAULT_LOCAL_TTL: Duration = Duration::from_secs(5);
+
     pub async fn new(redis_url: &str, ttl_seconds: u64) -> Result<Self, redis::RedisError> {
         let client = redis::Client::open(redis_url)?;
+        let conn = ConnectionManager::new(client).await?;
         let ttl = Duration::from_secs(ttl_seconds);
 
-        Ok(Self { client, ttl })
+        Ok(Self {
+            conn,
+            ttl,
+            local: Arc::new(Mutex::new(LruCache::new(local_capacity(Self::DEFAULT_LOCAL_CAPACITY)))),
+            local_ttl: Self::DEFAULT_LOCAL_TTL,
+            loads: Arc::new(Mutex::new(HashMap::new())),
+            counters: Arc::new(Counters::default()),
+            debit_script: redis::Script::new(DEBIT_QUOTA_SCRIPT),
+        })
+    }
+
+    /// Holds up to `capacity` entries in process for `ttl` each. A zero TTL
+    /// disables the local layer.
+    pub fn with_local_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.local = Arc::new(Mutex::new(LruCache::new(local_capacity(capacity))));
+        self.local_ttl = ttl;
+        self
+    }
+
+    /// Connection shared by every user of this cache. `ConnectionManager`
+    /// multiplexes requests and reconnects after failures.
+    pub(crate) fn connection(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    /// How long entries stay in Redis.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            local_hits: self.counters.local_hits.load(Ordering::Relaxed),
+            redis_hits: self.counters.redis_hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            loads: self.counters.loads.load(Ordering::Relaxed),
+        }
     }
 
     pub async fn get(&self, entitlement_id: &str) -> Result<Option<CachedEntitlement>, redis::RedisError> {
-        let mut conn = self.client.get_async_connection().await?;
-        let key = format!("ent:{}", entitlement_id);
+        if let Some(entitlement) = self.get_local(entitlement_id) {
+            self.counters.local_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(entitlement));
+        }
 
-        let data: Option<String> = conn.get(&key).await?;
-        match data {
-            Some(data) => {
-                let entitlement: CachedEntitlement = serde_json::from_str(&data)?;
+        match self.get_remote(entitlement_id).await? {
+            Some(entitlement) => {
+                self.counters.redis_hits.fetch_add(1, Ordering::Relaxed);
+                self.set_local(entitlement_id, &entitlement);
                 Ok(Some(entitlement))
             }
-            None => Ok(None),
+            None => {
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the cached entitlement, calling `load` on a miss and caching
+    /// what it returns.
+    ///
+    /// Concurrent misses for the same entitlement share a single call to
+    /// `load`. If it fails, each waiter gets its turn to retry.
+    pub async fn get_or_load<F, Fut>(&self, entitlement_id: &str, load: F) -> Result<Option<CachedEntitlement>, ValidatorError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<CachedEntitlement>, ValidatorError>>,
+    {
+        if let Some(entitlement) = self.get(entitlement_id).await? {
+            return Ok(Some(entitlement));
+        }
+
+        let cell = self.loads.lock().unwrap()
+            .entry(entitlement_id.to_string())
+            .or_default()
+            .clone();
+
+        let result = cell.get_or_try_init(|| async {
+            // A load that finished between our miss and joining the map
+            if let Some(entitlement) = self.get_remote(entitlement_id).await? {
+                self.set_local(entitlement_id, &entitlement);
+                return Ok(Some(entitlement));
+            }
+
+            self.counters.loads.fetch_add(1, Ordering::Relaxed);
+            let entitlement = load().await?;
+            if let Some(entitlement) = &entitlement {
+                self.set(entitlement_id.to_string(), entitlement.clone()).await?;
+            }
+            Ok(entitlement)
+        }).await.cloned();
+
+        let mut loads = self.loads.lock().unwrap();
+        if loads.get(entitlement_id).is_some_and(|current| Arc::ptr_eq(current, &cell)) {
+            loads.remove(entitlement_id);
         }
+        result
     }
 
     pub async fn set(&self, entitlement_id: String, entitlement: CachedEntitlement) -> Result<(), redis::RedisError> {
-        let mut conn = self.client.get_async_connection().await?;
-        let key = format!("ent:{}", entitlement_id);
-        let data = serde_json::to_string(&entitlement)?;
+        let data = serde_json::to_string(&entitlement).map_err(invalid_payload)?;
+        self.connection().set_ex::<_, _, ()>(entitlement_key(&entitlement_id), data, self.ttl.as_secs()).await?;
 
-        conn.set_ex(&key, data, self.ttl.as_secs() as usize).await?;
+        self.set_local(&entitlement_id, &entitlement);
         Ok(())
     }
 
+    /// Atomically debits `amount` from the entitlement's quota.
+    ///
+    /// The usage counter is seeded from `entitlement.quota_used` the first
+    /// time the entitlement is debited and is kept until `keep_until`. The
+    /// debited units are also queued for settlement.
+    pub async fn debit_quota(
+        &self,
+        entitlement: &Entitlement,
+        amount: u64,
+        keep_until: UnixMillis,
+    ) -> Result<QuotaDebit, redis::RedisError> {
+        let mut conn = self.connection();
+
+        let (debited, remaining, pending): (bool, u64, u64) = self.debit_script
+            .key(used_key(&entitlement.id))
+            .key(pending_key(&entitlement.id))
+            .key(DIRTY_KEY)
+            .arg(&entitlement.id)
+            .arg(entitlement.quota_used)
+            .arg(entitlement.quota_requests)
+            .arg(keep_until.as_millis())
+            .arg(amount)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(if debited {
+            QuotaDebit::Debited { remaining, pending }
+        } else {
+            QuotaDebit::Exceeded { remaining }
+        })
+    }
+
+    /// Raises the cached `quota_used` to match `remaining` from an
+    /// `EntitlementConsumed` event. Entries that are not cached stay that
+    /// way, so a concurrent invalidation is never undone.
+    pub async fn update_remaining(&self, entitlement_id: &str, remaining: u64) -> Result<(), redis::RedisError> {
+        let Some(mut cached) = self.get_remote(entitlement_id).await? else {
+            self.local.lock().unwrap().pop(entitlement_id);
+            return Ok(());
+        };
+        let entitlement = &mut cached.entitlement;
+        entitlement.quota_used = entitlement.quota_used.max(entitlement.quota_requests.saturating_sub(remaining));
+
+        let data = serde_json::to_string(&cached).map_err(invalid_payload)?;
+        let updated: Option<String> = redis::cmd("SET")
+            .arg(entitlement_key(entitlement_id))
+            .arg(data)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query_async(&mut self.connection())
+            .await?;
+
+        if updated.is_some() {
+            self.set_local(entitlement_id, &cached);
+        } else {
+            self.local.lock().unwrap().pop(entitlement_id);
+        }
+        Ok(())
+    }
+
+    /// Drops the entry from Redis and from this process. Other replicas may
+    /// serve their local copy until it expires.
     pub async fn invalidate(&self, entitlement_id: &str) -> Result<(), redis::RedisError> {
-        let mut conn = self.client.get_async_connection().await?;
-        let key = format!("ent:{}", entitlement_id);
-        conn.del(&key).await?;
+        self.local.lock().unwrap().pop(entitlement_id);
+        self.connection().del::<_, ()>(entitlement_key(entitlement_id)).await?;
         Ok(())
     }
+
+    fn get_local(&self, entitlement_id: &str) -> Option<CachedEntitlement> {
+        let mut local = self.local.lock().unwrap();
+        match local.get(entitlement_id) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.entitlement.clone()),
+            Some(_) => {
+                local.pop(entitlement_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn set_local(&self, entitlement_id: &str, entitlement: &CachedEntitlement) {
+        if self.local_ttl.is_zero() {
+            return;
+        }
+        self.local.lock().unwrap().put(entitlement_id.to_string(), LocalEntry {
+            entitlement: entitlement.clone(),
+            expires: Instant::now() + self.local_ttl,
+        });
+    }
+
+    async fn get_remote(&self, entitlement_id: &str) -> Result<Option<CachedEntitlement>, redis::RedisError> {
+        let data: Option<String> = self.connection().get(entitlement_key(entitlement_id)).await?;
+        data.map(|data| serde_json::from_str(&data).map_err(invalid_payload))
+            .transpose()
+    }
+}
+
+fn entitlement_key(entitlement_id: &str) -> String {
+    format!("ent:{}", entitlement_id)
+}
+
+fn local_capacity(capacity: usize) -> NonZeroUsize {
+    NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)
+}
+
+pub(crate) fn used_key(entitlement_id: &str) -> String {
+    format!("meter:used:{}", entitlement_id)
+}
+
+pub(crate) fn pending_key(entitlement_id: &str) -> String {
+    format!("meter:pending:{}", entitlement_id)
+}
+
+pub(crate) const DIRTY_KEY: &str = "meter:dirty";
+
+fn invalid_payload(err: serde_json::Error) -> redis::RedisError {
+    redis::RedisError::from((redis::ErrorKind::TypeError, "Invalid cached entitlement", err.to_string()))
 }
@@ -0,0 +1,83 @@
+//! The backend's service catalog, where providers describe their services
+//! and how requests to them are priced.
+
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::error::ValidatorError;
+use crate::jsonrpc::JsonRpcCostTable;
+
+/// A service as listed by `GET /api/v1/services/:id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceListing {
+    pub id: String,
+    #[serde(rename = "type_")]
+    pub service_type: String,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+impl ServiceListing {
+    /// JSON-RPC services are metered per method.
+    pub fn is_jsonrpc(&self) -> bool {
+        self.service_type == "rpc"
+    }
+
+    /// The cost table in the listing's metadata, or one charging 1 unit per
+    /// call if it has none.
+    pub fn jsonrpc_costs(&self) -> Result<JsonRpcCostTable, serde_json::Error> {
+        Ok(JsonRpcCostTable::from_metadata(&self.metadata)?.unwrap_or_default())
+    }
+}
+
+pub struct CatalogClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl CatalogClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn get_service(&self, service_id: &str) -> Result<ServiceListing, ValidatorError> {
+        let response = self.http
+            .get(format!("{}/api/v1/services/{}", self.base_url, service_id))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ValidatorError::NetworkError(e.to_string()))?;
+
+        response.json()
+            .await
+            .map_err(|e| ValidatorError::NetworkError(e.to_string()))
+    }
+
+    /// Reloads `costs` from the service's listing every `interval`. A failed
+    /// reload keeps the previous table.
+    pub async fn refresh_costs(self, service_id: String, costs: Arc<RwLock<JsonRpcCostTable>>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let table = self.get_service(&service_id)
+                .await
+                .and_then(|listing| listing.jsonrpc_costs().map_err(|e| ValidatorError::ConfigError(e.to_string())));
+            match table {
+                Ok(table) => {
+                    debug!("Reloaded JSON-RPC costs of {}", service_id);
+                    *costs.write().await = table;
+                }
+                Err(e) => warn!("Failed to reload JSON-RPC costs of {}: {}", service_id, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Wall-clock time for expiry checks and rate limiting, behind a trait so
+//! tests can control it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+pub use inframint_types::{UnixMillis, UnixSeconds};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> UnixMillis;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> UnixMillis {
+        UnixMillis::from_system_time(SystemTime::now())
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct FakeClock {
+    now: AtomicU64,
+}
+
+impl FakeClock {
+    pub fn new(now: UnixMillis) -> Self {
+        Self {
+            now: AtomicU64::new(now.as_millis()),
+        }
+    }
+
+    pub fn set(&self, now: UnixMillis) {
+        self.now.store(now.as_millis(), Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> UnixMillis {
+        UnixMillis(self.now.load(Ordering::SeqCst))
+    }
+}
@@ -1,16 +1,72 @@
 use serde::Deserialize;
-use std::env;
 use config::{Config, File, Environment, ConfigError};
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStore {
+    Memory,
+    Redis,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct ValidatorConfig {
     pub redis_url: String,
     pub sui_rpc_url: String,
     pub contract_address: String,
     pub cache_ttl: u64,
+    /// Entitlements held in process in front of Redis.
+    pub local_cache_capacity: usize,
+    /// Seconds an in-process entry is served before going back to Redis.
+    pub local_cache_ttl: u64,
+    /// Seconds between polls for on-chain entitlement events.
+    pub event_poll_interval: u64,
+    /// Milliseconds past `expires_at` during which an entitlement is still
+    /// accepted.
+    pub expiry_grace_period_ms: u64,
     pub rate_limit_window: u64,
     pub rate_limit_max: u64,
+    /// Where rate limit state lives; `redis` shares it across replicas.
+    pub rate_limit_store: RateLimitStore,
+    /// Seconds' worth of a tier's `rate_limit_per_second` admitted at once.
+    pub rate_limit_burst_factor: u64,
+    /// Shared `ServiceRegistry` object; without it every entitlement gets the
+    /// global rate limit.
+    pub registry_id: Option<String>,
     pub grpc_port: u16,
+    /// Port of the HTTP server exposing Prometheus metrics on `/metrics`.
+    pub metrics_port: u16,
+    /// Audience signed requests must name; shared by all replicas of a
+    /// deployment.
+    pub validator_id: String,
+    /// Milliseconds a signed request's `issued_at_ms` may be away from now.
+    pub signed_request_max_age_ms: u64,
+    /// Accept the free-form `signature`/`message` pair, which can be
+    /// replayed, alongside signed requests. Off by default; a temporary
+    /// switch for clients still migrating to signed requests, to be
+    /// removed with the legacy fields.
+    pub allow_legacy_signatures: bool,
+    /// `sui.keystore` entry (base64 of flag || key) of the Ed25519 key that
+    /// signs access tokens; shared by all replicas. Without it no tokens are
+    /// issued or accepted.
+    pub access_token_key: Option<String>,
+    /// Seconds an access token is valid for.
+    pub access_token_ttl: u64,
+    /// `sui.keystore` holding the key that owns the `ValidatorCap`.
+    pub keystore_path: Option<String>,
+    /// Address of the key to use when the keystore holds several.
+    pub validator_address: Option<String>,
+    pub validator_cap_id: Option<String>,
+    /// Gas budget for `consume_entitlement` transactions, in MIST.
+    pub gas_budget: u64,
+    /// Seconds between on-chain settlements of metered usage.
+    pub settle_interval: u64,
+    /// Pending units of a single entitlement that trigger an early settlement.
+    pub settle_threshold: u64,
+    /// Seconds between checks of Redis and the fullnode for the gRPC health
+    /// service.
+    pub health_check_interval: u64,
+    /// Most requests a batch RPC accepts.
+    pub max_batch_size: usize,
 }
 
 impl ValidatorConfig {
@@ -21,9 +77,90 @@ impl ValidatorConfig {
 
         // Set defaults
         builder = builder.set_default("cache_ttl", "300")?;
+        builder = builder.set_default("local_cache_capacity", "10000")?;
+        builder = builder.set_default("local_cache_ttl", "5")?;
+        builder = builder.set_default("event_poll_interval", "2")?;
+        builder = builder.set_default("expiry_grace_period_ms", "0")?;
         builder = builder.set_default("rate_limit_window", "60")?;
         builder = builder.set_default("rate_limit_max", "1000")?;
+        builder = builder.set_default("rate_limit_store", "redis")?;
+        builder = builder.set_default("rate_limit_burst_factor", "2")?;
         builder = builder.set_default("grpc_port", "50051")?;
+        builder = builder.set_default("metrics_port", "9090")?;
+        builder = builder.set_default("validator_id", "inframint-validator")?;
+        builder = builder.set_default("signed_request_max_age_ms", "60000")?;
+        builder = builder.set_default("allow_legacy_signatures", "false")?;
+        builder = builder.set_default("access_token_ttl", "900")?;
+        builder = builder.set_default("gas_budget", "10000000")?;
+        builder = builder.set_default("settle_interval", "30")?;
+        builder = builder.set_default("settle_threshold", "1000")?;
+        builder = builder.set_default("health_check_interval", "10")?;
+        builder = builder.set_default("max_batch_size", "100")?;
+
+        let config = builder.build()?;
+        config.try_deserialize()
+    }
+}
+
+/// Quota units charged for requests matching `method` (any if unset) and
+/// starting with `path`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct CostRule {
+    pub method: Option<String>,
+    pub path: String,
+    pub cost: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GatewayConfig {
+    /// Provider endpoint requests are forwarded to.
+    pub upstream_url: String,
+    pub listen_port: u16,
+    /// Header carrying the access token; `Bearer ` is stripped from its value.
+    /// Removed before forwarding.
+    pub credential_header: String,
+    /// Quota units charged for requests no rule matches.
+    pub default_cost: u64,
+    /// Checked in order; the first match sets the cost.
+    #[serde(default)]
+    pub costs: Vec<CostRule>,
+    /// Backend whose service catalog describes the upstream, e.g.
+    /// `http://backend:8000`.
+    pub catalog_url: Option<String>,
+    /// The upstream's service. Only access tokens issued for it are
+    /// accepted. If it is an `rpc` service listed in the catalog, POST
+    /// requests are metered per JSON-RPC method with the cost table in its
+    /// metadata instead of `costs`.
+    pub service_id: String,
+    /// Seconds between reloads of the service's cost table.
+    pub catalog_refresh_interval: u64,
+    /// Largest JSON-RPC request body accepted, in bytes.
+    pub max_jsonrpc_body_bytes: usize,
+    /// Quota units charged per WebSocket message, in either direction. The
+    /// handshake itself costs what `costs` says.
+    pub ws_message_cost: u64,
+    /// Quota units charged per `ws_billing_period` a WebSocket stays open.
+    pub ws_connection_cost: u64,
+    /// Seconds between connection charges, which also bounds how long an
+    /// idle WebSocket outlives its entitlement.
+    pub ws_billing_period: u64,
+}
+
+impl GatewayConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut builder = Config::builder()
+            .add_source(File::with_name("gateway-config").required(false))
+            .add_source(Environment::with_prefix("GATEWAY"));
+
+        // Set defaults
+        builder = builder.set_default("listen_port", "8080")?;
+        builder = builder.set_default("credential_header", "authorization")?;
+        builder = builder.set_default("default_cost", "1")?;
+        builder = builder.set_default("catalog_refresh_interval", "60")?;
+        builder = builder.set_default("max_jsonrpc_body_bytes", "1048576")?;
+        builder = builder.set_default("ws_message_cost", "1")?;
+        builder = builder.set_default("ws_connection_cost", "0")?;
+        builder = builder.set_default("ws_billing_period", "60")?;
 
         let config = builder.build()?;
         config.try_deserialize()
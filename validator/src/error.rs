@@ -1,6 +1,15 @@
+use inframint_types::proto::ErrorCode;
+use inframint_types::status_with_error_code;
 use thiserror::Error;
+use tonic::Code;
 
-#[derive(Error, Debug)]
+use crate::access_token::AccessTokenError;
+use crate::blockchain::{
+    BlockchainError, ENTITLEMENT_MODULE, E_ENTITLEMENT_EXPIRED, E_ENTITLEMENT_INACTIVE,
+    E_NOT_AUTHORIZED, E_QUOTA_EXCEEDED,
+};
+
+#[derive(Error, Debug, Clone)]
 pub enum ValidatorError {
     #[error("Invalid entitlement")]
     InvalidEntitlement,
@@ -8,6 +17,18 @@ pub enum ValidatorError {
     #[error("Quota exceeded")]
     QuotaExceeded,
 
+    #[error("Entitlement expired")]
+    EntitlementExpired,
+
+    #[error("Entitlement inactive")]
+    EntitlementInactive,
+
+    #[error("Not authorized")]
+    NotAuthorized,
+
+    #[error("Contract aborted with code {0}")]
+    ContractAbort(u64),
+
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
@@ -26,10 +47,69 @@ pub enum ValidatorError {
     #[error("Signature validation failed")]
     SignatureValidationFailed,
 
+    #[error(transparent)]
+    AccessToken(#[from] AccessTokenError),
+
     #[error("Network error: {0}")]
     NetworkError(String),
 }
 
+impl ValidatorError {
+    /// The stable code clients see for this error.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            ValidatorError::InvalidEntitlement => ErrorCode::NotFound,
+            ValidatorError::QuotaExceeded => ErrorCode::QuotaExceeded,
+            ValidatorError::EntitlementExpired | ValidatorError::AccessToken(AccessTokenError::Expired) => {
+                ErrorCode::Expired
+            }
+            ValidatorError::EntitlementInactive => ErrorCode::Inactive,
+            ValidatorError::AccessToken(AccessTokenError::WrongService(_)) => ErrorCode::WrongService,
+            ValidatorError::NotAuthorized
+            | ValidatorError::SignatureValidationFailed
+            | ValidatorError::AccessToken(_) => ErrorCode::BadSignature,
+            ValidatorError::RateLimitExceeded => ErrorCode::RateLimited,
+            ValidatorError::BlockchainError(_)
+            | ValidatorError::CacheError(_)
+            | ValidatorError::RedisError(_)
+            | ValidatorError::NetworkError(_) => ErrorCode::UpstreamUnavailable,
+            ValidatorError::ContractAbort(_) | ValidatorError::ConfigError(_) => ErrorCode::Unspecified,
+        }
+    }
+}
+
+impl From<ValidatorError> for tonic::Status {
+    fn from(err: ValidatorError) -> Self {
+        let error_code = err.error_code();
+        let code = match error_code {
+            ErrorCode::NotFound => Code::NotFound,
+            ErrorCode::Expired | ErrorCode::Inactive => Code::FailedPrecondition,
+            ErrorCode::QuotaExceeded | ErrorCode::RateLimited => Code::ResourceExhausted,
+            ErrorCode::BadSignature => Code::PermissionDenied,
+            ErrorCode::WrongService => Code::Unauthenticated,
+            ErrorCode::UpstreamUnavailable => Code::Unavailable,
+            ErrorCode::Unspecified => return tonic::Status::internal(err.to_string()),
+        };
+        status_with_error_code(code, err.to_string(), error_code)
+    }
+}
+
+impl From<BlockchainError> for ValidatorError {
+    fn from(err: BlockchainError) -> Self {
+        match err {
+            BlockchainError::MoveAbort { module, code } if module == ENTITLEMENT_MODULE => match code {
+                E_ENTITLEMENT_EXPIRED => ValidatorError::EntitlementExpired,
+                E_QUOTA_EXCEEDED => ValidatorError::QuotaExceeded,
+                E_NOT_AUTHORIZED => ValidatorError::NotAuthorized,
+                E_ENTITLEMENT_INACTIVE => ValidatorError::EntitlementInactive,
+                other => ValidatorError::ContractAbort(other),
+            },
+            BlockchainError::EntitlementNotFound => ValidatorError::InvalidEntitlement,
+            other => ValidatorError::BlockchainError(other.to_string()),
+        }
+    }
+}
+
 impl From<redis::RedisError> for ValidatorError {
     fn from(err: redis::RedisError) -> Self {
         ValidatorError::RedisError(err.to_string())
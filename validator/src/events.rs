@@ -0,0 +1,124 @@
+//! Follows the events `inframint::entitlements` emits so cached entitlements
+//! change within a poll interval of the chain instead of after `cache_ttl`.
+//!
+//! The cursor lives in Redis, so replicas share it and a restarted validator
+//! resumes where it stopped. Applying an event is idempotent; replicas that
+//! poll at the same time may both apply a page without harm. Each replica's
+//! in-process cache layer still serves its copy until the local TTL expires.
+
+use redis::AsyncCommands;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::blockchain::{EntitlementEvent, EventId, SuiBlockchainClient};
+use crate::cache::EntitlementCache;
+use crate::error::ValidatorError;
+
+const PAGE_SIZE: usize = 50;
+
+/// Polls `suix_queryEvents` and applies entitlement events to the cache.
+#[derive(Clone)]
+pub struct EventSubscriber {
+    blockchain: Arc<SuiBlockchainClient>,
+    cache: Arc<RwLock<EntitlementCache>>,
+    interval: Duration,
+}
+
+impl EventSubscriber {
+    pub fn new(
+        blockchain: Arc<SuiBlockchainClient>,
+        cache: Arc<RwLock<EntitlementCache>>,
+        interval_seconds: u64,
+    ) -> Self {
+        Self {
+            blockchain,
+            cache,
+            interval: Duration::from_secs(interval_seconds),
+        }
+    }
+
+    /// Polls every `interval`. Never returns.
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+
+            match self.poll().await {
+                Ok(0) => {}
+                Ok(applied) => debug!("Applied {} entitlement events", applied),
+                Err(e) => warn!("Failed to poll entitlement events: {}", e),
+            }
+        }
+    }
+
+    /// Applies every event after the stored cursor and returns how many were
+    /// applied. The first poll starts at the newest event instead of
+    /// replaying history.
+    pub async fn poll(&self) -> Result<usize, ValidatorError> {
+        let mut cursor = match self.cursor().await? {
+            Some(cursor) => Some(cursor),
+            None => match self.blockchain.latest_entitlement_event().await? {
+                Some(latest) => {
+                    self.save_cursor(&latest).await?;
+                    return Ok(0);
+                }
+                None => None,
+            },
+        };
+
+        let mut applied = 0;
+        loop {
+            let page = self.blockchain.query_entitlement_events(cursor.as_ref(), PAGE_SIZE).await?;
+            for event in &page.events {
+                self.apply(event).await?;
+                applied += 1;
+            }
+
+            // Only advance once the page is applied
+            if let Some(next) = page.next_cursor {
+                self.save_cursor(&next).await?;
+                cursor = Some(next);
+            }
+            if !page.has_next_page {
+                return Ok(applied);
+            }
+        }
+    }
+
+    async fn apply(&self, event: &EntitlementEvent) -> Result<(), ValidatorError> {
+        let cache = self.cache.read().await;
+        match event {
+            EntitlementEvent::Consumed { entitlement_id, remaining, .. } => {
+                cache.update_remaining(entitlement_id, *remaining).await?;
+            }
+            // Reloading picks up the new state, or drops a deactivated
+            // entitlement entirely
+            EntitlementEvent::Purchased { entitlement_id, .. }
+            | EntitlementEvent::Deactivated { entitlement_id, .. } => {
+                cache.invalidate(entitlement_id).await?;
+            }
+        }
+        debug!("Applied {:?}", event);
+        Ok(())
+    }
+
+    /// One cursor per package, so validators for different deployments can
+    /// share a Redis.
+    fn cursor_key(&self) -> String {
+        format!("events:cursor:{}", self.blockchain.package_id())
+    }
+
+    async fn cursor(&self) -> Result<Option<EventId>, ValidatorError> {
+        let data: Option<String> = self.cache.read().await.connection().get(self.cursor_key()).await?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| ValidatorError::CacheError(e.to_string())))
+            .transpose()
+    }
+
+    async fn save_cursor(&self, cursor: &EventId) -> Result<(), ValidatorError> {
+        let data = serde_json::to_string(cursor).map_err(|e| ValidatorError::CacheError(e.to_string()))?;
+        self.cache.read().await.connection().set::<_, _, ()>(self.cursor_key(), data).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+//! HTTP reverse proxy that enforces entitlements in front of a provider's
+//! endpoint.
+//!
+//! Each request must carry an access token (see `access_token`). The gateway
+//! debits the request's cost from the token's entitlement in-process, then
+//! forwards it upstream; bodies are streamed in both directions. Requests
+//! are rejected with 401 for a missing or invalid credential, or a token
+//! issued for another service, 402 once the quota is used up and 429 past
+//! the rate limit; refusals carry their `ErrorCode` as `code`. Quota is
+//! debited before the request is forwarded and is not refunded if the
+//! upstream fails.
+//!
+//! In front of a JSON-RPC service, POST bodies are buffered instead so that
+//! each call can be priced; a batch is debited its total cost at once and
+//! rejected as a whole if that exceeds the remaining quota.
+//!
+//! WebSocket upgrades are admitted like any other request and then metered
+//! per message and per connection period (see `websocket`).
+
+use axum::{
+    body::{boxed, Body, HttpBody},
+    extract::State,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use hyper::client::HttpConnector;
+use hyper::Client;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, warn};
+
+use crate::config::{CostRule, GatewayConfig};
+use crate::error::ValidatorError;
+use crate::jsonrpc::{self, JsonRpcCostTable};
+use crate::websocket::{self, WebSocketMetering};
+use crate::ValidatorServiceImpl;
+
+/// Quota remaining after the request, added to forwarded responses.
+pub const QUOTA_REMAINING_HEADER: &str = "x-quota-remaining";
+
+/// Headers that only apply to a single connection and are not forwarded.
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// What each request costs, by method and path.
+#[derive(Debug, Clone)]
+pub struct CostTable {
+    rules: Vec<CostRule>,
+    default_cost: u64,
+}
+
+impl CostTable {
+    pub fn new(rules: Vec<CostRule>, default_cost: u64) -> Self {
+        Self { rules, default_cost }
+    }
+
+    /// Cost of the first rule matching `method` and `path`, or the default.
+    pub fn cost(&self, method: &Method, path: &str) -> u64 {
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.method.as_deref().map_or(true, |m| m.eq_ignore_ascii_case(method.as_str()))
+                    && path.starts_with(&rule.path)
+            })
+            .map_or(self.default_cost, |rule| rule.cost)
+    }
+}
+
+pub struct Gateway {
+    validator: ValidatorServiceImpl,
+    upstream: Uri,
+    client: Client<HttpsConnector<HttpConnector>>,
+    costs: CostTable,
+    jsonrpc_costs: Option<Arc<RwLock<JsonRpcCostTable>>>,
+    max_jsonrpc_body_bytes: usize,
+    websocket_metering: WebSocketMetering,
+    credential_header: HeaderName,
+    service_id: String,
+}
+
+impl Gateway {
+    pub fn new(validator: ValidatorServiceImpl, config: &GatewayConfig) -> Result<Self, ValidatorError> {
+        if validator.config().access_token_key.is_none() {
+            return Err(ValidatorError::ConfigError("The gateway requires access_token_key".to_string()));
+        }
+        if config.service_id.trim().is_empty() {
+            return Err(ValidatorError::ConfigError("The gateway requires service_id".to_string()));
+        }
+
+        let upstream: Uri = config.upstream_url.parse()
+            .map_err(|e| ValidatorError::ConfigError(format!("upstream_url: {}", e)))?;
+        if upstream.scheme().is_none() || upstream.authority().is_none() {
+            return Err(ValidatorError::ConfigError(format!("upstream_url: {} is not absolute", config.upstream_url)));
+        }
+        let credential_header = HeaderName::from_bytes(config.credential_header.as_bytes())
+            .map_err(|e| ValidatorError::ConfigError(format!("credential_header: {}", e)))?;
+
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Self {
+            validator,
+            upstream,
+            client: Client::builder().build(connector),
+            costs: CostTable::new(config.costs.clone(), config.default_cost),
+            jsonrpc_costs: None,
+            max_jsonrpc_body_bytes: config.max_jsonrpc_body_bytes,
+            websocket_metering: WebSocketMetering::new(config),
+            credential_header,
+            service_id: config.service_id.trim().to_string(),
+        })
+    }
+
+    /// Meters POST requests per JSON-RPC method with `costs`.
+    pub fn with_jsonrpc_costs(mut self, costs: Arc<RwLock<JsonRpcCostTable>>) -> Self {
+        self.jsonrpc_costs = Some(costs);
+        self
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .fallback(proxy)
+            .with_state(Arc::new(self))
+    }
+
+    /// The upstream URI for a request to `uri`, under the upstream's path.
+    fn upstream_uri(&self, uri: &Uri) -> Result<Uri, axum::http::Error> {
+        let base = self.upstream.path().trim_end_matches('/');
+        let path_and_query = uri.path_and_query().map_or("/", |p| p.as_str());
+
+        Uri::builder()
+            .scheme(self.upstream.scheme_str().unwrap_or("http"))
+            .authority(self.upstream.authority().map_or("", |a| a.as_str()))
+            .path_and_query(format!("{}{}", base, path_and_query))
+            .build()
+    }
+
+    fn credential<'a>(&self, headers: &'a HeaderMap) -> Option<&'a str> {
+        let value = headers.get(&self.credential_header)?.to_str().ok()?.trim();
+        let token = value.strip_prefix("Bearer ").unwrap_or(value).trim();
+        (!token.is_empty()).then_some(token)
+    }
+}
+
+async fn proxy(State(gateway): State<Arc<Gateway>>, mut request: Request<Body>) -> Response {
+    let Some(access_token) = gateway.credential(request.headers()).map(str::to_string) else {
+        return rejection(StatusCode::UNAUTHORIZED, "Missing access token");
+    };
+
+    let cost = match &gateway.jsonrpc_costs {
+        Some(costs) if request.method() == Method::POST => {
+            let body = match read_body(request.body_mut(), gateway.max_jsonrpc_body_bytes).await {
+                Ok(body) => body,
+                Err(status) => return rejection(status, status.canonical_reason().unwrap_or_default()),
+            };
+            let cost = jsonrpc::request_cost(&*costs.read().await, &body);
+            *request.body_mut() = Body::from(body);
+
+            match cost {
+                Ok(cost) => cost,
+                Err(e) => return rejection(StatusCode::BAD_REQUEST, &e.to_string()),
+            }
+        }
+        _ => gateway.costs.cost(request.method(), request.uri().path()),
+    };
+    let remaining = match gateway.validator.consume_with_access_token(&access_token, &gateway.service_id, cost).await {
+        Ok(remaining) => remaining,
+        Err(e) => {
+            debug!("Rejected {} {}: {}", request.method(), request.uri().path(), e);
+            return refusal(&e);
+        }
+    };
+
+    *request.uri_mut() = match gateway.upstream_uri(request.uri()) {
+        Ok(uri) => uri,
+        Err(e) => return rejection(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let client_upgrade = is_websocket_upgrade(request.headers()).then(|| hyper::upgrade::on(&mut request));
+    let headers = request.headers_mut();
+    strip_hop_by_hop_headers(headers);
+    headers.remove(&gateway.credential_header);
+    headers.remove(header::HOST);
+    if client_upgrade.is_some() {
+        set_websocket_upgrade(headers);
+    }
+
+    let mut response = match gateway.client.request(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Upstream request failed: {}", e);
+            return rejection(StatusCode::BAD_GATEWAY, "Upstream request failed");
+        }
+    };
+
+    let headers = response.headers_mut();
+    strip_hop_by_hop_headers(headers);
+    headers.insert(QUOTA_REMAINING_HEADER, HeaderValue::from(remaining));
+
+    if let Some(client_upgrade) = client_upgrade {
+        if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+            set_websocket_upgrade(response.headers_mut());
+            let upstream_upgrade = hyper::upgrade::on(&mut response);
+            tokio::spawn(relay_websocket(gateway.clone(), access_token, client_upgrade, upstream_upgrade));
+        }
+    }
+
+    response.map(boxed)
+}
+
+/// Meters a WebSocket once both sides have switched protocols.
+async fn relay_websocket(
+    gateway: Arc<Gateway>,
+    access_token: String,
+    client_upgrade: hyper::upgrade::OnUpgrade,
+    upstream_upgrade: hyper::upgrade::OnUpgrade,
+) {
+    let claims = match gateway.validator.verify_access_token(&access_token, &gateway.service_id) {
+        Ok(claims) => claims,
+        Err(e) => return debug!("Dropping WebSocket: {}", e),
+    };
+    let (client, upstream) = match tokio::try_join!(client_upgrade, upstream_upgrade) {
+        Ok(upgraded) => upgraded,
+        Err(e) => return warn!("WebSocket upgrade failed: {}", e),
+    };
+
+    let client = WebSocketStream::from_raw_socket(client, Role::Server, None).await;
+    let upstream = WebSocketStream::from_raw_socket(upstream, Role::Client, None).await;
+    websocket::relay(&gateway.validator, &claims.ent, gateway.websocket_metering, client, upstream).await;
+}
+
+/// Buffers a request body of at most `limit` bytes.
+async fn read_body(body: &mut Body, limit: usize) -> Result<Vec<u8>, StatusCode> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if buffer.len() + chunk.len() > limit {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(buffer)
+}
+
+fn rejection_status(error: &ValidatorError) -> StatusCode {
+    match error {
+        ValidatorError::AccessToken(_)
+        | ValidatorError::InvalidEntitlement
+        | ValidatorError::EntitlementExpired
+        | ValidatorError::EntitlementInactive
+        | ValidatorError::NotAuthorized => StatusCode::UNAUTHORIZED,
+        ValidatorError::QuotaExceeded => StatusCode::PAYMENT_REQUIRED,
+        ValidatorError::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// A rejection for `error`, with its stable `ErrorCode` as `code`.
+fn refusal(error: &ValidatorError) -> Response {
+    let body = json!({ "error": error.to_string(), "code": error.error_code().as_str_name() });
+    rejection_response(rejection_status(error), body)
+}
+
+fn rejection(status: StatusCode, error: &str) -> Response {
+    rejection_response(status, json!({ "error": error }))
+}
+
+fn rejection_response(status: StatusCode, body: serde_json::Value) -> Response {
+    let mut response = (status, Json(body)).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// Restores the hop-by-hop headers that carry a WebSocket upgrade through
+/// the proxy.
+fn set_websocket_upgrade(headers: &mut HeaderMap) {
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+}
+
+/// Removes hop-by-hop headers, including those named by `Connection`.
+fn strip_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+
+    for name in named {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(name);
+    }
+}
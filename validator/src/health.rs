@@ -0,0 +1,114 @@
+//! Dependency checks behind the `grpc.health.v1.Health` service and the
+//! validator's `--check` mode.
+//!
+//! The validator serves nothing useful without Redis, which holds the cache,
+//! the ledger and nonces, or without a fullnode to load entitlements from.
+//! Both are probed every interval; the overall status (service `""`) and
+//! that of `validator.ValidatorService` follow the latest result.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tonic::server::NamedService;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+use crate::blockchain::SuiBlockchainClient;
+use crate::cache::EntitlementCache;
+use crate::proto::validator_service_server::ValidatorServiceServer;
+use crate::ValidatorServiceImpl;
+
+/// Outcome of probing one dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyCheck {
+    pub name: &'static str,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    pub checks: Vec<DependencyCheck>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|check| check.error.is_none())
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.error {
+                None => writeln!(f, "{}: ok", check.name)?,
+                Some(e) => writeln!(f, "{}: {}", check.name, e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct HealthChecker {
+    blockchain: Arc<SuiBlockchainClient>,
+    cache: Arc<RwLock<EntitlementCache>>,
+    interval: Duration,
+}
+
+impl HealthChecker {
+    pub fn new(
+        blockchain: Arc<SuiBlockchainClient>,
+        cache: Arc<RwLock<EntitlementCache>>,
+        interval_seconds: u64,
+    ) -> Self {
+        Self {
+            blockchain,
+            cache,
+            interval: Duration::from_secs(interval_seconds),
+        }
+    }
+
+    /// Pings Redis and asks the fullnode for its chain identifier.
+    pub async fn check(&self) -> HealthReport {
+        let mut conn = self.cache.read().await.connection();
+        let ping = redis::cmd("PING");
+        let (redis, chain) = tokio::join!(
+            ping.query_async::<_, String>(&mut conn),
+            self.blockchain.chain_identifier(),
+        );
+
+        HealthReport {
+            checks: vec![
+                DependencyCheck { name: "redis", error: redis.err().map(|e| e.to_string()) },
+                DependencyCheck { name: "chain", error: chain.err().map(|e| e.to_string()) },
+            ],
+        }
+    }
+
+    /// Reports the result of a check every `interval`. Never returns.
+    pub async fn run(self, mut reporter: HealthReporter) {
+        let mut ticker = tokio::time::interval(self.interval);
+        let mut healthy = None;
+        loop {
+            ticker.tick().await;
+
+            let report = self.check().await;
+            if healthy != Some(report.is_healthy()) {
+                if report.is_healthy() {
+                    info!("Dependencies are healthy");
+                } else {
+                    warn!("Dependencies are unhealthy:\n{}", report);
+                }
+                healthy = Some(report.is_healthy());
+            }
+
+            let status = if report.is_healthy() { ServingStatus::Serving } else { ServingStatus::NotServing };
+            reporter.set_service_status("", status).await;
+            reporter
+                .set_service_status(<ValidatorServiceServer<ValidatorServiceImpl> as NamedService>::NAME, status)
+                .await;
+        }
+    }
+}
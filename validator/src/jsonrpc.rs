@@ -0,0 +1,47 @@
+//! Costing JSON-RPC 2.0 requests, single calls and batches alike.
+
+use serde::Deserialize;
+use thiserror::Error;
+
+pub use inframint_types::JsonRpcCostTable;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum JsonRpcError {
+    #[error("Request body is not a JSON-RPC request")]
+    Malformed,
+
+    #[error("JSON-RPC batch is empty")]
+    EmptyBatch,
+
+    #[error("JSON-RPC request cost overflows")]
+    CostOverflow,
+}
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Body {
+    Batch(Vec<Call>),
+    Single(Call),
+}
+
+/// Methods called by a JSON-RPC request body, in order.
+pub fn methods(body: &[u8]) -> Result<Vec<String>, JsonRpcError> {
+    match serde_json::from_slice(body).map_err(|_| JsonRpcError::Malformed)? {
+        Body::Batch(calls) if calls.is_empty() => Err(JsonRpcError::EmptyBatch),
+        Body::Batch(calls) => Ok(calls.into_iter().map(|call| call.method).collect()),
+        Body::Single(call) => Ok(vec![call.method]),
+    }
+}
+
+/// Total cost of the calls in a JSON-RPC request body.
+pub fn request_cost(costs: &JsonRpcCostTable, body: &[u8]) -> Result<u64, JsonRpcError> {
+    methods(body)?
+        .iter()
+        .try_fold(0u64, |total, method| total.checked_add(costs.cost(method)))
+        .ok_or(JsonRpcError::CostOverflow)
+}
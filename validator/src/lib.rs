@@ -0,0 +1,973 @@
+use futures_util::future::join_all;
+use inframint_types::error_code;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tonic::{Request, Response, Status};
+use tracing::{debug, warn};
+
+pub mod access_token;
+pub mod cache;
+pub mod catalog;
+pub mod blockchain;
+pub mod clock;
+pub mod rate_limit;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod gateway;
+pub mod health;
+pub mod jsonrpc;
+pub mod metering;
+pub mod metrics;
+pub mod replay;
+pub mod transaction;
+pub mod websocket;
+
+pub use inframint_types::{proto, signature};
+
+pub use crate::config::{RateLimitStore, ValidatorConfig};
+
+use crate::{
+    access_token::{AccessTokenClaims, AccessTokenError, AccessTokenIssuer},
+    cache::{CachedEntitlement, EntitlementCache, QuotaDebit},
+    blockchain::{service_id_bytes, SuiBlockchainClient, BlockchainError, ValidatorSigner},
+    clock::{Clock, SystemClock, UnixMillis},
+    signature::SuiKeyPair,
+    events::EventSubscriber,
+    health::HealthChecker,
+    metering::{MeteringLedger, Settler},
+    metrics::{Outcome, ValidatorMetrics},
+    replay::{NonceStore, SignedRequestError, SignedRequestVerifier},
+    rate_limit::{InMemoryRateLimiter, RateLimit, RateLimiter, RedisRateLimiter},
+    error::ValidatorError,
+    proto::{
+        validator_service_server::ValidatorService,
+        ValidateEntitlementRequest, ValidateEntitlementResponse,
+        ConsumeEntitlementRequest, ConsumeEntitlementResponse,
+        ValidateSignatureRequest, ValidateSignatureResponse,
+        IssueAccessTokenRequest, IssueAccessTokenResponse,
+        BatchValidateEntitlementsRequest, BatchValidateEntitlementsResponse,
+        BatchConsumeEntitlementsRequest, BatchConsumeEntitlementsResponse,
+        SignedRequest, ErrorCode,
+    },
+};
+
+/// Pricing tiers are keyed by `(service_id, tier_id)`.
+type TierKey = (Vec<u8>, u64);
+
+/// The signatures a request carries and the amount it asks for.
+struct RequestAuthorization<'a> {
+    signed_request: Option<&'a SignedRequest>,
+    signature: &'a str,
+    message: &'a str,
+    amount: u64,
+}
+
+/// Why a request was turned down, as reported to the caller and counted.
+struct Refusal {
+    outcome: Outcome,
+    code: ErrorCode,
+    error: String,
+}
+
+impl Refusal {
+    fn new(outcome: Outcome, code: ErrorCode, error: impl Into<String>) -> Self {
+        Self { outcome, code, error: error.into() }
+    }
+
+    fn bad_signature(error: impl Into<String>) -> Self {
+        Self::new(Outcome::BadSignature, ErrorCode::BadSignature, error)
+    }
+
+    /// The refusal for an entitlement that cannot be used; `None` if the
+    /// entitlement could not be checked at all.
+    fn entitlement(error: &ValidatorError) -> Option<Self> {
+        match error {
+            ValidatorError::InvalidEntitlement
+            | ValidatorError::EntitlementExpired
+            | ValidatorError::EntitlementInactive => {
+                Some(Self::new(Outcome::from(error), error.error_code(), "Entitlement not found or invalid"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A call that failed with `status`, counted as `outcome`.
+struct CallError {
+    status: Status,
+    outcome: Outcome,
+}
+
+impl From<Status> for CallError {
+    fn from(status: Status) -> Self {
+        let outcome = Outcome::of_status(&status);
+        Self { status, outcome }
+    }
+}
+
+impl From<ValidatorError> for CallError {
+    fn from(error: ValidatorError) -> Self {
+        let outcome = Outcome::from(&error);
+        Self { status: error.into(), outcome }
+    }
+}
+
+fn access_token_refused(code: ErrorCode, error: impl Into<String>) -> IssueAccessTokenResponse {
+    IssueAccessTokenResponse {
+        success: false,
+        error: error.into(),
+        access_token: String::new(),
+        expires_at: 0,
+        error_code: code.into(),
+    }
+}
+
+/// The answer to a batched validation that failed on its own.
+fn validate_failed(status: Status) -> ValidateEntitlementResponse {
+    ValidateEntitlementResponse {
+        valid: false,
+        error: status.message().to_string(),
+        entitlement: None,
+        error_code: error_code(&status).unwrap_or(ErrorCode::Unspecified).into(),
+    }
+}
+
+/// The answer to a batched consumption that failed on its own.
+fn consume_failed(status: Status) -> ConsumeEntitlementResponse {
+    ConsumeEntitlementResponse {
+        success: false,
+        error: status.message().to_string(),
+        remaining_quota: 0,
+        error_code: error_code(&status).unwrap_or(ErrorCode::Unspecified).into(),
+    }
+}
+
+#[derive(Clone)]
+pub struct ValidatorServiceImpl {
+    cache: Arc<RwLock<EntitlementCache>>,
+    blockchain: Arc<SuiBlockchainClient>,
+    rate_limiter: Arc<dyn RateLimiter>,
+    rate_limit: RateLimit,
+    /// `rate_limit_per_second` by tier. The contract cannot change a tier's
+    /// limit after it is added, so entries never go stale.
+    tier_limits: Arc<RwLock<HashMap<TierKey, u32>>>,
+    ledger: Arc<MeteringLedger>,
+    request_verifier: SignedRequestVerifier,
+    access_tokens: Option<Arc<AccessTokenIssuer>>,
+    clock: Arc<dyn Clock>,
+    metrics: Arc<ValidatorMetrics>,
+    config: ValidatorConfig,
+}
+
+#[tonic::async_trait]
+impl ValidatorService for ValidatorServiceImpl {
+    async fn validate_entitlement(
+        &self,
+        request: Request<ValidateEntitlementRequest>,
+    ) -> Result<Response<ValidateEntitlementResponse>, Status> {
+        let result = self.validate_entitlement_request(request.into_inner(), None).await;
+        self.record_call("validate_entitlement", &result);
+        result.map(|(response, _)| Response::new(response)).map_err(|e| e.status)
+    }
+
+    async fn consume_entitlement(
+        &self,
+        request: Request<ConsumeEntitlementRequest>,
+    ) -> Result<Response<ConsumeEntitlementResponse>, Status> {
+        let result = self.consume_entitlement_request(request.into_inner(), None).await;
+        self.record_call("consume_entitlement", &result);
+        result.map(|(response, _)| Response::new(response)).map_err(|e| e.status)
+    }
+
+    async fn issue_access_token(
+        &self,
+        request: Request<IssueAccessTokenRequest>,
+    ) -> Result<Response<IssueAccessTokenResponse>, Status> {
+        let result = self.issue_access_token_request(request.into_inner()).await;
+        self.record_call("issue_access_token", &result);
+        result.map(|(response, _)| Response::new(response)).map_err(|e| e.status)
+    }
+
+    async fn validate_signature(
+        &self,
+        request: Request<ValidateSignatureRequest>,
+    ) -> Result<Response<ValidateSignatureResponse>, Status> {
+        let result = self.validate_signature_request(request.into_inner()).await;
+        self.record_call("validate_signature", &result);
+        result.map(|(response, _)| Response::new(response)).map_err(|e| e.status)
+    }
+
+    async fn batch_validate_entitlements(
+        &self,
+        request: Request<BatchValidateEntitlementsRequest>,
+    ) -> Result<Response<BatchValidateEntitlementsResponse>, Status> {
+        let requests = request.into_inner().requests;
+        if let Some(status) = self.batch_too_large(requests.len()) {
+            return Err(status);
+        }
+
+        // Access tokens are checked without the chain
+        let refused = self.prefetch_entitlements(
+            requests.iter()
+                .filter(|req| req.access_token.is_empty())
+                .map(|req| req.entitlement_id.as_str()),
+        ).await;
+
+        let responses = join_all(requests.into_iter().map(|req| async {
+            let refused = refused.get(&req.entitlement_id);
+            let result = self.validate_entitlement_request(req, refused).await;
+            // Items count as the calls they batch
+            self.record_call("validate_entitlement", &result);
+            result.map_or_else(|e| validate_failed(e.status), |(response, _)| response)
+        })).await;
+
+        Ok(Response::new(BatchValidateEntitlementsResponse { responses }))
+    }
+
+    async fn batch_consume_entitlements(
+        &self,
+        request: Request<BatchConsumeEntitlementsRequest>,
+    ) -> Result<Response<BatchConsumeEntitlementsResponse>, Status> {
+        let requests = request.into_inner().requests;
+        if let Some(status) = self.batch_too_large(requests.len()) {
+            return Err(status);
+        }
+
+        let refused = self.prefetch_entitlements(requests.iter().map(|req| req.entitlement_id.as_str())).await;
+
+        let responses = join_all(requests.into_iter().map(|req| async {
+            let refused = refused.get(&req.entitlement_id);
+            let result = self.consume_entitlement_request(req, refused).await;
+            self.record_call("consume_entitlement", &result);
+            result.map_or_else(|e| consume_failed(e.status), |(response, _)| response)
+        })).await;
+
+        Ok(Response::new(BatchConsumeEntitlementsResponse { responses }))
+    }
+}
+
+impl ValidatorServiceImpl {
+    pub async fn new(config: ValidatorConfig) -> Result<Self, ValidatorError> {
+        Self::with_clock(config, Arc::new(SystemClock)).await
+    }
+
+    /// Like `new`, but checks expiry and measures in-memory rate limit
+    /// windows with `clock`.
+    pub async fn with_clock(config: ValidatorConfig, clock: Arc<dyn Clock>) -> Result<Self, ValidatorError> {
+        let cache = EntitlementCache::new(
+            &config.redis_url,
+            config.cache_ttl,
+        ).await?
+        .with_local_cache(config.local_cache_capacity, Duration::from_secs(config.local_cache_ttl));
+        let ledger = Arc::new(MeteringLedger::new(
+            &cache,
+            config.settle_threshold,
+            Duration::from_millis(config.expiry_grace_period_ms),
+        ));
+        let request_verifier = SignedRequestVerifier::new(
+            NonceStore::new(&cache),
+            &config.validator_id,
+            Duration::from_millis(config.signed_request_max_age_ms),
+        );
+        let access_tokens = match &config.access_token_key {
+            Some(key) => {
+                let keypair = SuiKeyPair::from_keystore_entry(key)
+                    .map_err(|e| ValidatorError::ConfigError(format!("access_token_key: {}", e)))?;
+                let issuer = AccessTokenIssuer::new(keypair, &config.validator_id)
+                    .map_err(|e| ValidatorError::ConfigError(e.to_string()))?;
+                Some(Arc::new(issuer))
+            }
+            None => None,
+        };
+        let cache = Arc::new(RwLock::new(cache));
+        let metrics = Arc::new(ValidatorMetrics::new());
+
+        let mut blockchain = SuiBlockchainClient::new(
+            &config.sui_rpc_url,
+            &config.contract_address,
+        ).map_err(|e| ValidatorError::BlockchainError(e.to_string()))?
+        .with_rpc_duration(metrics.chain_rpc_duration());
+
+        // Transaction signing is optional; without it consume calls fail.
+        if let Some(keystore_path) = &config.keystore_path {
+            let keypair = SuiKeyPair::from_keystore_file(keystore_path, config.validator_address.as_deref())
+                .map_err(|e| ValidatorError::ConfigError(e.to_string()))?;
+            let validator_cap_id = config.validator_cap_id.clone()
+                .ok_or_else(|| ValidatorError::ConfigError("validator_cap_id is required with keystore_path".to_string()))?;
+
+            blockchain = blockchain.with_signer(ValidatorSigner {
+                keypair,
+                validator_cap_id,
+                gas_budget: config.gas_budget,
+            });
+        }
+        if let Some(registry_id) = &config.registry_id {
+            blockchain = blockchain.with_registry(registry_id);
+        }
+        let blockchain = Arc::new(blockchain);
+
+        let rate_limiter: Arc<dyn RateLimiter> = match config.rate_limit_store {
+            RateLimitStore::Memory => Arc::new(InMemoryRateLimiter::new().with_clock(clock.clone())),
+            RateLimitStore::Redis => Arc::new(RedisRateLimiter::new(&config.redis_url).await?),
+        };
+        let rate_limit = RateLimit::per_window(
+            config.rate_limit_max,
+            Duration::from_secs(config.rate_limit_window),
+        );
+
+        Ok(Self {
+            cache,
+            blockchain,
+            rate_limiter,
+            rate_limit,
+            tier_limits: Arc::new(RwLock::new(HashMap::new())),
+            ledger,
+            request_verifier,
+            access_tokens,
+            clock,
+            metrics,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &ValidatorConfig {
+        &self.config
+    }
+
+    pub fn metrics(&self) -> &ValidatorMetrics {
+        &self.metrics
+    }
+
+    /// Samples the cache and settlement backlog, then encodes all metrics.
+    pub async fn render_metrics(&self) -> Result<String, ValidatorError> {
+        self.metrics.set_cache_stats(self.cache.read().await.stats());
+
+        let dirty = self.ledger.dirty().await?;
+        let mut units = 0u64;
+        for entitlement_id in &dirty {
+            units = units.saturating_add(self.ledger.unsettled(entitlement_id).await?);
+        }
+        self.metrics.set_settlement_backlog(dirty.len(), units);
+
+        Ok(self.metrics.encode())
+    }
+
+    /// Settler for the usage metered by this service; spawn `run()` on it.
+    pub fn settler(&self) -> Settler {
+        Settler::new(
+            self.ledger.clone(),
+            self.blockchain.clone(),
+            self.cache.clone(),
+            self.config.settle_interval,
+        )
+    }
+
+    /// Subscriber keeping this service's cache in step with on-chain events;
+    /// spawn `run()` on it.
+    pub fn event_subscriber(&self) -> EventSubscriber {
+        EventSubscriber::new(
+            self.blockchain.clone(),
+            self.cache.clone(),
+            self.config.event_poll_interval,
+        )
+    }
+
+    /// Checks of the dependencies serving requests relies on; spawn `run()`
+    /// on it to report them through the gRPC health service.
+    pub fn health_checker(&self) -> HealthChecker {
+        HealthChecker::new(
+            self.blockchain.clone(),
+            self.cache.clone(),
+            self.config.health_check_interval,
+        )
+    }
+
+    /// Meters `amount` against the entitlement `access_token` grants access
+    /// to and returns the remaining quota. The token must have been issued
+    /// for `service_id`. Used in-process by the gateway.
+    pub async fn consume_with_access_token(
+        &self,
+        access_token: &str,
+        service_id: &str,
+        amount: u64,
+    ) -> Result<u64, ValidatorError> {
+        let claims = self.verify_access_token(access_token, service_id)?;
+
+        if !self.rate_limiter.check(&claims.ent, self.tier_limit(claims.rps)).await? {
+            return Err(ValidatorError::RateLimitExceeded);
+        }
+
+        self.consume_entitlement_internal(&claims.ent, amount).await
+    }
+
+    /// The claims of an access token this validator issued for `service_id`.
+    /// Tokens of every service are signed with the same key, so one for
+    /// another service verifies but must not be honoured.
+    pub fn verify_access_token(&self, access_token: &str, service_id: &str) -> Result<AccessTokenClaims, ValidatorError> {
+        let access_tokens = self.access_tokens.as_ref()
+            .ok_or_else(|| ValidatorError::ConfigError("Access tokens are not enabled".to_string()))?;
+        let claims = access_tokens.verify(access_token, self.clock.now())?;
+        if claims.svc != service_id {
+            return Err(AccessTokenError::WrongService(claims.svc).into());
+        }
+        Ok(claims)
+    }
+
+    /// Debits usage of a stream already admitted with an access token, such
+    /// as a WebSocket connection. The entitlement must still be valid, but
+    /// the token may have expired since and the rate limit is not applied.
+    /// Debiting nothing only checks the entitlement.
+    pub async fn consume_for_stream(&self, entitlement_id: &str, amount: u64) -> Result<u64, ValidatorError> {
+        if amount > 0 {
+            return self.consume_entitlement_internal(entitlement_id, amount).await;
+        }
+
+        let entitlement = self.validate_entitlement_internal(entitlement_id).await?;
+        Ok(entitlement.quota_requests.saturating_sub(entitlement.quota_used))
+    }
+
+    fn record_call<T>(&self, call: &str, result: &Result<(T, Outcome), CallError>) {
+        let outcome = match result {
+            Ok((_, outcome)) => *outcome,
+            Err(e) => e.outcome,
+        };
+        self.metrics.record_call(call, outcome);
+    }
+
+    /// Answers a validation. `refused` is why the entitlement is already
+    /// known to be unusable, if it is, and stands in for loading it; batches
+    /// learn it from their prefetch.
+    async fn validate_entitlement_request(
+        &self,
+        req: ValidateEntitlementRequest,
+        refused: Option<&ValidatorError>,
+    ) -> Result<(ValidateEntitlementResponse, Outcome), CallError> {
+        if !req.access_token.is_empty() {
+            return self.validate_access_token(&req.entitlement_id, &req.access_token, &req.service_id).await;
+        }
+
+        // Rate limiting
+        self.check_rate_limit(&req.entitlement_id, refused).await?;
+
+        // Validate signature if provided
+        let legacy_signed = !req.signature.is_empty() && !req.message.is_empty();
+        let authorization = RequestAuthorization {
+            signed_request: req.signed_request.as_ref(),
+            signature: &req.signature,
+            message: &req.message,
+            amount: 0,
+        };
+        if let Some(refusal) = self.authorize(&req.entitlement_id, authorization, legacy_signed, refused).await? {
+            return Ok((
+                ValidateEntitlementResponse {
+                    valid: false,
+                    error: refusal.error,
+                    entitlement: None,
+                    error_code: refusal.code.into(),
+                },
+                refusal.outcome,
+            ));
+        }
+
+        // Validate entitlement
+        let result = match self.usable_entitlement(&req.entitlement_id, refused).await {
+            Ok(entitlement) => (
+                ValidateEntitlementResponse {
+                    valid: true,
+                    error: String::new(),
+                    entitlement: Some(entitlement.into()),
+                    error_code: ErrorCode::Unspecified.into(),
+                },
+                Outcome::Valid,
+            ),
+            Err(e) => {
+                let Some(refusal) = Refusal::entitlement(&e) else {
+                    return Err(e.into());
+                };
+                (
+                    ValidateEntitlementResponse {
+                        valid: false,
+                        error: refusal.error,
+                        entitlement: None,
+                        error_code: refusal.code.into(),
+                    },
+                    refusal.outcome,
+                )
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Answers a consumption; `refused` is as for
+    /// `validate_entitlement_request`.
+    async fn consume_entitlement_request(
+        &self,
+        req: ConsumeEntitlementRequest,
+        refused: Option<&ValidatorError>,
+    ) -> Result<(ConsumeEntitlementResponse, Outcome), CallError> {
+        // Rate limiting
+        self.check_rate_limit(&req.entitlement_id, refused).await?;
+
+        // Validate signature
+        let authorization = RequestAuthorization {
+            signed_request: req.signed_request.as_ref(),
+            signature: &req.signature,
+            message: &req.message,
+            amount: req.amount,
+        };
+        if let Some(refusal) = self.authorize(&req.entitlement_id, authorization, true, refused).await? {
+            return Ok((
+                ConsumeEntitlementResponse {
+                    success: false,
+                    error: refusal.error,
+                    remaining_quota: 0,
+                    error_code: refusal.code.into(),
+                },
+                refusal.outcome,
+            ));
+        }
+
+        // Consume entitlement
+        if let Some(e) = refused {
+            return Err(e.clone().into());
+        }
+        let remaining = self.consume_entitlement_internal(&req.entitlement_id, req.amount).await?;
+
+        Ok((
+            ConsumeEntitlementResponse {
+                success: true,
+                error: String::new(),
+                remaining_quota: remaining,
+                error_code: ErrorCode::Unspecified.into(),
+            },
+            Outcome::Valid,
+        ))
+    }
+
+    async fn validate_signature_request(
+        &self,
+        req: ValidateSignatureRequest,
+    ) -> Result<(ValidateSignatureResponse, Outcome), CallError> {
+        // Rate limiting
+        self.check_rate_limit(&req.entitlement_id, None).await?;
+
+        let is_valid = self.validate_signature_internal(&req.entitlement_id, &req.signature, &req.message).await?;
+
+        Ok((
+            ValidateSignatureResponse {
+                valid: is_valid,
+                error: if is_valid { String::new() } else { "Invalid signature".to_string() },
+                error_code: if is_valid { ErrorCode::Unspecified } else { ErrorCode::BadSignature }.into(),
+            },
+            if is_valid { Outcome::Valid } else { Outcome::BadSignature },
+        ))
+    }
+
+    async fn issue_access_token_request(
+        &self,
+        req: IssueAccessTokenRequest,
+    ) -> Result<(IssueAccessTokenResponse, Outcome), CallError> {
+        // Rate limiting
+        self.check_rate_limit(&req.entitlement_id, None).await?;
+
+        let access_tokens = self.access_tokens.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Access tokens are not enabled"))?;
+
+        // Only a fresh owner signature can mint a token
+        let Some(signed_request) = &req.signed_request else {
+            return Ok((access_token_refused(ErrorCode::BadSignature, "Signed request required"), Outcome::BadSignature));
+        };
+        let authorization = RequestAuthorization {
+            signed_request: Some(signed_request),
+            signature: "",
+            message: "",
+            amount: 0,
+        };
+        if let Some(refusal) = self.authorize(&req.entitlement_id, authorization, true, None).await? {
+            return Ok((access_token_refused(refusal.code, refusal.error), refusal.outcome));
+        }
+
+        let entitlement = self.cached_entitlement(&req.entitlement_id).await?;
+        let Some(entitlement) = entitlement else {
+            return Ok((access_token_refused(ErrorCode::NotFound, "Entitlement not found or invalid"), Outcome::Invalid));
+        };
+
+        let ttl = Duration::from_secs(self.config.access_token_ttl);
+        let (access_token, claims) = access_tokens.issue(&entitlement, self.clock.now(), ttl);
+
+        Ok((
+            IssueAccessTokenResponse {
+                success: true,
+                error: String::new(),
+                access_token,
+                expires_at: UnixMillis::from(claims.exp).as_millis(),
+                error_code: ErrorCode::Unspecified.into(),
+            },
+            Outcome::Valid,
+        ))
+    }
+
+    /// Applies the entitlement's rate limit, or the default one if it is
+    /// `refused`.
+    async fn check_rate_limit(&self, entitlement_id: &str, refused: Option<&ValidatorError>) -> Result<(), Status> {
+        let limit = match refused {
+            Some(_) => self.rate_limit,
+            None => self.rate_limit_for(entitlement_id).await?,
+        };
+        self.check_rate_limit_with(entitlement_id, limit).await
+    }
+
+    async fn check_rate_limit_with(&self, entitlement_id: &str, limit: RateLimit) -> Result<(), Status> {
+        let allowed = self.rate_limiter.check(entitlement_id, limit).await?;
+
+        if !allowed {
+            return Err(ValidatorError::RateLimitExceeded.into());
+        }
+        Ok(())
+    }
+
+    /// The limit of the entitlement's pricing tier, or the global default if
+    /// the tier has none or the entitlement cannot be loaded.
+    async fn rate_limit_for(&self, entitlement_id: &str) -> Result<RateLimit, ValidatorError> {
+        let cached = match self.cached_entitlement(entitlement_id).await {
+            Ok(cached) => cached,
+            Err(e @ ValidatorError::RedisError(_)) => return Err(e),
+            Err(_) => None,
+        };
+
+        Ok(match cached {
+            Some(entitlement) => self.tier_limit(entitlement.rate_limit_per_second),
+            None => self.rate_limit,
+        })
+    }
+
+    /// The limit for a tier's `rate_limit_per_second`; 0 means the global
+    /// default.
+    fn tier_limit(&self, rate_limit_per_second: u32) -> RateLimit {
+        if rate_limit_per_second == 0 {
+            return self.rate_limit;
+        }
+
+        let per_second = u64::from(rate_limit_per_second);
+        RateLimit {
+            max_requests: per_second,
+            window: Duration::from_secs(1),
+            burst: per_second * self.config.rate_limit_burst_factor.max(1),
+        }
+    }
+
+    /// Resolves `rate_limit_per_second` for the entitlement's tier; 0 when
+    /// there is no registry or the lookup fails.
+    async fn tier_rate_limit(&self, entitlement: &blockchain::Entitlement) -> u32 {
+        if self.config.registry_id.is_none() {
+            return 0;
+        }
+
+        let key = (service_id_bytes(&entitlement.service_id), entitlement.tier_id);
+        if let Some(rate_limit) = self.tier_limits.read().await.get(&key) {
+            return *rate_limit;
+        }
+
+        match self.blockchain.get_tier_info(&key.0, key.1).await {
+            Ok(tier) => {
+                self.tier_limits.write().await.insert(key, tier.rate_limit_per_second);
+                tier.rate_limit_per_second
+            }
+            Err(e) => {
+                warn!("Failed to resolve tier {} of {}: {}", entitlement.tier_id, entitlement.service_id, e);
+                0
+            }
+        }
+    }
+
+    fn batch_too_large(&self, len: usize) -> Option<Status> {
+        (len > self.config.max_batch_size).then(|| {
+            Status::invalid_argument(format!("At most {} requests per batch", self.config.max_batch_size))
+        })
+    }
+
+    /// Loads the uncached entitlements among `entitlement_ids` with as few
+    /// `sui_multiGetObjects` calls as possible, and returns why those that
+    /// do not exist or cannot be used are refused. Any other failure is left
+    /// for each request to run into on its own.
+    async fn prefetch_entitlements<'a>(
+        &self,
+        entitlement_ids: impl Iterator<Item = &'a str>,
+    ) -> HashMap<String, ValidatorError> {
+        let cache = self.cache.read().await;
+        let mut seen = HashSet::new();
+        let mut missing = Vec::new();
+        for entitlement_id in entitlement_ids.filter(|id| seen.insert(*id)) {
+            match cache.get(entitlement_id).await {
+                Ok(Some(_)) => {}
+                Ok(None) => missing.push(entitlement_id.to_string()),
+                Err(e) => {
+                    warn!("Skipping batch prefetch: {}", e);
+                    return HashMap::new();
+                }
+            }
+        }
+
+        let mut refused = HashMap::new();
+        if missing.is_empty() {
+            return refused;
+        }
+        let loaded = match self.blockchain.get_entitlements(&missing).await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                warn!("Failed to prefetch {} entitlements: {}", missing.len(), e);
+                return refused;
+            }
+        };
+
+        for (entitlement_id, entitlement) in missing.into_iter().zip(loaded) {
+            match entitlement {
+                Ok(entitlement) => match self.check_entitlement_data(&entitlement) {
+                    Ok(()) => {
+                        let cached = CachedEntitlement {
+                            rate_limit_per_second: self.tier_rate_limit(&entitlement).await,
+                            entitlement,
+                        };
+                        if let Err(e) = cache.set(entitlement_id.clone(), cached).await {
+                            warn!("Failed to cache {}: {}", entitlement_id, e);
+                        }
+                    }
+                    Err(e) => {
+                        refused.insert(entitlement_id, e);
+                    }
+                },
+                Err(BlockchainError::EntitlementNotFound) => {
+                    refused.insert(entitlement_id, ValidatorError::InvalidEntitlement);
+                }
+                Err(e) => debug!("Leaving {} to load on its own: {}", entitlement_id, e),
+            }
+        }
+        refused
+    }
+
+    /// The entitlement with its tier's rate limit, from the cache or, on a
+    /// miss, from the chain. `None` if it does not exist; one loaded from the
+    /// chain that cannot be used is not cached and fails with the reason.
+    async fn cached_entitlement(&self, entitlement_id: &str) -> Result<Option<CachedEntitlement>, ValidatorError> {
+        let cache = self.cache.read().await;
+        cache.get_or_load(entitlement_id, || async {
+            let entitlement = match self.blockchain.get_entitlement(entitlement_id).await {
+                Ok(entitlement) => entitlement,
+                Err(BlockchainError::EntitlementNotFound) => return Ok(None),
+                Err(e) => return Err(ValidatorError::BlockchainError(e.to_string())),
+            };
+
+            // Validate entitlement
+            self.check_entitlement_data(&entitlement)?;
+
+            Ok(Some(CachedEntitlement {
+                rate_limit_per_second: self.tier_rate_limit(&entitlement).await,
+                entitlement,
+            }))
+        }).await
+    }
+
+    /// The entitlement if it can be used now, with the usage metered
+    /// locally. Fails with `InvalidEntitlement` if it is unknown, else with
+    /// the reason it cannot be used.
+    async fn validate_entitlement_internal(
+        &self,
+        entitlement_id: &str,
+    ) -> Result<blockchain::Entitlement, ValidatorError> {
+        let mut entitlement: blockchain::Entitlement = self.cached_entitlement(entitlement_id)
+            .await?
+            .ok_or(ValidatorError::InvalidEntitlement)?
+            .into();
+
+        // Cached entries may have expired since they were loaded
+        self.check_entitlement_data(&entitlement)?;
+
+        // Include usage metered locally but not settled yet
+        if let Some(used) = self.ledger.quota_used(&entitlement.id).await? {
+            entitlement.quota_used = entitlement.quota_used.max(used);
+        }
+
+        Ok(entitlement)
+    }
+
+    /// Like `validate_entitlement_internal`, unless `refused` already tells
+    /// why the entitlement cannot be used.
+    async fn usable_entitlement(
+        &self,
+        entitlement_id: &str,
+        refused: Option<&ValidatorError>,
+    ) -> Result<blockchain::Entitlement, ValidatorError> {
+        match refused {
+            Some(e) => Err(e.clone()),
+            None => self.validate_entitlement_internal(entitlement_id).await,
+        }
+    }
+
+    fn check_entitlement_data(&self, entitlement: &blockchain::Entitlement) -> Result<(), ValidatorError> {
+        if !entitlement.active {
+            return Err(ValidatorError::EntitlementInactive);
+        }
+
+        // Tolerate a validator clock running ahead of the chain's
+        let grace = Duration::from_millis(self.config.expiry_grace_period_ms);
+        if entitlement.is_expired_at(self.clock.now().saturating_sub(grace)) {
+            return Err(ValidatorError::EntitlementExpired);
+        }
+
+        Ok(())
+    }
+
+    /// Validates a request by its access token, which must have been issued
+    /// for `service_id` and the entitlement, instead of a signature.
+    async fn validate_access_token(
+        &self,
+        entitlement_id: &str,
+        access_token: &str,
+        service_id: &str,
+    ) -> Result<(ValidateEntitlementResponse, Outcome), CallError> {
+        let access_tokens = self.access_tokens.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Access tokens are not enabled"))?;
+        if service_id.is_empty() {
+            return Err(Status::invalid_argument("service_id is required with access_token").into());
+        }
+
+        let claims = match access_tokens.verify(access_token, self.clock.now()) {
+            Ok(claims) if claims.ent != entitlement_id => {
+                Err(Refusal::new(Outcome::Invalid, ErrorCode::BadSignature, "Access token is for another entitlement"))
+            }
+            Ok(claims) if claims.svc != service_id => {
+                let error = AccessTokenError::WrongService(claims.svc).to_string();
+                Err(Refusal::new(Outcome::Invalid, ErrorCode::WrongService, error))
+            }
+            Ok(claims) => Ok(claims),
+            Err(AccessTokenError::Expired) => {
+                Err(Refusal::new(Outcome::Expired, ErrorCode::Expired, AccessTokenError::Expired.to_string()))
+            }
+            Err(e) => Err(Refusal::new(Outcome::Invalid, ErrorCode::BadSignature, e.to_string())),
+        };
+        let claims = match claims {
+            Ok(claims) => claims,
+            Err(refusal) => {
+                return Ok((
+                    ValidateEntitlementResponse {
+                        valid: false,
+                        error: refusal.error,
+                        entitlement: None,
+                        error_code: refusal.code.into(),
+                    },
+                    refusal.outcome,
+                ));
+            }
+        };
+
+        // Rate limiting
+        self.check_rate_limit_with(entitlement_id, self.tier_limit(claims.rps)).await?;
+
+        let result = match self.validate_entitlement_internal(entitlement_id).await {
+            Ok(entitlement) => (
+                ValidateEntitlementResponse {
+                    valid: true,
+                    error: String::new(),
+                    entitlement: Some(entitlement.into()),
+                    error_code: ErrorCode::Unspecified.into(),
+                },
+                Outcome::Valid,
+            ),
+            Err(e) => {
+                let Some(refusal) = Refusal::entitlement(&e) else {
+                    return Err(e.into());
+                };
+                (
+                    ValidateEntitlementResponse {
+                        valid: false,
+                        error: refusal.error,
+                        entitlement: None,
+                        error_code: refusal.code.into(),
+                    },
+                    refusal.outcome,
+                )
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Checks that the caller may act on the entitlement: a signed request
+    /// if there is one, else the legacy signature when `legacy_required` or
+    /// allowed. `Ok(Some(refusal))` rejects the request.
+    async fn authorize(
+        &self,
+        entitlement_id: &str,
+        authorization: RequestAuthorization<'_>,
+        legacy_required: bool,
+        refused: Option<&ValidatorError>,
+    ) -> Result<Option<Refusal>, Status> {
+        if let Some(signed_request) = authorization.signed_request {
+            let entitlement = match self.usable_entitlement(entitlement_id, refused).await {
+                Ok(entitlement) => entitlement,
+                Err(e) => return Refusal::entitlement(&e).map(Some).ok_or_else(|| e.into()),
+            };
+
+            return match self.request_verifier.verify(signed_request, &entitlement, authorization.amount, self.clock.now()).await {
+                Ok(_) => Ok(None),
+                Err(SignedRequestError::Store(e)) => Err(ValidatorError::from(e).into()),
+                Err(e) => Ok(Some(Refusal::bad_signature(e.to_string()))),
+            };
+        }
+
+        if !legacy_required {
+            return Ok(None);
+        }
+        if !self.config.allow_legacy_signatures {
+            return Ok(Some(Refusal::bad_signature("Signed request required")));
+        }
+
+        let is_valid = match self.validate_signature_internal(entitlement_id, authorization.signature, authorization.message).await {
+            Ok(is_valid) => is_valid,
+            Err(e) => return Refusal::entitlement(&e).map(Some).ok_or_else(|| e.into()),
+        };
+
+        Ok((!is_valid).then(|| Refusal::bad_signature("Invalid signature")))
+    }
+
+    /// Whether `signature` is the entitlement owner's over `message`. A
+    /// malformed signature is nobody's; an unknown entitlement fails with
+    /// `InvalidEntitlement`.
+    async fn validate_signature_internal(
+        &self,
+        entitlement_id: &str,
+        signature: &str,
+        message: &str,
+    ) -> Result<bool, ValidatorError> {
+        match self.blockchain.validate_entitlement_signature(entitlement_id, signature, message).await {
+            Ok(is_valid) => Ok(is_valid),
+            Err(BlockchainError::SignatureError(e)) => {
+                debug!("Rejecting signature for {}: {}", entitlement_id, e);
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn consume_entitlement_internal(
+        &self,
+        entitlement_id: &str,
+        amount: u64,
+    ) -> Result<u64, ValidatorError> {
+        // Validate first
+        let entitlement = self.validate_entitlement_internal(entitlement_id).await?;
+
+        // Debit locally; the settler submits the usage on-chain in batches
+        match self.ledger.debit(&entitlement, amount, self.clock.now()).await? {
+            QuotaDebit::Debited { remaining, pending } => {
+                debug!("Metered {} against {} ({} pending settlement)", amount, entitlement_id, pending);
+                Ok(remaining)
+            }
+            QuotaDebit::Exceeded { .. } => Err(ValidatorError::QuotaExceeded),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! Off-chain usage metering.
+//!
+//! Consume calls debit quota in Redis and return immediately; a background
+//! [`Settler`] aggregates the unsettled usage per entitlement and submits it
+//! with a single `consume_entitlement` transaction every `settle_interval`
+//! seconds, or sooner once `settle_threshold` units are pending.
+//!
+//! Per entitlement the ledger keeps:
+//!
+//! * `meter:used:{id}` – quota used as seen by the validator (on-chain usage
+//!   plus everything metered since),
+//! * `meter:pending:{id}` – usage not yet submitted,
+//! * `meter:inflight:{id}` – usage moved out of `pending` by a settlement
+//!   whose outcome is not known yet, with the on-chain `quota_used` observed
+//!   before submitting.
+//!
+//! An in-flight record outlives validator crashes and failed RPCs. The next
+//! settlement pass compares it against the chain and either drops it (the
+//! transaction landed) or moves it back to `pending`, so usage is neither
+//! lost nor charged twice.
+
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock};
+use tracing::{debug, info, warn};
+
+use crate::blockchain::{BlockchainError, Entitlement, SuiBlockchainClient};
+use crate::cache::{pending_key, used_key, EntitlementCache, QuotaDebit, DIRTY_KEY};
+use crate::clock::UnixMillis;
+use crate::error::ValidatorError;
+
+/// Upper bound on a single settlement; the lock expires after this so a
+/// crashed settler does not block the entitlement forever.
+const SETTLE_LOCK_TTL: Duration = Duration::from_secs(120);
+
+/// Releases a settlement lock only if it still holds this settler's token,
+/// so a settlement that outlived its lock leaves the next holder's alone.
+///
+/// KEYS: lock key. ARGV: token. Returns 1 if released, 0 otherwise.
+const RELEASE_LOCK_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Usage submitted by a settlement whose outcome is not confirmed yet.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct InFlight {
+    amount: u64,
+    /// On-chain `quota_used` before the transaction was submitted.
+    baseline: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub entitlement_id: String,
+    pub amount: u64,
+    pub digest: String,
+}
+
+/// Local vs on-chain view of an entitlement's usage.
+///
+/// `drift` is `local_quota_used - (on_chain_quota_used + unsettled)` and is
+/// zero when every metered unit is either on-chain or still queued. A
+/// positive drift means metered usage was rejected by the contract; a
+/// negative one means the entitlement was consumed outside this validator.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reconciliation {
+    pub entitlement_id: String,
+    pub local_quota_used: u64,
+    pub on_chain_quota_used: u64,
+    pub unsettled: u64,
+    pub drift: i64,
+}
+
+pub struct MeteringLedger {
+    cache: EntitlementCache,
+    settle_threshold: u64,
+    /// How long past `expires_at` an entitlement may still be debited.
+    expiry_grace_period: Duration,
+    settle_requested: Notify,
+}
+
+impl MeteringLedger {
+    /// Creates a ledger on the same Redis instance as `cache`.
+    pub fn new(cache: &EntitlementCache, settle_threshold: u64, expiry_grace_period: Duration) -> Self {
+        Self {
+            cache: cache.clone(),
+            settle_threshold,
+            expiry_grace_period,
+            settle_requested: Notify::new(),
+        }
+    }
+
+    /// Debits `amount` from the entitlement's quota at `now` and queues it
+    /// for settlement, requesting an early settlement past the threshold.
+    ///
+    /// The usage counter is kept while the entitlement can still be debited,
+    /// and for at least the cache TTL so that it outlives cached copies of
+    /// the entitlement.
+    pub async fn debit(&self, entitlement: &Entitlement, amount: u64, now: UnixMillis) -> Result<QuotaDebit, redis::RedisError> {
+        let keep_until = entitlement.expires_at
+            .saturating_add(self.expiry_grace_period)
+            .max(now.saturating_add(self.cache.ttl()));
+        let debit = self.cache.debit_quota(entitlement, amount, keep_until).await?;
+
+        if let QuotaDebit::Debited { pending, .. } = debit {
+            if pending >= self.settle_threshold {
+                self.settle_requested.notify_one();
+            }
+        }
+
+        Ok(debit)
+    }
+
+    /// Quota used as seen by the validator, if anything was metered locally.
+    pub async fn quota_used(&self, entitlement_id: &str) -> Result<Option<u64>, redis::RedisError> {
+        let mut conn = self.cache.connection();
+        conn.get(used_key(entitlement_id)).await
+    }
+
+    /// Usage metered locally but not yet confirmed on-chain.
+    pub async fn unsettled(&self, entitlement_id: &str) -> Result<u64, redis::RedisError> {
+        let mut conn = self.cache.connection();
+        let (pending, in_flight): (Option<u64>, Option<String>) = redis::pipe()
+            .get(pending_key(entitlement_id))
+            .get(inflight_key(entitlement_id))
+            .query_async(&mut conn)
+            .await?;
+
+        let in_flight = in_flight.map(|data| parse_in_flight(&data)).transpose()?;
+        Ok(pending.unwrap_or(0) + in_flight.map_or(0, |f| f.amount))
+    }
+
+    /// Entitlements with pending or in-flight usage.
+    pub async fn dirty(&self) -> Result<Vec<String>, redis::RedisError> {
+        let mut conn = self.cache.connection();
+        conn.smembers(DIRTY_KEY).await
+    }
+
+    async fn settle_requested(&self) {
+        self.settle_requested.notified().await
+    }
+}
+
+/// Periodically submits metered usage on-chain.
+#[derive(Clone)]
+pub struct Settler {
+    ledger: Arc<MeteringLedger>,
+    blockchain: Arc<SuiBlockchainClient>,
+    cache: Arc<RwLock<EntitlementCache>>,
+    interval: Duration,
+    release_lock: redis::Script,
+}
+
+impl Settler {
+    pub fn new(
+        ledger: Arc<MeteringLedger>,
+        blockchain: Arc<SuiBlockchainClient>,
+        cache: Arc<RwLock<EntitlementCache>>,
+        interval_seconds: u64,
+    ) -> Self {
+        Self {
+            ledger,
+            blockchain,
+            cache,
+            interval: Duration::from_secs(interval_seconds),
+            release_lock: redis::Script::new(RELEASE_LOCK_SCRIPT),
+        }
+    }
+
+    /// Settles every `interval`, or earlier when a debit crosses the
+    /// ledger's threshold. Never returns.
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.ledger.settle_requested() => {}
+            }
+
+            let settled = self.settle_all().await;
+            if settled.is_empty() {
+                continue;
+            }
+
+            let ids: Vec<String> = settled.into_iter().map(|s| s.entitlement_id).collect();
+            match self.reconcile(&ids).await {
+                Ok(report) => {
+                    for entry in report.iter().filter(|entry| entry.drift != 0) {
+                        warn!(
+                            "Usage drift for {}: local {} vs on-chain {} + {} unsettled",
+                            entry.entitlement_id,
+                            entry.local_quota_used,
+                            entry.on_chain_quota_used,
+                            entry.unsettled,
+                        );
+                    }
+                }
+                Err(e) => warn!("Reconciliation failed: {}", e),
+            }
+        }
+    }
+
+    /// Settles all entitlements with unsettled usage, logging failures.
+    pub async fn settle_all(&self) -> Vec<Settlement> {
+        let ids = match self.ledger.dirty().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                warn!("Failed to list unsettled entitlements: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut settled = Vec::new();
+        for id in ids {
+            match self.settle(&id).await {
+                Ok(Some(settlement)) => settled.push(settlement),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to settle usage for {}: {}", id, e),
+            }
+        }
+
+        if !settled.is_empty() {
+            info!("Settled usage for {} entitlements", settled.len());
+        }
+        settled
+    }
+
+    /// Submits the pending usage of one entitlement. Returns `None` when there
+    /// was nothing to submit or another settler holds the entitlement.
+    pub async fn settle(&self, entitlement_id: &str) -> Result<Option<Settlement>, ValidatorError> {
+        let mut conn = self.ledger.cache.connection();
+        let lock_key = format!("meter:lock:{}", entitlement_id);
+
+        let token = hex::encode(rand::random::<[u8; 16]>());
+        let locked: Option<String> = redis::cmd("SET")
+            .arg(&lock_key).arg(&token)
+            .arg("NX").arg("PX").arg(SETTLE_LOCK_TTL.as_millis() as u64)
+            .query_async(&mut conn)
+            .await?;
+        if locked.is_none() {
+            return Ok(None);
+        }
+
+        // Re-added below if anything is left over.
+        conn.srem::<_, _, ()>(DIRTY_KEY, entitlement_id).await?;
+
+        let result = self.settle_locked(&mut conn, entitlement_id).await;
+
+        let (pending, in_flight): (Option<u64>, bool) = redis::pipe()
+            .get(pending_key(entitlement_id))
+            .exists(inflight_key(entitlement_id))
+            .query_async(&mut conn)
+            .await?;
+        if pending.unwrap_or(0) > 0 || in_flight {
+            conn.sadd::<_, _, ()>(DIRTY_KEY, entitlement_id).await?;
+        }
+        if self.release_lock.key(&lock_key).arg(&token).invoke_async::<_, i64>(&mut conn).await? == 0 {
+            warn!("Settlement of {} outlived its lock", entitlement_id);
+        }
+
+        result
+    }
+
+    async fn settle_locked(
+        &self,
+        conn: &mut ConnectionManager,
+        entitlement_id: &str,
+    ) -> Result<Option<Settlement>, ValidatorError> {
+        let pending_key = pending_key(entitlement_id);
+        let inflight_key = inflight_key(entitlement_id);
+
+        let (pending, in_flight): (Option<u64>, Option<String>) = redis::pipe()
+            .get(&pending_key)
+            .get(&inflight_key)
+            .query_async(conn)
+            .await?;
+        if pending.unwrap_or(0) == 0 && in_flight.is_none() {
+            return Ok(None);
+        }
+
+        let entitlement = self.blockchain.get_entitlement(entitlement_id).await?;
+
+        // Resolve a settlement left behind by a crash or a failed RPC.
+        if let Some(data) = in_flight {
+            let in_flight = parse_in_flight(&data)?;
+            if entitlement.quota_used >= in_flight.baseline + in_flight.amount {
+                debug!("In-flight usage of {} for {} landed on-chain", in_flight.amount, entitlement_id);
+                conn.del::<_, ()>(&inflight_key).await?;
+            } else {
+                debug!("Requeueing in-flight usage of {} for {}", in_flight.amount, entitlement_id);
+                redis::pipe()
+                    .atomic()
+                    .incr(&pending_key, in_flight.amount).ignore()
+                    .del(&inflight_key).ignore()
+                    .query_async::<_, ()>(conn)
+                    .await?;
+            }
+        }
+
+        let pending: Option<u64> = conn.get(&pending_key).await?;
+        let amount = match pending {
+            Some(amount) if amount > 0 => amount,
+            _ => return Ok(None),
+        };
+
+        let in_flight = InFlight {
+            amount,
+            baseline: entitlement.quota_used,
+        };
+        let data = serde_json::to_string(&in_flight)
+            .map_err(|e| ValidatorError::CacheError(e.to_string()))?;
+        redis::pipe()
+            .atomic()
+            .set(&inflight_key, data).ignore()
+            .decr(&pending_key, amount).ignore()
+            .query_async::<_, ()>(conn)
+            .await?;
+
+        match self.blockchain.consume_entitlement(entitlement_id, amount).await {
+            Ok(digest) => {
+                conn.del::<_, ()>(&inflight_key).await?;
+                self.cache.write().await.invalidate(entitlement_id).await?;
+                debug!("Settled {} for {} in transaction {}", amount, entitlement_id, digest);
+
+                Ok(Some(Settlement {
+                    entitlement_id: entitlement_id.to_string(),
+                    amount,
+                    digest,
+                }))
+            }
+            // The contract refused the usage; retrying cannot succeed.
+            // Reconciliation reports it as drift.
+            Err(e @ BlockchainError::MoveAbort { .. }) => {
+                conn.del::<_, ()>(&inflight_key).await?;
+                self.cache.write().await.invalidate(entitlement_id).await?;
+                Err(e.into())
+            }
+            // The transaction may still have landed; keep the in-flight
+            // record for the next pass to resolve.
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Compares local and on-chain usage for the given entitlements.
+    pub async fn reconcile(&self, entitlement_ids: &[String]) -> Result<Vec<Reconciliation>, ValidatorError> {
+        let on_chain = self.blockchain.get_entitlements(entitlement_ids).await?;
+
+        let mut report = Vec::with_capacity(entitlement_ids.len());
+        for (id, entitlement) in entitlement_ids.iter().zip(on_chain) {
+            let entitlement = match entitlement {
+                Ok(entitlement) => entitlement,
+                Err(e) => {
+                    warn!("Skipping reconciliation of {}: {}", id, e);
+                    continue;
+                }
+            };
+
+            let unsettled = self.ledger.unsettled(id).await?;
+            let local_quota_used = self.ledger.quota_used(id).await?
+                .unwrap_or(entitlement.quota_used);
+
+            report.push(Reconciliation {
+                entitlement_id: id.clone(),
+                local_quota_used,
+                on_chain_quota_used: entitlement.quota_used,
+                unsettled,
+                drift: local_quota_used as i64 - (entitlement.quota_used + unsettled) as i64,
+            });
+        }
+
+        Ok(report)
+    }
+}
+
+fn inflight_key(entitlement_id: &str) -> String {
+    format!("meter:inflight:{}", entitlement_id)
+}
+
+fn parse_in_flight(data: &str) -> Result<InFlight, redis::RedisError> {
+    serde_json::from_str(data).map_err(|e| {
+        redis::RedisError::from((redis::ErrorKind::TypeError, "Invalid in-flight settlement", e.to_string()))
+    })
+}
@@ -0,0 +1,201 @@
+//! Prometheus metrics of a validator, served as text on `/metrics`.
+//!
+//! Call outcomes and fullnode latencies are recorded as they happen. Cache
+//! counters and the settlement backlog are sampled when the endpoint is
+//! scraped. Each service has its own registry, so several can share a
+//! process.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use inframint_types::error_code;
+use inframint_types::proto::ErrorCode;
+use tonic::Status;
+
+use crate::cache::CacheStats;
+use crate::error::ValidatorError;
+use crate::ValidatorServiceImpl;
+
+/// How a validate, consume or signature call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Valid,
+    /// Unknown or inactive entitlement, or an unusable access token.
+    Invalid,
+    Expired,
+    QuotaExceeded,
+    RateLimited,
+    BadSignature,
+    /// The validator or one of its dependencies failed.
+    Error,
+}
+
+impl Outcome {
+    /// Outcome of a call that failed with `status`.
+    pub fn of_status(status: &Status) -> Self {
+        match error_code(status) {
+            Some(ErrorCode::RateLimited) => Self::RateLimited,
+            Some(ErrorCode::QuotaExceeded) => Self::QuotaExceeded,
+            Some(ErrorCode::Expired) => Self::Expired,
+            Some(ErrorCode::NotFound | ErrorCode::Inactive | ErrorCode::WrongService) => Self::Invalid,
+            Some(ErrorCode::BadSignature) => Self::BadSignature,
+            _ => Self::Error,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Invalid => "invalid",
+            Self::Expired => "expired",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::RateLimited => "rate_limited",
+            Self::BadSignature => "bad_signature",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl From<&ValidatorError> for Outcome {
+    fn from(error: &ValidatorError) -> Self {
+        match error {
+            ValidatorError::InvalidEntitlement
+            | ValidatorError::EntitlementInactive
+            | ValidatorError::AccessToken(_) => Self::Invalid,
+            ValidatorError::EntitlementExpired => Self::Expired,
+            ValidatorError::QuotaExceeded => Self::QuotaExceeded,
+            ValidatorError::RateLimitExceeded => Self::RateLimited,
+            ValidatorError::NotAuthorized | ValidatorError::SignatureValidationFailed => Self::BadSignature,
+            _ => Self::Error,
+        }
+    }
+}
+
+pub struct ValidatorMetrics {
+    registry: Registry,
+    calls: IntCounterVec,
+    chain_rpc_duration: HistogramVec,
+    cache_lookups: IntCounterVec,
+    cache_hit_ratio: Gauge,
+    settlement_backlog: IntGauge,
+    settlement_backlog_units: IntGauge,
+}
+
+impl ValidatorMetrics {
+    pub fn new() -> Self {
+        let calls = IntCounterVec::new(
+            Opts::new("inframint_validator_calls_total", "Validator calls by outcome"),
+            &["call", "outcome"],
+        ).expect("valid metric");
+        let chain_rpc_duration = HistogramVec::new(
+            HistogramOpts::new("inframint_validator_chain_rpc_duration_seconds", "Fullnode JSON-RPC latency"),
+            &["method"],
+        ).expect("valid metric");
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("inframint_validator_cache_lookups_total", "Entitlement cache lookups by result"),
+            &["result"],
+        ).expect("valid metric");
+        let cache_hit_ratio = Gauge::new(
+            "inframint_validator_cache_hit_ratio",
+            "Share of entitlement lookups served without the fullnode",
+        ).expect("valid metric");
+        let settlement_backlog = IntGauge::new(
+            "inframint_validator_settlement_backlog",
+            "Entitlements with usage not yet settled on-chain",
+        ).expect("valid metric");
+        let settlement_backlog_units = IntGauge::new(
+            "inframint_validator_settlement_backlog_units",
+            "Units of usage not yet settled on-chain",
+        ).expect("valid metric");
+
+        let registry = Registry::new();
+        registry.register(Box::new(calls.clone())).expect("unique metric");
+        registry.register(Box::new(chain_rpc_duration.clone())).expect("unique metric");
+        registry.register(Box::new(cache_lookups.clone())).expect("unique metric");
+        registry.register(Box::new(cache_hit_ratio.clone())).expect("unique metric");
+        registry.register(Box::new(settlement_backlog.clone())).expect("unique metric");
+        registry.register(Box::new(settlement_backlog_units.clone())).expect("unique metric");
+
+        Self {
+            registry,
+            calls,
+            chain_rpc_duration,
+            cache_lookups,
+            cache_hit_ratio,
+            settlement_backlog,
+            settlement_backlog_units,
+        }
+    }
+
+    pub fn record_call(&self, call: &str, outcome: Outcome) {
+        self.calls.with_label_values(&[call, outcome.as_str()]).inc();
+    }
+
+    /// Calls of `call` that ended with `outcome` so far.
+    pub fn calls(&self, call: &str, outcome: Outcome) -> u64 {
+        self.calls.with_label_values(&[call, outcome.as_str()]).get()
+    }
+
+    /// Histogram for the blockchain client to time its calls with.
+    pub fn chain_rpc_duration(&self) -> HistogramVec {
+        self.chain_rpc_duration.clone()
+    }
+
+    pub(crate) fn set_cache_stats(&self, stats: CacheStats) {
+        for (result, total) in [
+            ("local_hit", stats.local_hits),
+            ("redis_hit", stats.redis_hits),
+            ("miss", stats.misses),
+        ] {
+            let counter = self.cache_lookups.with_label_values(&[result]);
+            counter.inc_by(total.saturating_sub(counter.get()));
+        }
+
+        let hits = stats.local_hits + stats.redis_hits;
+        let lookups = hits + stats.misses;
+        if lookups > 0 {
+            self.cache_hit_ratio.set(hits as f64 / lookups as f64);
+        }
+    }
+
+    pub(crate) fn set_settlement_backlog(&self, entitlements: usize, units: u64) {
+        self.settlement_backlog.set(i64::try_from(entitlements).unwrap_or(i64::MAX));
+        self.settlement_backlog_units.set(i64::try_from(units).unwrap_or(i64::MAX));
+    }
+
+    /// Everything registered, in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
+
+impl Default for ValidatorMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serves `GET /metrics` for `service`.
+pub fn router(service: ValidatorServiceImpl) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(service)
+}
+
+async fn metrics(State(service): State<ValidatorServiceImpl>) -> Response {
+    match service.render_metrics().await {
+        Ok(body) => ([(header::CONTENT_TYPE, TextEncoder::new().format_type().to_string())], body).into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    }
+}
@@ -1,45 +1,208 @@
+//! Rate limiting with GCRA, the generic cell rate algorithm.
+//!
+//! GCRA behaves like a token bucket holding `burst` tokens that refill at
+//! `max_requests` per `window`, but only stores one timestamp per key: the
+//! theoretical arrival time (TAT) of the next request. A key whose TAT is in
+//! the past has a full bucket and carries no information, so both
+//! implementations drop it.
+
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-pub struct RateLimiter {
-    windows: Arc<Mutex<HashMap<String, Vec<u64>>>>,
-    window_size: u64,
-    max_requests: u64,
+use crate::clock::{Clock, SystemClock};
+use crate::error::ValidatorError;
+
+/// Allows `max_requests` per `window` on average and up to `burst` requests
+/// at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_requests: u64,
+    pub window: Duration,
+    pub burst: u64,
 }
 
-impl RateLimiter {
-    pub fn new(window_size: u64, max_requests: u64) -> Self {
+impl RateLimit {
+    /// `max_requests` per `window`, all of which may arrive at once.
+    pub fn per_window(max_requests: u64, window: Duration) -> Self {
         Self {
-            windows: Arc::new(Mutex::new(HashMap::new())),
-            window_size,
             max_requests,
+            window,
+            burst: max_requests,
         }
     }
 
-    pub async fn check(&self, key: &str) -> bool {
-        let mut windows = self.windows.lock().await;
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// Milliseconds for one request's token to refill.
+    fn emission_interval_ms(&self) -> f64 {
+        self.window.as_millis() as f64 / self.max_requests.max(1) as f64
+    }
 
-        let window = windows.entry(key.to_string()).or_insert_with(Vec::new);
+    /// How far ahead of now the TAT may be while still admitting a request.
+    fn tolerance_ms(&self) -> f64 {
+        self.emission_interval_ms() * self.burst.max(1) as f64
+    }
+}
+
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Records a request for `key` and returns whether it is within `limit`.
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<bool, ValidatorError>;
 
-        // Remove old timestamps
-        window.retain(|&t| t > now - self.window_size);
+    /// Forgets all requests recorded for `key`.
+    async fn reset(&self, key: &str) -> Result<(), ValidatorError>;
+}
+
+/// Applies GCRA to the stored TAT, returning the new TAT if the request is
+/// admitted.
+fn admit(tat: Option<f64>, now: f64, limit: RateLimit) -> Option<f64> {
+    let new_tat = tat.unwrap_or(now).max(now) + limit.emission_interval_ms();
+    (new_tat - now <= limit.tolerance_ms()).then_some(new_tat)
+}
+
+/// Per-process limiter for single-replica deployments and tests.
+pub struct InMemoryRateLimiter {
+    state: Mutex<InMemoryState>,
+    sweep_interval: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+struct InMemoryState {
+    tats: HashMap<String, f64>,
+    last_sweep: f64,
+}
 
-        if window.len() >= self.max_requests as usize {
-            return false;
+impl InMemoryRateLimiter {
+    pub fn new() -> Self {
+        Self::with_sweep_interval(Duration::from_secs(60))
+    }
+
+    /// Idle keys are dropped at most once per `sweep_interval`.
+    pub fn with_sweep_interval(sweep_interval: Duration) -> Self {
+        Self {
+            state: Mutex::new(InMemoryState {
+                tats: HashMap::new(),
+                last_sweep: now_ms(&SystemClock),
+            }),
+            sweep_interval,
+            clock: Arc::new(SystemClock),
         }
+    }
+
+    /// Measures windows with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.state.get_mut().last_sweep = now_ms(&*clock);
+        self.clock = clock;
+        self
+    }
 
-        window.push(now);
-        true
+    /// Number of keys currently tracked.
+    pub async fn len(&self) -> usize {
+        self.state.lock().await.tats.len()
     }
 
-    pub async fn reset(&self, key: &str) {
-        let mut windows = self.windows.lock().await;
-        windows.remove(key);
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
     }
 }
+
+impl Default for InMemoryRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryRateLimiter {
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<bool, ValidatorError> {
+        let mut state = self.state.lock().await;
+        let now = now_ms(&*self.clock);
+
+        if now - state.last_sweep >= self.sweep_interval.as_millis() as f64 {
+            state.tats.retain(|_, tat| *tat > now);
+            state.last_sweep = now;
+        }
+
+        match admit(state.tats.get(key).copied(), now, limit) {
+            Some(tat) => {
+                state.tats.insert(key.to_string(), tat);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), ValidatorError> {
+        self.state.lock().await.tats.remove(key);
+        Ok(())
+    }
+}
+
+/// GCRA in a single script so replicas sharing a Redis share limits. Uses the
+/// Redis clock, keeping replicas with skewed clocks consistent.
+///
+/// KEYS: TAT key. ARGV: emission interval (ms), tolerance (ms).
+/// Returns 1 if admitted, 0 otherwise.
+const GCRA_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + tonumber(time[2]) / 1000
+local interval = tonumber(ARGV[1])
+local tolerance = tonumber(ARGV[2])
+
+local tat = tonumber(redis.call('GET', KEYS[1]) or now)
+local new_tat = math.max(tat, now) + interval
+if new_tat - now > tolerance then
+    return 0
+end
+
+-- The key expires once the bucket is full again
+redis.call('SET', KEYS[1], string.format('%.3f', new_tat), 'PX', math.ceil(new_tat - now))
+return 1
+";
+
+/// Limiter shared by every replica using the same Redis.
+pub struct RedisRateLimiter {
+    conn: ConnectionManager,
+    script: redis::Script,
+}
+
+impl RedisRateLimiter {
+    pub async fn new(redis_url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self {
+            conn: ConnectionManager::new(client).await?,
+            script: redis::Script::new(GCRA_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<bool, ValidatorError> {
+        let mut conn = self.conn.clone();
+
+        let admitted: bool = self.script
+            .key(redis_key(key))
+            .arg(limit.emission_interval_ms())
+            .arg(limit.tolerance_ms())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(admitted)
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), ValidatorError> {
+        let mut conn = self.conn.clone();
+        redis::cmd("DEL").arg(redis_key(key)).query_async::<_, ()>(&mut conn).await?;
+        Ok(())
+    }
+}
+
+fn redis_key(key: &str) -> String {
+    format!("ratelimit:{}", key)
+}
+
+fn now_ms(clock: &dyn Clock) -> f64 {
+    clock.now().as_millis() as f64
+}
@@ -0,0 +1,167 @@
+//! Replay protection for signed requests.
+//!
+//! Clients sign a `RequestPayload` naming the entitlement, the validator it is
+//! meant for, the amount, a nonce and when it was issued. A payload is only
+//! accepted while fresh, and only once: its nonce is claimed in Redis for
+//! longer than the payload stays fresh, so a captured request cannot be
+//! replayed against this or any other replica.
+
+use prost::Message;
+use redis::aio::ConnectionManager;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::blockchain::{normalize_sui_address, Entitlement};
+use crate::cache::EntitlementCache;
+use crate::clock::UnixMillis;
+use crate::proto::{RequestPayload, SignedRequest};
+use crate::signature::{SignatureError, SuiKeyPair, SuiSignature};
+
+/// `RequestPayload` format understood by this validator.
+pub const PAYLOAD_VERSION: u32 = 1;
+
+const MAX_NONCE_LENGTH: usize = 128;
+
+#[derive(Error, Debug)]
+pub enum SignedRequestError {
+    #[error("Signed request payload is malformed")]
+    MalformedPayload,
+
+    #[error("Unsupported signed request version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Signed request {0} does not match the request")]
+    Mismatch(&'static str),
+
+    #[error("Signed request nonce is invalid")]
+    InvalidNonce,
+
+    #[error("Signed request is not fresh")]
+    Stale,
+
+    #[error("Signed request was already used")]
+    Replayed,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+
+    #[error("Nonce store error: {0}")]
+    Store(#[from] redis::RedisError),
+}
+
+/// Nonces claimed by accepted requests, shared through Redis.
+#[derive(Clone)]
+pub struct NonceStore {
+    conn: ConnectionManager,
+}
+
+impl NonceStore {
+    /// Creates a store on the same Redis instance as `cache`.
+    pub fn new(cache: &EntitlementCache) -> Self {
+        Self {
+            conn: cache.connection(),
+        }
+    }
+
+    /// Claims `nonce` for `entitlement_id` for `ttl`. Returns false if it was
+    /// already claimed.
+    pub async fn claim(&self, entitlement_id: &str, nonce: &str, ttl: Duration) -> Result<bool, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(format!("nonce:{}:{}", entitlement_id, nonce))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(claimed.is_some())
+    }
+}
+
+/// Checks signed requests against the entitlement they target.
+#[derive(Clone)]
+pub struct SignedRequestVerifier {
+    audience: String,
+    max_age: Duration,
+    nonces: NonceStore,
+}
+
+impl SignedRequestVerifier {
+    /// Accepts requests addressed to `audience` whose `issued_at_ms` is
+    /// within `max_age` of the validator's clock, in either direction.
+    pub fn new(nonces: NonceStore, audience: &str, max_age: Duration) -> Self {
+        Self {
+            audience: audience.to_string(),
+            max_age,
+            nonces,
+        }
+    }
+
+    /// Verifies that `signed` authorizes `amount` against `entitlement` at
+    /// `now`, and claims its nonce. The nonce is only claimed once every
+    /// other check has passed.
+    pub async fn verify(
+        &self,
+        signed: &SignedRequest,
+        entitlement: &Entitlement,
+        amount: u64,
+        now: UnixMillis,
+    ) -> Result<RequestPayload, SignedRequestError> {
+        let payload = RequestPayload::decode(signed.payload.as_slice())
+            .map_err(|_| SignedRequestError::MalformedPayload)?;
+
+        if payload.version != PAYLOAD_VERSION {
+            return Err(SignedRequestError::UnsupportedVersion(payload.version));
+        }
+        if payload.entitlement_id != entitlement.id {
+            return Err(SignedRequestError::Mismatch("entitlement"));
+        }
+        if payload.service_id != entitlement.service_id {
+            return Err(SignedRequestError::Mismatch("service"));
+        }
+        if payload.audience != self.audience {
+            return Err(SignedRequestError::Mismatch("audience"));
+        }
+        if payload.amount != amount {
+            return Err(SignedRequestError::Mismatch("amount"));
+        }
+        if payload.nonce.is_empty() || payload.nonce.len() > MAX_NONCE_LENGTH {
+            return Err(SignedRequestError::InvalidNonce);
+        }
+
+        let issued_at = UnixMillis(payload.issued_at_ms);
+        if issued_at < now.saturating_sub(self.max_age) || issued_at > now.saturating_add(self.max_age) {
+            return Err(SignedRequestError::Stale);
+        }
+
+        let signature = SuiSignature::from_base64(&signed.signature)?;
+        let buyer = normalize_sui_address(&entitlement.buyer);
+        if buyer.as_deref() != Some(signature.signer_address().as_str())
+            || !signature.verify_personal_message(&signed.payload)?
+        {
+            return Err(SignedRequestError::InvalidSignature);
+        }
+
+        // A payload issued up to `max_age` ahead is accepted until `max_age`
+        // after it was issued, so the claim must outlive both.
+        if !self.nonces.claim(&entitlement.id, &payload.nonce, self.max_age * 2).await? {
+            return Err(SignedRequestError::Replayed);
+        }
+
+        Ok(payload)
+    }
+}
+
+/// Encodes `payload` and signs it as a Sui personal message with `keypair`.
+pub fn sign_request(keypair: &SuiKeyPair, payload: &RequestPayload) -> SignedRequest {
+    let payload = payload.encode_to_vec();
+    SignedRequest {
+        signature: keypair.sign_personal_message(&payload).to_base64(),
+        payload,
+    }
+}
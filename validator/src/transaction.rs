@@ -0,0 +1,152 @@
+//! Minimal BCS model of Sui `TransactionData` for the programmable
+//! transactions the validator submits. Variant order mirrors `sui-types`
+//! and must not change.
+
+use serde::Serialize;
+
+use crate::blockchain::{normalize_sui_address, BlockchainError};
+
+pub type ObjectId = [u8; 32];
+pub type SuiAddress = [u8; 32];
+
+/// `(object id, version, digest)`; the digest is BCS-encoded as a byte vector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectRef(pub ObjectId, pub u64, pub Vec<u8>);
+
+impl ObjectRef {
+    pub fn parse(object_id: &str, version: u64, digest: &str) -> Result<Self, BlockchainError> {
+        let digest = bs58::decode(digest)
+            .into_vec()
+            .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+        if digest.len() != 32 {
+            return Err(BlockchainError::InvalidResponseFormat);
+        }
+
+        Ok(ObjectRef(parse_object_id(object_id)?, version, digest))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum ObjectArg {
+    ImmOrOwnedObject(ObjectRef),
+    SharedObject {
+        id: ObjectId,
+        initial_shared_version: u64,
+        mutable: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum CallArg {
+    Pure(Vec<u8>),
+    Object(ObjectArg),
+}
+
+impl CallArg {
+    pub fn pure_u64(value: u64) -> Self {
+        CallArg::Pure(value.to_le_bytes().to_vec())
+    }
+
+    /// A `vector<u8>` argument.
+    pub fn pure_bytes(value: &[u8]) -> Result<Self, BlockchainError> {
+        bcs::to_bytes(value)
+            .map(CallArg::Pure)
+            .map_err(|e| BlockchainError::ContractCallError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum Argument {
+    GasCoin,
+    Input(u16),
+    Result(u16),
+    NestedResult(u16, u16),
+}
+
+/// Type arguments are never needed by the entitlement entry functions.
+#[derive(Debug, Clone, Serialize)]
+pub enum TypeTag {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgrammableMoveCall {
+    pub package: ObjectId,
+    pub module: String,
+    pub function: String,
+    pub type_arguments: Vec<TypeTag>,
+    pub arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Command {
+    MoveCall(Box<ProgrammableMoveCall>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgrammableTransaction {
+    pub inputs: Vec<CallArg>,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+enum TransactionKind {
+    ProgrammableTransaction(ProgrammableTransaction),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GasData {
+    pub payment: Vec<ObjectRef>,
+    pub owner: SuiAddress,
+    pub price: u64,
+    pub budget: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+enum TransactionExpiration {
+    None,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TransactionDataV1 {
+    kind: TransactionKind,
+    sender: SuiAddress,
+    gas_data: GasData,
+    expiration: TransactionExpiration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+enum TransactionData {
+    V1(TransactionDataV1),
+}
+
+/// Serializes a programmable transaction paid for by `sender`.
+pub fn transaction_bytes(
+    transaction: ProgrammableTransaction,
+    sender: SuiAddress,
+    gas_data: GasData,
+) -> Result<Vec<u8>, BlockchainError> {
+    let data = TransactionData::V1(TransactionDataV1 {
+        kind: TransactionKind::ProgrammableTransaction(transaction),
+        sender,
+        gas_data,
+        expiration: TransactionExpiration::None,
+    });
+
+    bcs::to_bytes(&data).map_err(|e| BlockchainError::ContractCallError(e.to_string()))
+}
+
+/// Serializes a programmable transaction as a `TransactionKind`, the form
+/// `sui_devInspectTransactionBlock` takes.
+pub fn transaction_kind_bytes(transaction: ProgrammableTransaction) -> Result<Vec<u8>, BlockchainError> {
+    bcs::to_bytes(&TransactionKind::ProgrammableTransaction(transaction))
+        .map_err(|e| BlockchainError::ContractCallError(e.to_string()))
+}
+
+pub fn parse_object_id(object_id: &str) -> Result<ObjectId, BlockchainError> {
+    let normalized = normalize_sui_address(object_id)
+        .ok_or(BlockchainError::InvalidResponseFormat)?;
+
+    let mut id = [0u8; 32];
+    hex::decode_to_slice(&normalized[2..], &mut id)
+        .map_err(|_| BlockchainError::InvalidResponseFormat)?;
+    Ok(id)
+}
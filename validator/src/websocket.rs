@@ -0,0 +1,129 @@
+//! Metering WebSocket connections proxied by the gateway.
+//!
+//! Once the handshake is admitted, frames are relayed between the client and
+//! the upstream. Every text or binary message, in either direction, is
+//! debited before it is forwarded, and the connection is charged again each
+//! billing period it stays open. When a debit fails because the quota is
+//! used up or the entitlement expired, the client is sent a policy violation
+//! close frame (1008) and both sides are closed.
+
+use futures_util::{SinkExt, StreamExt};
+use std::borrow::Cow;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::debug;
+
+use crate::config::GatewayConfig;
+use crate::error::ValidatorError;
+use crate::ValidatorServiceImpl;
+
+/// What a WebSocket connection costs once it is open.
+#[derive(Debug, Clone, Copy)]
+pub struct WebSocketMetering {
+    /// Charged per text or binary message.
+    pub message_cost: u64,
+    /// Charged per `billing_period` the connection stays open.
+    pub connection_cost: u64,
+    /// Also how often the entitlement is checked when no messages flow.
+    pub billing_period: Duration,
+}
+
+impl WebSocketMetering {
+    pub fn new(config: &GatewayConfig) -> Self {
+        Self {
+            message_cost: config.ws_message_cost,
+            connection_cost: config.ws_connection_cost,
+            billing_period: Duration::from_secs(config.ws_billing_period.max(1)),
+        }
+    }
+}
+
+/// Relays messages between `client` and `upstream` until either side closes
+/// or the entitlement can no longer pay for them.
+pub async fn relay<C, U>(
+    validator: &ValidatorServiceImpl,
+    entitlement_id: &str,
+    metering: WebSocketMetering,
+    client: WebSocketStream<C>,
+    upstream: WebSocketStream<U>,
+) where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    let mut billing = interval_at(Instant::now() + metering.billing_period, metering.billing_period);
+    billing.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let refusal = loop {
+        tokio::select! {
+            message = client_rx.next() => match message {
+                Some(Ok(message)) => {
+                    if let Err(e) = meter_message(validator, entitlement_id, metering, &message).await {
+                        break Some(e);
+                    }
+                    let _ = upstream_tx.send(message).await;
+                }
+                _ => break None,
+            },
+            message = upstream_rx.next() => match message {
+                Some(Ok(message)) => {
+                    if let Err(e) = meter_message(validator, entitlement_id, metering, &message).await {
+                        break Some(e);
+                    }
+                    let _ = client_tx.send(message).await;
+                }
+                _ => break None,
+            },
+            _ = billing.tick() => {
+                if let Err(e) = validator.consume_for_stream(entitlement_id, metering.connection_cost).await {
+                    break Some(e);
+                }
+            }
+        }
+    };
+
+    if let Some(e) = refusal {
+        debug!("Closing WebSocket of {}: {}", entitlement_id, e);
+        let _ = client_tx.send(Message::Close(Some(close_frame(&e)))).await;
+        let _ = upstream_tx.send(Message::Close(None)).await;
+    }
+    let _ = client_tx.close().await;
+    let _ = upstream_tx.close().await;
+}
+
+async fn meter_message(
+    validator: &ValidatorServiceImpl,
+    entitlement_id: &str,
+    metering: WebSocketMetering,
+    message: &Message,
+) -> Result<(), ValidatorError> {
+    if !matches!(message, Message::Text(_) | Message::Binary(_)) || metering.message_cost == 0 {
+        return Ok(());
+    }
+    validator.consume_for_stream(entitlement_id, metering.message_cost).await.map(|_| ())
+}
+
+/// Policy violation for refusals the client can act on, internal error
+/// otherwise.
+fn close_frame(error: &ValidatorError) -> CloseFrame<'static> {
+    match error {
+        ValidatorError::QuotaExceeded
+        | ValidatorError::InvalidEntitlement
+        | ValidatorError::EntitlementExpired
+        | ValidatorError::EntitlementInactive => CloseFrame {
+            code: CloseCode::Policy,
+            reason: Cow::Owned(error.to_string()),
+        },
+        _ => CloseFrame {
+            code: CloseCode::Error,
+            reason: Cow::Borrowed("Metering unavailable"),
+        },
+    }
+}
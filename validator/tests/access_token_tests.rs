@@ -0,0 +1,102 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use std::time::Duration;
+use validator::access_token::{AccessTokenError, AccessTokenIssuer};
+use validator::blockchain::Entitlement;
+use validator::cache::CachedEntitlement;
+use validator::clock::{UnixMillis, UnixSeconds};
+use validator::signature::{SignatureScheme, SuiKeyPair};
+
+const ISSUER: &str = "inframint-validator";
+const NOW: UnixMillis = UnixMillis(1_729_166_400_000);
+const TTL: Duration = Duration::from_secs(900);
+
+fn issuer(secret: u8, name: &str) -> AccessTokenIssuer {
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[secret; 32]).unwrap();
+    AccessTokenIssuer::new(keypair, name).unwrap()
+}
+
+fn entitlement(expires_at: UnixMillis) -> CachedEntitlement {
+    CachedEntitlement {
+        entitlement: Entitlement {
+            id: format!("0x{}", "f7".repeat(32)),
+            service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+            buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+            tier_id: 1,
+            quota_requests: 1000,
+            quota_used: 0,
+            purchased_at: UnixMillis(1_729_000_000_000),
+            expires_at,
+            active: true,
+        },
+        rate_limit_per_second: 5,
+    }
+}
+
+#[test]
+fn test_issued_token_verifies_until_it_expires() {
+    let issuer = issuer(0x44, ISSUER);
+    let (token, claims) = issuer.issue(&entitlement(UnixMillis(4_102_444_800_000)), NOW, TTL);
+
+    assert_eq!(token.split('.').count(), 3);
+    assert_eq!(claims.ent, entitlement(UnixMillis(0)).entitlement.id);
+    assert_eq!(claims.svc, "11111111-1111-1111-1111-111111111111");
+    assert_eq!(claims.rps, 5);
+    assert_eq!(claims.iat, UnixSeconds(1_729_166_400));
+    assert_eq!(claims.exp, UnixSeconds(1_729_166_400 + 900));
+
+    let last_valid = UnixMillis::from(claims.exp).saturating_sub(Duration::from_millis(1));
+    assert_eq!(issuer.verify(&token, last_valid).unwrap(), claims);
+    assert!(matches!(issuer.verify(&token, claims.exp.into()), Err(AccessTokenError::Expired)));
+}
+
+#[test]
+fn test_token_never_outlives_the_entitlement() {
+    let expires_at = NOW.saturating_add(Duration::from_millis(60_500));
+    let (_, claims) = issuer(0x44, ISSUER).issue(&entitlement(expires_at), NOW, TTL);
+
+    assert_eq!(claims.exp, UnixSeconds(1_729_166_460));
+}
+
+#[test]
+fn test_rejects_tokens_from_other_keys_or_issuers() {
+    let (token, _) = issuer(0x45, ISSUER).issue(&entitlement(UnixMillis(4_102_444_800_000)), NOW, TTL);
+    assert!(matches!(issuer(0x44, ISSUER).verify(&token, NOW), Err(AccessTokenError::InvalidSignature)));
+
+    let (token, _) = issuer(0x44, "another-validator").issue(&entitlement(UnixMillis(4_102_444_800_000)), NOW, TTL);
+    assert!(matches!(issuer(0x44, ISSUER).verify(&token, NOW), Err(AccessTokenError::WrongIssuer(_))));
+}
+
+#[test]
+fn test_rejects_tampered_tokens() {
+    let issuer = issuer(0x44, ISSUER);
+    let (token, claims) = issuer.issue(&entitlement(UnixMillis(4_102_444_800_000)), NOW, TTL);
+    let segments: Vec<&str> = token.split('.').collect();
+
+    // Raising the rate limit invalidates the signature
+    let mut forged = serde_json::to_value(&claims).unwrap();
+    forged["rps"] = 1_000_000.into();
+    let forged = BASE64_URL.encode(serde_json::to_vec(&forged).unwrap());
+    let token_with_forged_claims = format!("{}.{}.{}", segments[0], forged, segments[2]);
+    assert!(matches!(
+        issuer.verify(&token_with_forged_claims, NOW),
+        Err(AccessTokenError::InvalidSignature)
+    ));
+
+    let none = BASE64_URL.encode(br#"{"alg":"none","typ":"JWT"}"#);
+    let unsigned = format!("{}.{}.", none, segments[1]);
+    assert!(matches!(
+        issuer.verify(&unsigned, NOW),
+        Err(AccessTokenError::UnsupportedAlgorithm(alg)) if alg == "none"
+    ));
+
+    assert!(matches!(issuer.verify("not-a-token", NOW), Err(AccessTokenError::Malformed)));
+}
+
+#[test]
+fn test_requires_an_ed25519_key() {
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &[0x22; 32]).unwrap();
+    assert!(matches!(
+        AccessTokenIssuer::new(keypair, ISSUER),
+        Err(AccessTokenError::UnsupportedKey)
+    ));
+}
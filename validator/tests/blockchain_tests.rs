@@ -0,0 +1,396 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::json;
+use validator::blockchain::{
+    parse_move_abort, service_id_bytes, BlockchainError, EntitlementEvent, EventId, PricingTier, SuiBlockchainClient,
+    ValidatorSigner,
+};
+use validator::clock::UnixMillis;
+use validator::error::ValidatorError;
+use validator::signature::{transaction_digest, SignatureScheme, SuiKeyPair, SuiSignature};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const BUYER: &str = "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207";
+const MISSING_ID: &str = "0xaa";
+const VALIDATOR_ADDRESS: &str = "0xe3cef37dac8ae923cd30422d8a9e9eae7d4d68bc1b287fc1567556c7abf92ff3";
+const VALIDATOR_CAP_ID: &str = "0x6f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+const REGISTRY_ID: &str = "0x7a3c5e7f9b1d3f5a7c9e1b3d5f7a9c1e3b5d7f9a1c3e5b7d9f1a3c5e7b9d1f3a";
+const SERVICE_ID: &str = "11111111-1111-1111-1111-111111111111";
+const GAS_COIN_ID: &str = "3e5a7c9e1b3d5f7a9c1e3b5d7f9a1c3e5b7d9f1a3c5e7b9d1f3a5c7e9b1d3f5a";
+
+/// Replays a recorded fullnode response for the given JSON-RPC method.
+async fn mount_fixture(server: &MockServer, rpc_method: &str, params: serde_json::Value, fixture: &str) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": rpc_method, "params": params })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(fixture, "application/json"))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+/// Writes a `sui.keystore` holding the validator key and loads it back.
+fn validator_signer(name: &str) -> ValidatorSigner {
+    let path = std::env::temp_dir().join(format!("inframint-{}-{}.keystore", name, std::process::id()));
+    let mut entry = vec![0x00];
+    entry.extend_from_slice(&[0x44; 32]);
+    std::fs::write(&path, json!([BASE64.encode(entry)]).to_string()).unwrap();
+
+    let keypair = SuiKeyPair::from_keystore_file(path.to_str().unwrap(), Some(VALIDATOR_ADDRESS)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    ValidatorSigner {
+        keypair,
+        validator_cap_id: VALIDATOR_CAP_ID.to_string(),
+        gas_budget: 10_000_000,
+    }
+}
+
+/// Mounts the object, gas and execution responses used by `consume_entitlement`.
+async fn mount_consume_fixtures(server: &MockServer, execute_fixture: &str) {
+    mount_fixture(
+        server,
+        "sui_multiGetObjects",
+        json!([[VALIDATOR_CAP_ID, ENTITLEMENT_ID]]),
+        include_str!("fixtures/sui_multiGetObjects_consume_inputs.json"),
+    ).await;
+    mount_fixture(
+        server,
+        "suix_getCoins",
+        json!([VALIDATOR_ADDRESS, "0x2::sui::SUI"]),
+        include_str!("fixtures/suix_getCoins.json"),
+    ).await;
+    mount_fixture(
+        server,
+        "suix_getReferenceGasPrice",
+        json!([]),
+        include_str!("fixtures/suix_getReferenceGasPrice.json"),
+    ).await;
+    mount_fixture(server, "sui_executeTransactionBlock", json!([]), execute_fixture).await;
+}
+
+#[tokio::test]
+async fn test_get_entitlement_decodes_move_fields() {
+    let server = MockServer::start().await;
+    mount_fixture(
+        &server,
+        "sui_getObject",
+        json!([ENTITLEMENT_ID]),
+        include_str!("fixtures/sui_getObject_entitlement.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let entitlement = client.get_entitlement(ENTITLEMENT_ID).await.unwrap();
+
+    assert_eq!(entitlement.id, ENTITLEMENT_ID);
+    assert_eq!(entitlement.service_id, "11111111-1111-1111-1111-111111111111");
+    assert_eq!(entitlement.buyer, BUYER);
+    assert_eq!(entitlement.tier_id, 1);
+    assert_eq!(entitlement.quota_requests, 1000);
+    assert_eq!(entitlement.quota_used, 0);
+    assert_eq!(entitlement.purchased_at, UnixMillis(1_729_166_400_000));
+    assert_eq!(entitlement.expires_at, UnixMillis(4_102_444_800_000));
+    assert!(entitlement.active);
+}
+
+#[tokio::test]
+async fn test_get_entitlement_not_found() {
+    let server = MockServer::start().await;
+    mount_fixture(
+        &server,
+        "sui_getObject",
+        json!(["0x00000000000000000000000000000000000000000000000000000000000000aa"]),
+        include_str!("fixtures/sui_getObject_not_exists.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.get_entitlement(MISSING_ID).await;
+
+    assert!(matches!(result, Err(BlockchainError::EntitlementNotFound)));
+}
+
+#[tokio::test]
+async fn test_get_entitlement_rejects_other_object_types() {
+    let server = MockServer::start().await;
+    let coin_id = "0x1d0e5a7b3c9f2e8d4a6b0c1e7f3d9a5b2c8e4f0a6d1b7c3e9f5a2d8b4c0e6f1a";
+    mount_fixture(
+        &server,
+        "sui_getObject",
+        json!([coin_id]),
+        include_str!("fixtures/sui_getObject_coin.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.get_entitlement(coin_id).await;
+
+    assert!(matches!(result, Err(BlockchainError::UnexpectedObjectType(_))));
+}
+
+#[tokio::test]
+async fn test_get_entitlement_rejects_malformed_id_without_rpc() {
+    let server = MockServer::start().await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.get_entitlement("test-entitlement-1").await;
+
+    assert!(matches!(result, Err(BlockchainError::EntitlementNotFound)));
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_get_entitlements_uses_single_multi_get() {
+    let server = MockServer::start().await;
+    mount_fixture(
+        &server,
+        "sui_multiGetObjects",
+        json!([[ENTITLEMENT_ID, "0x00000000000000000000000000000000000000000000000000000000000000aa"]]),
+        include_str!("fixtures/sui_multiGetObjects.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let ids = vec![
+        ENTITLEMENT_ID.to_string(),
+        "not-an-object-id".to_string(),
+        MISSING_ID.to_string(),
+    ];
+    let results = client.get_entitlements(&ids).await.unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().quota_used, 0);
+    assert!(matches!(results[1], Err(BlockchainError::EntitlementNotFound)));
+    assert!(matches!(results[2], Err(BlockchainError::EntitlementNotFound)));
+}
+
+#[tokio::test]
+async fn test_rpc_error_is_surfaced() {
+    let server = MockServer::start().await;
+    mount_fixture(
+        &server,
+        "sui_getObject",
+        json!([ENTITLEMENT_ID]),
+        include_str!("fixtures/rpc_error_invalid_params.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.get_entitlement(ENTITLEMENT_ID).await;
+
+    assert!(matches!(result, Err(BlockchainError::RpcError { code: -32602, .. })));
+}
+
+#[tokio::test]
+async fn test_validate_entitlement_signature_requires_buyer_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [ENTITLEMENT_ID] })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/sui_getObject_entitlement.json"),
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let message = "inframint:access";
+
+    let buyer = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+    assert_eq!(buyer.address(), BUYER);
+    let signature = buyer.sign_personal_message(message.as_bytes()).to_base64();
+    assert!(client.validate_entitlement_signature(ENTITLEMENT_ID, &signature, message).await.unwrap());
+    assert!(!client.validate_entitlement_signature(ENTITLEMENT_ID, &signature, "inframint:other").await.unwrap());
+
+    let stranger = SuiKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &[0x22; 32]).unwrap();
+    let signature = stranger.sign_personal_message(message.as_bytes()).to_base64();
+    assert!(!client.validate_entitlement_signature(ENTITLEMENT_ID, &signature, message).await.unwrap());
+
+    let result = client.validate_entitlement_signature(ENTITLEMENT_ID, "test-signature", message).await;
+    assert!(matches!(result, Err(BlockchainError::SignatureError(_))));
+}
+
+#[tokio::test]
+async fn test_consume_entitlement_submits_signed_transaction() {
+    let server = MockServer::start().await;
+    mount_consume_fixtures(&server, include_str!("fixtures/sui_executeTransactionBlock_success.json")).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID)
+        .unwrap()
+        .with_signer(validator_signer("consume"));
+    let digest = client.consume_entitlement(ENTITLEMENT_ID, 25).await.unwrap();
+    assert_eq!(digest, "3Zx5vQyB7nCk9tW2hM4pL6rF8sD1aG3jK5mN7qR9uT2w");
+
+    let requests = server.received_requests().await.unwrap();
+    let execute: serde_json::Value = requests.iter()
+        .map(|r| r.body_json::<serde_json::Value>().unwrap())
+        .find(|body| body["method"] == "sui_executeTransactionBlock")
+        .unwrap();
+    let tx_bytes = BASE64.decode(execute["params"][0].as_str().unwrap()).unwrap();
+    let signature = SuiSignature::from_base64(execute["params"][1][0].as_str().unwrap()).unwrap();
+
+    assert_eq!(signature.signer_address(), VALIDATOR_ADDRESS);
+    assert!(signature.verify_digest(&transaction_digest(&tx_bytes)).unwrap());
+
+    // V1, programmable transaction, four inputs: cap, entitlement, amount, clock.
+    assert_eq!(&tx_bytes[..3], &[0x00, 0x00, 0x04]);
+    let contains = |needle: &[u8]| tx_bytes.windows(needle.len()).any(|w| w == needle);
+    assert!(contains(&hex::decode(&PACKAGE_ID[2..]).unwrap()));
+    assert!(contains(&hex::decode(&VALIDATOR_CAP_ID[2..]).unwrap()));
+    assert!(contains(&hex::decode(GAS_COIN_ID).unwrap()));
+    assert!(contains(b"consume_entitlement"));
+    assert!(contains(&[0x00, 0x08, 25, 0, 0, 0, 0, 0, 0, 0]));
+}
+
+#[tokio::test]
+async fn test_consume_entitlement_surfaces_move_abort() {
+    let server = MockServer::start().await;
+    mount_consume_fixtures(&server, include_str!("fixtures/sui_executeTransactionBlock_quota_exceeded.json")).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID)
+        .unwrap()
+        .with_signer(validator_signer("abort"));
+    let err = client.consume_entitlement(ENTITLEMENT_ID, 2000).await.unwrap_err();
+
+    assert!(matches!(&err, BlockchainError::MoveAbort { module, code: 6 } if module == "entitlements"));
+    assert!(matches!(ValidatorError::from(err), ValidatorError::QuotaExceeded));
+}
+
+#[tokio::test]
+async fn test_consume_entitlement_requires_signer() {
+    let server = MockServer::start().await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.consume_entitlement(ENTITLEMENT_ID, 1).await;
+
+    assert!(matches!(result, Err(BlockchainError::SignerNotConfigured)));
+}
+
+#[tokio::test]
+async fn test_get_tier_info_inspects_registry() {
+    let server = MockServer::start().await;
+    mount_fixture(&server, "sui_getObject", json!([REGISTRY_ID]), include_str!("fixtures/sui_getObject_registry.json")).await;
+    mount_fixture(
+        &server,
+        "sui_devInspectTransactionBlock",
+        json!([]),
+        include_str!("fixtures/sui_devInspectTransactionBlock_tier.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID)
+        .unwrap()
+        .with_registry(REGISTRY_ID);
+    let tier = client.get_tier_info(SERVICE_ID.as_bytes(), 1).await.unwrap();
+
+    assert_eq!(tier, PricingTier {
+        price_sui: 1_000_000_000,
+        quota_requests: 1000,
+        validity_period_ms: 2_592_000_000,
+        rate_limit_per_second: 5,
+        active: true,
+    });
+
+    let requests = server.received_requests().await.unwrap();
+    let inspect: serde_json::Value = requests.iter()
+        .map(|r| r.body_json::<serde_json::Value>().unwrap())
+        .find(|body| body["method"] == "sui_devInspectTransactionBlock")
+        .unwrap();
+    let tx_bytes = BASE64.decode(inspect["params"][1].as_str().unwrap()).unwrap();
+
+    // Programmable transaction kind, three inputs: registry, service ID, tier ID.
+    assert_eq!(&tx_bytes[..2], &[0x00, 0x03]);
+    let contains = |needle: &[u8]| tx_bytes.windows(needle.len()).any(|w| w == needle);
+    let mut registry = vec![0x01, 0x01];
+    registry.extend(hex::decode(&REGISTRY_ID[2..]).unwrap());
+    registry.extend([3, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(contains(&registry));
+    assert!(contains(&[&[0x00, 37, 36][..], SERVICE_ID.as_bytes()].concat()));
+    assert!(contains(&[0x00, 0x08, 1, 0, 0, 0, 0, 0, 0, 0]));
+    assert!(contains(b"get_tier_info"));
+}
+
+#[tokio::test]
+async fn test_get_tier_info_surfaces_missing_tier() {
+    let server = MockServer::start().await;
+    mount_fixture(&server, "sui_getObject", json!([REGISTRY_ID]), include_str!("fixtures/sui_getObject_registry.json")).await;
+    mount_fixture(
+        &server,
+        "sui_devInspectTransactionBlock",
+        json!([]),
+        include_str!("fixtures/sui_devInspectTransactionBlock_tier_missing.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID)
+        .unwrap()
+        .with_registry(REGISTRY_ID);
+    let result = client.get_tier_info(SERVICE_ID.as_bytes(), 9).await;
+
+    assert!(matches!(result, Err(BlockchainError::MoveAbort { module, code: 1 }) if module == "dynamic_field"));
+}
+
+#[tokio::test]
+async fn test_get_tier_info_requires_registry() {
+    let server = MockServer::start().await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let result = client.get_tier_info(SERVICE_ID.as_bytes(), 1).await;
+
+    assert!(matches!(result, Err(BlockchainError::RegistryNotConfigured)));
+}
+
+#[tokio::test]
+async fn test_query_entitlement_events_decodes_page() {
+    let server = MockServer::start().await;
+    let cursor = EventId {
+        tx_digest: "7nDoNZsMxaLm3dAqNnsRW7Eu1ryvkoY5uhsf6fFnhKmq".to_string(),
+        event_seq: "1".to_string(),
+    };
+    mount_fixture(
+        &server,
+        "suix_queryEvents",
+        json!([
+            { "MoveEventModule": { "package": PACKAGE_ID, "module": "entitlements" } },
+            { "txDigest": cursor.tx_digest, "eventSeq": "1" },
+            50,
+            false,
+        ]),
+        include_str!("fixtures/suix_queryEvents_entitlements.json"),
+    ).await;
+
+    let client = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID).unwrap();
+    let page = client.query_entitlement_events(Some(&cursor), 50).await.unwrap();
+
+    // `RevenueWithdrawn` is skipped
+    assert_eq!(page.events, vec![
+        EntitlementEvent::Purchased {
+            entitlement_id: format!("{}01", &ENTITLEMENT_ID[..64]),
+            buyer: BUYER.to_string(),
+            tier_id: 1,
+            amount_paid: 1_000_000_000,
+        },
+        EntitlementEvent::Consumed {
+            entitlement_id: format!("{}02", &ENTITLEMENT_ID[..64]),
+            amount: 25,
+            remaining: 975,
+        },
+        EntitlementEvent::Deactivated {
+            entitlement_id: format!("{}03", &ENTITLEMENT_ID[..64]),
+            reason: "Refunded".to_string(),
+        },
+    ]);
+    assert_eq!(page.next_cursor.unwrap().tx_digest, "9hG4fD7sA2qW5eR8tY3uJ6kL9zX4cV7bN2mP5wQ8eTr");
+    assert!(!page.has_next_page);
+}
+
+#[test]
+fn test_service_id_bytes_reverses_decoding() {
+    assert_eq!(service_id_bytes(SERVICE_ID), SERVICE_ID.as_bytes());
+    assert_eq!(service_id_bytes("0xff00"), vec![0xff, 0x00]);
+    // Valid UTF-8 that merely looks like hex is kept as text
+    assert_eq!(service_id_bytes("0x4142"), b"0x4142".to_vec());
+}
+
+#[test]
+fn test_parse_move_abort() {
+    let error = "MoveAbort(MoveLocation { module: ModuleId { address: 9c2a, name: Identifier(\"entitlements\") }, \
+                 function: 6, instruction: 12, function_name: Some(\"consume_entitlement\") }, 5) in command 0";
+    assert_eq!(parse_move_abort(error), Some(("entitlements".to_string(), 5)));
+    assert_eq!(parse_move_abort("InsufficientGas"), None);
+}
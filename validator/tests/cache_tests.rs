@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use validator::blockchain::Entitlement;
+use validator::cache::{CacheStats, CachedEntitlement, EntitlementCache, QuotaDebit};
+use validator::clock::UnixMillis;
+use validator::error::ValidatorError;
+
+const REDIS_URL: &str = "redis://localhost:6379";
+
+/// Each test debits its own entitlement so they can share one Redis.
+fn entitlement(test: u8, quota_requests: u64, quota_used: u64) -> Entitlement {
+    Entitlement {
+        id: format!("0x{}{:02x}", "c4".repeat(31), test),
+        service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+        buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+        tier_id: 1,
+        quota_requests,
+        quota_used,
+        purchased_at: UnixMillis(1729166400000),
+        expires_at: UnixMillis(4102444800000),
+        active: true,
+    }
+}
+
+async fn redis_connection() -> redis::aio::MultiplexedConnection {
+    redis::Client::open(REDIS_URL).unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap()
+}
+
+async fn reset_usage(entitlement_id: &str) {
+    redis::pipe()
+        .del(format!("ent:{}", entitlement_id))
+        .del(format!("meter:used:{}", entitlement_id))
+        .del(format!("meter:pending:{}", entitlement_id))
+        .srem("meter:dirty", entitlement_id)
+        .query_async::<_, ()>(&mut redis_connection().await)
+        .await
+        .unwrap();
+}
+
+async fn counters(entitlement_id: &str) -> (u64, u64) {
+    redis::pipe()
+        .get(format!("meter:used:{}", entitlement_id))
+        .get(format!("meter:pending:{}", entitlement_id))
+        .query_async(&mut redis_connection().await)
+        .await
+        .unwrap()
+}
+
+/// Debits `amount` from `tasks` concurrent tasks spread over `caches`.
+async fn hammer(caches: &[EntitlementCache], entitlement: &Entitlement, tasks: usize, amount: u64) -> Vec<QuotaDebit> {
+    let entitlement = Arc::new(entitlement.clone());
+    let handles: Vec<_> = (0..tasks)
+        .map(|i| {
+            let cache = caches[i % caches.len()].clone();
+            let entitlement = entitlement.clone();
+            tokio::spawn(async move { cache.debit_quota(&entitlement, amount, entitlement.expires_at).await.unwrap() })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(tasks);
+    for handle in handles {
+        results.push(handle.await.unwrap());
+    }
+    results
+}
+
+#[tokio::test]
+async fn test_debit_quota_seeds_from_on_chain_usage() {
+    let entitlement = entitlement(1, 1000, 990);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+
+    assert_eq!(cache.debit_quota(&entitlement, 20, entitlement.expires_at).await.unwrap(), QuotaDebit::Exceeded { remaining: 10 });
+    assert_eq!(cache.debit_quota(&entitlement, 10, entitlement.expires_at).await.unwrap(), QuotaDebit::Debited { remaining: 0, pending: 10 });
+    assert_eq!(cache.debit_quota(&entitlement, 1, entitlement.expires_at).await.unwrap(), QuotaDebit::Exceeded { remaining: 0 });
+
+    assert_eq!(counters(&entitlement.id).await, (1000, 10));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_debits_never_overdraw() {
+    let entitlement = entitlement(2, 1000, 0);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+
+    let results = hammer(&[cache], &entitlement, 64, 20).await;
+
+    let remaining: Vec<u64> = results.iter()
+        .filter_map(|result| match result {
+            QuotaDebit::Debited { remaining, .. } => Some(*remaining),
+            QuotaDebit::Exceeded { .. } => None,
+        })
+        .collect();
+    assert_eq!(remaining.len(), 50);
+
+    // Every successful debit observed a distinct balance
+    let distinct: HashSet<u64> = remaining.iter().copied().collect();
+    assert_eq!(distinct.len(), 50);
+    assert!(distinct.contains(&0));
+
+    assert_eq!(counters(&entitlement.id).await, (1000, 1000));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_debits_across_replicas() {
+    let entitlement = entitlement(3, 1000, 0);
+    reset_usage(&entitlement.id).await;
+
+    // Separate clients stand in for separate validator processes
+    let mut replicas = Vec::new();
+    for _ in 0..4 {
+        replicas.push(EntitlementCache::new(REDIS_URL, 300).await.unwrap());
+    }
+
+    let results = hammer(&replicas, &entitlement, 400, 7).await;
+
+    let debited = results.iter()
+        .filter(|result| matches!(result, QuotaDebit::Debited { .. }))
+        .count();
+    assert_eq!(debited, 142);
+    assert!(results.iter().all(|result| match result {
+        QuotaDebit::Exceeded { remaining } => *remaining < 7,
+        QuotaDebit::Debited { .. } => true,
+    }));
+
+    assert_eq!(counters(&entitlement.id).await, (994, 994));
+}
+
+#[tokio::test]
+async fn test_get_prefers_local_layer() {
+    let entitlement = entitlement(4, 1000, 0);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+    let replica = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+
+    assert!(cache.get(&entitlement.id).await.unwrap().is_none());
+    cache.set(entitlement.id.clone(), CachedEntitlement::from(entitlement.clone())).await.unwrap();
+    assert!(cache.get(&entitlement.id).await.unwrap().is_some());
+    assert_eq!(cache.stats(), CacheStats { local_hits: 1, redis_hits: 0, misses: 1, loads: 0 });
+
+    // Another replica finds it in Redis, then keeps its own copy
+    assert!(replica.get(&entitlement.id).await.unwrap().is_some());
+    assert!(replica.get(&entitlement.id).await.unwrap().is_some());
+    assert_eq!(replica.stats(), CacheStats { local_hits: 1, redis_hits: 1, misses: 0, loads: 0 });
+
+    cache.invalidate(&entitlement.id).await.unwrap();
+    assert!(cache.get(&entitlement.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_local_entries_expire() {
+    let entitlement = entitlement(5, 1000, 0);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap()
+        .with_local_cache(16, Duration::from_millis(50));
+    let replica = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+
+    cache.set(entitlement.id.clone(), CachedEntitlement::from(entitlement.clone())).await.unwrap();
+    replica.invalidate(&entitlement.id).await.unwrap();
+
+    // Stale until the local TTL runs out
+    assert!(cache.get(&entitlement.id).await.unwrap().is_some());
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(cache.get(&entitlement.id).await.unwrap().is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_misses_load_once() {
+    let entitlement = entitlement(6, 1000, 0);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..32)
+        .map(|_| {
+            let cache = cache.clone();
+            let calls = calls.clone();
+            let entitlement = entitlement.clone();
+            tokio::spawn(async move {
+                let id = entitlement.id.clone();
+                cache.get_or_load(&id, || async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(Some(CachedEntitlement::from(entitlement)))
+                }).await.unwrap()
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert!(handle.await.unwrap().is_some());
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(cache.stats().loads, 1);
+
+    // Later lookups are served from the cache
+    let loaded = cache.get_or_load(&entitlement.id, || async { panic!("loaded twice") }).await.unwrap();
+    assert_eq!(loaded.unwrap().entitlement.id, entitlement.id);
+}
+
+#[tokio::test]
+async fn test_failed_load_is_not_cached() {
+    let entitlement = entitlement(7, 1000, 0);
+    reset_usage(&entitlement.id).await;
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+
+    let result = cache.get_or_load(&entitlement.id, || async {
+        Err(ValidatorError::BlockchainError("fullnode unavailable".to_string()))
+    }).await;
+    assert!(matches!(result, Err(ValidatorError::BlockchainError(_))));
+
+    let loaded = cache.get_or_load(&entitlement.id, || async {
+        Ok(Some(CachedEntitlement::from(entitlement.clone())))
+    }).await.unwrap();
+    assert!(loaded.is_some());
+    assert_eq!(cache.stats().loads, 2);
+}
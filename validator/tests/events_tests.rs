@@ -0,0 +1,135 @@
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use validator::blockchain::{Entitlement, SuiBlockchainClient};
+use validator::cache::{CachedEntitlement, EntitlementCache};
+use validator::clock::UnixMillis;
+use validator::events::EventSubscriber;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REDIS_URL: &str = "redis://localhost:6379";
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const LATEST_DIGEST: &str = "9hG4fD7sA2qW5eR8tY3uJ6kL9zX4cV7bN2mP5wQ8eTr";
+
+/// Each test follows its own package so the cursors do not collide.
+fn package_id(test: u8) -> String {
+    format!("{}{:02x}", &PACKAGE_ID[..64], test)
+}
+
+/// The entitlement `n` of the events fixture.
+fn entitlement_id(n: u8) -> String {
+    format!("{}{:02x}", &ENTITLEMENT_ID[..64], n)
+}
+
+fn entitlement(n: u8) -> CachedEntitlement {
+    CachedEntitlement::from(Entitlement {
+        id: entitlement_id(n),
+        service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+        buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+        tier_id: 1,
+        quota_requests: 1000,
+        quota_used: 0,
+        purchased_at: UnixMillis(1729166400000),
+        expires_at: UnixMillis(4102444800000),
+        active: true,
+    })
+}
+
+async fn reset(package_id: &str) {
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    let mut pipe = redis::pipe();
+    pipe.del(format!("events:cursor:{}", package_id));
+    for n in 1..=3 {
+        pipe.del(format!("ent:{}", entitlement_id(n)));
+    }
+    pipe.query_async::<_, ()>(&mut conn).await.unwrap();
+}
+
+fn filter(package_id: &str) -> Value {
+    json!({ "MoveEventModule": { "package": package_id, "module": "entitlements" } })
+}
+
+async fn mount_events(server: &MockServer, params: Value, body: String) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "suix_queryEvents", "params": params })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+        .mount(server)
+        .await;
+}
+
+async fn subscriber(server: &MockServer, package_id: &str) -> (EntitlementCache, EventSubscriber) {
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+    let blockchain = SuiBlockchainClient::new(&server.uri(), package_id).unwrap();
+    let subscriber = EventSubscriber::new(Arc::new(blockchain), Arc::new(RwLock::new(cache.clone())), 2);
+    (cache, subscriber)
+}
+
+#[tokio::test]
+async fn test_first_poll_starts_at_latest_event() {
+    let package_id = package_id(1);
+    reset(&package_id).await;
+    let server = MockServer::start().await;
+    mount_events(
+        &server,
+        json!([filter(&package_id), null, 1, true]),
+        include_str!("fixtures/suix_queryEvents_latest.json").replace(PACKAGE_ID, &package_id),
+    ).await;
+    let (_, subscriber) = subscriber(&server, &package_id).await;
+
+    // History is not replayed
+    assert_eq!(subscriber.poll().await.unwrap(), 0);
+
+    // The next poll continues after the latest event
+    mount_events(
+        &server,
+        json!([filter(&package_id), { "txDigest": LATEST_DIGEST, "eventSeq": "0" }, 50, false]),
+        json!({ "jsonrpc": "2.0", "id": 1, "result": { "data": [], "nextCursor": null, "hasNextPage": false } }).to_string(),
+    ).await;
+    assert_eq!(subscriber.poll().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_poll_applies_events_to_cache() {
+    let package_id = package_id(2);
+    reset(&package_id).await;
+    let server = MockServer::start().await;
+    let (cache, subscriber) = subscriber(&server, &package_id).await;
+
+    // Resume from a stored cursor
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::cmd("SET")
+        .arg(format!("events:cursor:{}", package_id))
+        .arg(json!({ "txDigest": "7nDoNZsMxaLm3dAqNnsRW7Eu1ryvkoY5uhsf6fFnhKmq", "eventSeq": "1" }).to_string())
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+
+    for n in 2..=3 {
+        cache.set(entitlement_id(n), entitlement(n)).await.unwrap();
+    }
+
+    mount_events(
+        &server,
+        json!([filter(&package_id), { "txDigest": "7nDoNZsMxaLm3dAqNnsRW7Eu1ryvkoY5uhsf6fFnhKmq" }, 50, false]),
+        include_str!("fixtures/suix_queryEvents_entitlements.json").replace(PACKAGE_ID, &package_id),
+    ).await;
+    assert_eq!(subscriber.poll().await.unwrap(), 3);
+
+    // Purchases are not cached ahead of time
+    assert!(cache.get(&entitlement_id(1)).await.unwrap().is_none());
+    // 25 consumed, 975 remaining
+    assert_eq!(cache.get(&entitlement_id(2)).await.unwrap().unwrap().entitlement.quota_used, 25);
+    // Deactivated entitlements are dropped
+    assert!(cache.get(&entitlement_id(3)).await.unwrap().is_none());
+
+    let cursor: String = redis::cmd("GET")
+        .arg(format!("events:cursor:{}", package_id))
+        .query_async(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&cursor).unwrap()["txDigest"], LATEST_DIGEST);
+}
@@ -0,0 +1,547 @@
+use axum::http::Method;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures_util::{SinkExt, StreamExt};
+use redis::AsyncCommands;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::RwLock;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use validator::access_token::AccessTokenIssuer;
+use validator::blockchain::Entitlement;
+use validator::cache::CachedEntitlement;
+use validator::catalog::CatalogClient;
+use validator::clock::{Clock, SystemClock, UnixMillis};
+use validator::config::{CostRule, GatewayConfig};
+use validator::gateway::{CostTable, Gateway, QUOTA_REMAINING_HEADER};
+use validator::jsonrpc::JsonRpcCostTable;
+use validator::signature::{SignatureScheme, SuiKeyPair};
+use validator::{RateLimitStore, ValidatorConfig, ValidatorServiceImpl};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const SERVICE_ID: &str = "11111111-1111-1111-1111-111111111111";
+const REDIS_URL: &str = "redis://localhost:6379";
+const TOKEN_SECRET: [u8; 32] = [0x66; 32];
+
+/// Drops cached and metered state for `entitlement_id`.
+async fn reset_usage(entitlement_id: &str) {
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::pipe()
+        .del(format!("ent:{}", entitlement_id))
+        .del(format!("meter:used:{}", entitlement_id))
+        .del(format!("meter:pending:{}", entitlement_id))
+        .del(format!("meter:inflight:{}", entitlement_id))
+        .del(format!("ratelimit:{}", entitlement_id))
+        .srem("meter:dirty", entitlement_id)
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+}
+
+/// Serves a copy of the recorded entitlement under `entitlement_id`.
+async fn mock_fullnode(entitlement_id: &str) -> MockServer {
+    let fullnode = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [entitlement_id] })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, entitlement_id),
+            "application/json",
+        ))
+        .mount(&fullnode)
+        .await;
+    fullnode
+}
+
+fn validator_config(fullnode: &MockServer, rate_limit_max: u64) -> ValidatorConfig {
+    let mut token_key = vec![0x00];
+    token_key.extend_from_slice(&TOKEN_SECRET);
+
+    ValidatorConfig {
+        redis_url: REDIS_URL.to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
+        cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
+        rate_limit_window: 60,
+        rate_limit_max,
+        rate_limit_store: RateLimitStore::Memory,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
+        grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: Some(BASE64.encode(token_key)),
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
+    }
+}
+
+fn gateway_config(upstream_url: String) -> GatewayConfig {
+    GatewayConfig {
+        upstream_url,
+        listen_port: 0,
+        credential_header: "authorization".to_string(),
+        default_cost: 1,
+        costs: vec![
+            CostRule { method: Some("POST".to_string()), path: "/v1/expensive".to_string(), cost: 10 },
+            CostRule { method: None, path: "/v1/huge".to_string(), cost: 2000 },
+        ],
+        catalog_url: None,
+        service_id: SERVICE_ID.to_string(),
+        catalog_refresh_interval: 60,
+        max_jsonrpc_body_bytes: 1024,
+        ws_message_cost: 1,
+        ws_connection_cost: 10,
+        ws_billing_period: 1,
+    }
+}
+
+/// An access token for `entitlement_id` as the validator would issue it.
+fn access_token(entitlement_id: &str) -> String {
+    access_token_for(entitlement_id, SERVICE_ID)
+}
+
+/// Like `access_token`, for an entitlement of `service_id`.
+fn access_token_for(entitlement_id: &str, service_id: &str) -> String {
+    let keypair = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &TOKEN_SECRET).unwrap();
+    let issuer = AccessTokenIssuer::new(keypair, "inframint-validator").unwrap();
+    let entitlement = CachedEntitlement {
+        entitlement: Entitlement {
+            id: entitlement_id.to_string(),
+            service_id: service_id.to_string(),
+            buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+            tier_id: 1,
+            quota_requests: 1000,
+            quota_used: 0,
+            purchased_at: UnixMillis(1_729_166_400_000),
+            expires_at: UnixMillis(4_102_444_800_000),
+            active: true,
+        },
+        rate_limit_per_second: 0,
+    };
+
+    issuer.issue(&entitlement, SystemClock.now(), Duration::from_secs(900)).0
+}
+
+/// Starts a gateway in front of `upstream_url` and returns its base URL.
+async fn start_gateway(entitlement_id: &str, upstream_url: String, rate_limit_max: u64) -> (String, MockServer) {
+    start_gateway_with(entitlement_id, upstream_url, rate_limit_max, None).await
+}
+
+/// Like `start_gateway`, metering POST requests per JSON-RPC method if
+/// `jsonrpc_costs` is set.
+async fn start_gateway_with(
+    entitlement_id: &str,
+    upstream_url: String,
+    rate_limit_max: u64,
+    jsonrpc_costs: Option<JsonRpcCostTable>,
+) -> (String, MockServer) {
+    let fullnode = mock_fullnode(entitlement_id).await;
+    reset_usage(entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(validator_config(&fullnode, rate_limit_max)).await.unwrap();
+    let mut gateway = Gateway::new(service, &gateway_config(upstream_url)).unwrap();
+    if let Some(costs) = jsonrpc_costs {
+        gateway = gateway.with_jsonrpc_costs(Arc::new(RwLock::new(costs)));
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(gateway.router().into_make_service()));
+
+    (format!("http://{}", addr), fullnode)
+}
+
+fn quota_remaining(response: &reqwest::Response) -> u64 {
+    response.headers()[QUOTA_REMAINING_HEADER].to_str().unwrap().parse().unwrap()
+}
+
+/// Starts a WebSocket server echoing data messages and returns its base URL.
+async fn echo_upstream() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(message)) = socket.next().await {
+                    if (message.is_text() || message.is_binary()) && socket.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Opens a WebSocket through the gateway, returning the quota remaining
+/// after the handshake.
+async fn connect_websocket(gateway: &str, entitlement_id: &str) -> (ClientSocket, u64) {
+    let mut request = format!("{}/v1/subscribe", gateway.replace("http://", "ws://"))
+        .into_client_request()
+        .unwrap();
+    request.headers_mut().insert(
+        "authorization",
+        format!("Bearer {}", access_token(entitlement_id)).parse().unwrap(),
+    );
+
+    let (socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+    let remaining = response.headers()[QUOTA_REMAINING_HEADER].to_str().unwrap().parse().unwrap();
+    (socket, remaining)
+}
+
+#[tokio::test]
+async fn test_forwards_and_meters_requests() {
+    let entitlement_id = format!("{}a1", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("pong"))
+        .mount(&upstream)
+        .await;
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, format!("{}/base/", upstream.uri()), 1000).await;
+
+    let http = reqwest::Client::new();
+    let response = http.post(format!("{}/v1/rpc?chain=sui", gateway))
+        .bearer_auth(access_token(&entitlement_id))
+        .body("ping")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(quota_remaining(&response), 999);
+    assert_eq!(response.text().await.unwrap(), "pong");
+
+    let response = http.post(format!("{}/v1/expensive", gateway))
+        .bearer_auth(access_token(&entitlement_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(quota_remaining(&response), 989);
+
+    // The upstream sees the request under its own path, without the token
+    let received = upstream.received_requests().await.unwrap();
+    assert_eq!(received[0].url.path(), "/base/v1/rpc");
+    assert_eq!(received[0].url.query(), Some("chain=sui"));
+    assert_eq!(received[0].body, b"ping");
+    assert!(received[0].headers.iter().all(|(name, _)| name.as_str() != "authorization"));
+}
+
+#[tokio::test]
+async fn test_rejects_requests_without_valid_token() {
+    let entitlement_id = format!("{}a2", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, upstream.uri(), 1000).await;
+
+    let http = reqwest::Client::new();
+    let response = http.get(format!("{}/v1/rpc", gateway)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let response = http.get(format!("{}/v1/rpc", gateway))
+        .bearer_auth("not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rejects_tokens_for_another_service() {
+    let entitlement_id = format!("{}ab", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, upstream.uri(), 1000).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/v1/rpc", gateway))
+        .bearer_auth(access_token_for(&entitlement_id, "22222222-2222-2222-2222-222222222222"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "WRONG_SERVICE");
+
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+    // Nothing was billed to the token's entitlement
+    let mut conn = redis::Client::open(REDIS_URL).unwrap().get_multiplexed_async_connection().await.unwrap();
+    let used: Option<u64> = conn.get(format!("meter:used:{}", entitlement_id)).await.unwrap();
+    assert_eq!(used, None);
+}
+
+#[tokio::test]
+async fn test_rejects_requests_past_the_quota() {
+    let entitlement_id = format!("{}a3", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, upstream.uri(), 1000).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/v1/huge", gateway))
+        .bearer_auth(access_token(&entitlement_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 402);
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rejects_requests_past_the_rate_limit() {
+    let entitlement_id = format!("{}a4", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/rpc"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&upstream)
+        .await;
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, upstream.uri(), 2).await;
+
+    let http = reqwest::Client::new();
+    let mut statuses = Vec::new();
+    for _ in 0..3 {
+        let response = http.get(format!("{}/v1/rpc", gateway))
+            .bearer_auth(access_token(&entitlement_id))
+            .send()
+            .await
+            .unwrap();
+        statuses.push(response.status().as_u16());
+    }
+    assert_eq!(statuses, [200, 200, 429]);
+}
+
+#[tokio::test]
+async fn test_unreachable_upstream_is_a_bad_gateway() {
+    let entitlement_id = format!("{}a5", &ENTITLEMENT_ID[..64]);
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, format!("http://{}", closed), 1000).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/v1/rpc", gateway))
+        .bearer_auth(access_token(&entitlement_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+}
+
+fn jsonrpc_costs() -> JsonRpcCostTable {
+    JsonRpcCostTable {
+        default_cost: 1,
+        methods: BTreeMap::from([
+            ("sui_getObject".to_string(), 5),
+            ("suix_queryEvents".to_string(), 600),
+        ]),
+    }
+}
+
+#[tokio::test]
+async fn test_meters_jsonrpc_batches_per_method() {
+    let entitlement_id = format!("{}a6", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+        .mount(&upstream)
+        .await;
+    let (gateway, _fullnode) = start_gateway_with(&entitlement_id, upstream.uri(), 1000, Some(jsonrpc_costs())).await;
+
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": 1, "method": "sui_getObject", "params": ["0x1"] },
+        { "jsonrpc": "2.0", "id": 2, "method": "sui_getObject", "params": ["0x2"] },
+        { "jsonrpc": "2.0", "id": 3, "method": "sui_getChainIdentifier" },
+    ]);
+    let http = reqwest::Client::new();
+    let response = http.post(gateway.clone())
+        .bearer_auth(access_token(&entitlement_id))
+        .json(&batch)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(quota_remaining(&response), 1000 - 11);
+
+    // The buffered body is forwarded unchanged
+    let received = upstream.received_requests().await.unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&received[0].body).unwrap(), batch);
+
+    // Requests other than POST keep their path-based cost
+    let response = http.get(format!("{}/health", gateway))
+        .bearer_auth(access_token(&entitlement_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(quota_remaining(&response), 1000 - 12);
+
+    let response = http.post(gateway)
+        .bearer_auth(access_token(&entitlement_id))
+        .body("not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_rejects_jsonrpc_batches_past_the_quota() {
+    let entitlement_id = format!("{}a7", &ENTITLEMENT_ID[..64]);
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&upstream)
+        .await;
+    let (gateway, _fullnode) = start_gateway_with(&entitlement_id, upstream.uri(), 1000, Some(jsonrpc_costs())).await;
+
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": 1, "method": "suix_queryEvents", "params": [] },
+        { "jsonrpc": "2.0", "id": 2, "method": "suix_queryEvents", "params": [] },
+    ]);
+    let http = reqwest::Client::new();
+    let response = http.post(gateway.clone())
+        .bearer_auth(access_token(&entitlement_id))
+        .json(&batch)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 402);
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+
+    // Nothing of the rejected batch was debited
+    let response = http.post(gateway.clone())
+        .bearer_auth(access_token(&entitlement_id))
+        .json(&batch[0])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(quota_remaining(&response), 400);
+
+    let response = http.post(gateway)
+        .bearer_auth(access_token(&entitlement_id))
+        .body(vec![b' '; 2048])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+}
+
+#[tokio::test]
+async fn test_reads_jsonrpc_costs_from_the_catalog() {
+    let backend = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/services/11111111-1111-1111-1111-111111111111"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "11111111-1111-1111-1111-111111111111",
+            "name": "Sui RPC",
+            "type_": "rpc",
+            "metadata": {
+                "jsonrpc_costs": { "default_cost": 2, "methods": { "sui_getObject": 5 } }
+            }
+        })))
+        .mount(&backend)
+        .await;
+
+    let catalog = CatalogClient::new(&format!("{}/", backend.uri()));
+    let listing = catalog.get_service("11111111-1111-1111-1111-111111111111").await.unwrap();
+    assert!(listing.is_jsonrpc());
+
+    let costs = listing.jsonrpc_costs().unwrap();
+    assert_eq!(costs.cost("sui_getObject"), 5);
+    assert_eq!(costs.cost("sui_getChainIdentifier"), 2);
+
+    assert!(catalog.get_service("22222222-2222-2222-2222-222222222222").await.is_err());
+}
+
+#[tokio::test]
+async fn test_meters_websocket_messages() {
+    let entitlement_id = format!("{}a8", &ENTITLEMENT_ID[..64]);
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, echo_upstream().await, 1000).await;
+
+    let (mut socket, remaining) = connect_websocket(&gateway, &entitlement_id).await;
+    assert_eq!(remaining, 999);
+
+    for text in ["first", "second"] {
+        socket.send(Message::Text(text.to_string())).await.unwrap();
+        assert_eq!(socket.next().await.unwrap().unwrap(), Message::Text(text.to_string()));
+    }
+    socket.close(None).await.unwrap();
+
+    // Both messages were charged in each direction
+    let (_, remaining) = connect_websocket(&gateway, &entitlement_id).await;
+    assert_eq!(remaining, 999 - 4 - 1);
+}
+
+#[tokio::test]
+async fn test_charges_open_websockets_per_period() {
+    let entitlement_id = format!("{}a9", &ENTITLEMENT_ID[..64]);
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, echo_upstream().await, 1000).await;
+
+    let (mut socket, _) = connect_websocket(&gateway, &entitlement_id).await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    socket.close(None).await.unwrap();
+
+    let (_, remaining) = connect_websocket(&gateway, &entitlement_id).await;
+    assert_eq!(remaining, 999 - 2 * 10 - 1);
+}
+
+#[tokio::test]
+async fn test_closes_websockets_past_the_quota() {
+    let entitlement_id = format!("{}aa", &ENTITLEMENT_ID[..64]);
+    let (gateway, _fullnode) = start_gateway(&entitlement_id, echo_upstream().await, 1000).await;
+
+    // Leave room for the handshake and a single message
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::cmd("SET")
+        .arg(format!("meter:used:{}", entitlement_id))
+        .arg(998)
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+
+    let (mut socket, remaining) = connect_websocket(&gateway, &entitlement_id).await;
+    assert_eq!(remaining, 1);
+
+    // The message is forwarded, but its echo can no longer be paid for
+    socket.send(Message::Text("ping".to_string())).await.unwrap();
+    match socket.next().await.unwrap().unwrap() {
+        Message::Close(Some(frame)) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, "Quota exceeded");
+        }
+        message => panic!("Expected a close frame, got {:?}", message),
+    }
+}
+
+#[test]
+fn test_cost_table_uses_first_matching_rule() {
+    let costs = CostTable::new(gateway_config(String::new()).costs, 1);
+
+    assert_eq!(costs.cost(&Method::POST, "/v1/expensive/call"), 10);
+    assert_eq!(costs.cost(&Method::GET, "/v1/expensive"), 1);
+    assert_eq!(costs.cost(&Method::GET, "/v1/huge"), 2000);
+    assert_eq!(costs.cost(&Method::PUT, "/v2/other"), 1);
+}
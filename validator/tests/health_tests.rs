@@ -0,0 +1,118 @@
+use serde_json::json;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Channel, Server};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use validator::health::DependencyCheck;
+use validator::{RateLimitStore, ValidatorConfig, ValidatorServiceImpl};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+
+/// A fullnode that answers `sui_getChainIdentifier` with `status`.
+async fn mock_fullnode(status: u16) -> MockServer {
+    let fullnode = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getChainIdentifier" })))
+        .respond_with(ResponseTemplate::new(status).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": "4c78adac",
+        })))
+        .mount(&fullnode)
+        .await;
+    fullnode
+}
+
+fn health_config(fullnode: &MockServer) -> ValidatorConfig {
+    ValidatorConfig {
+        redis_url: "redis://localhost:6379".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
+        cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
+        rate_limit_window: 60,
+        rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Memory,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
+        grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 1,
+        max_batch_size: 100,
+    }
+}
+
+#[tokio::test]
+async fn test_healthy_with_redis_and_fullnode_up() {
+    let fullnode = mock_fullnode(200).await;
+    let service = ValidatorServiceImpl::new(health_config(&fullnode)).await.unwrap();
+
+    let report = service.health_checker().check().await;
+    assert!(report.is_healthy());
+    assert_eq!(report.to_string(), "redis: ok\nchain: ok\n");
+}
+
+#[tokio::test]
+async fn test_unhealthy_when_the_fullnode_fails() {
+    let fullnode = mock_fullnode(503).await;
+    let service = ValidatorServiceImpl::new(health_config(&fullnode)).await.unwrap();
+
+    let report = service.health_checker().check().await;
+    assert!(!report.is_healthy());
+    assert_eq!(report.checks[0], DependencyCheck { name: "redis", error: None });
+    assert_eq!(report.checks[1].name, "chain");
+    assert!(report.checks[1].error.is_some());
+}
+
+#[tokio::test]
+async fn test_health_service_follows_the_checks() {
+    let fullnode = mock_fullnode(200).await;
+    let service = ValidatorServiceImpl::new(health_config(&fullnode)).await.unwrap();
+
+    let (reporter, health_service) = tonic_health::server::health_reporter();
+    tokio::spawn(service.health_checker().run(reporter));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(health_service)
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+    let mut client = HealthClient::new(channel);
+
+    // The first check runs right away
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(serving_status(&mut client).await, ServingStatus::Serving);
+
+    // The next check sees the fullnode down
+    fullnode.reset().await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(serving_status(&mut client).await, ServingStatus::NotServing);
+}
+
+async fn serving_status(client: &mut HealthClient<Channel>) -> ServingStatus {
+    let request = HealthCheckRequest { service: "validator.ValidatorService".to_string() };
+    client.check(request).await.unwrap().into_inner().status()
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+use validator::jsonrpc::{methods, request_cost, JsonRpcCostTable, JsonRpcError};
+
+fn costs() -> JsonRpcCostTable {
+    JsonRpcCostTable {
+        default_cost: 1,
+        methods: BTreeMap::from([
+            ("sui_getObject".to_string(), 5),
+            ("suix_queryEvents".to_string(), 20),
+        ]),
+    }
+}
+
+#[test]
+fn test_single_call_costs_its_method() {
+    let body = br#"{"jsonrpc":"2.0","id":1,"method":"sui_getObject","params":["0x1"]}"#;
+
+    assert_eq!(methods(body).unwrap(), ["sui_getObject"]);
+    assert_eq!(request_cost(&costs(), body), Ok(5));
+}
+
+#[test]
+fn test_batch_costs_the_sum_of_its_calls() {
+    let body = br#"[
+        {"jsonrpc":"2.0","id":1,"method":"sui_getObject","params":["0x1"]},
+        {"jsonrpc":"2.0","id":2,"method":"suix_queryEvents","params":[]},
+        {"jsonrpc":"2.0","id":3,"method":"sui_getLatestCheckpointSequenceNumber"}
+    ]"#;
+
+    assert_eq!(request_cost(&costs(), body), Ok(5 + 20 + 1));
+}
+
+#[test]
+fn test_rejects_requests_that_cannot_be_costed() {
+    assert_eq!(request_cost(&costs(), b"[]"), Err(JsonRpcError::EmptyBatch));
+    assert_eq!(request_cost(&costs(), b"not json"), Err(JsonRpcError::Malformed));
+    assert_eq!(request_cost(&costs(), br#"{"jsonrpc":"2.0","id":1}"#), Err(JsonRpcError::Malformed));
+
+    let costs = JsonRpcCostTable { default_cost: u64::MAX, methods: BTreeMap::new() };
+    let body = br#"[{"method":"a"},{"method":"b"}]"#;
+    assert_eq!(request_cost(&costs, body), Err(JsonRpcError::CostOverflow));
+}
@@ -0,0 +1,273 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use validator::blockchain::{Entitlement, SuiBlockchainClient, ValidatorSigner};
+use validator::cache::{EntitlementCache, QuotaDebit};
+use validator::clock::{Clock, SystemClock, UnixMillis};
+use validator::error::ValidatorError;
+use validator::metering::{MeteringLedger, Settler};
+use validator::signature::SuiKeyPair;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REDIS_URL: &str = "redis://localhost:6379";
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const VALIDATOR_ADDRESS: &str = "0xe3cef37dac8ae923cd30422d8a9e9eae7d4d68bc1b287fc1567556c7abf92ff3";
+const VALIDATOR_CAP_ID: &str = "0x6f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+const SUCCESS_DIGEST: &str = "3Zx5vQyB7nCk9tW2hM4pL6rF8sD1aG3jK5mN7qR9uT2w";
+
+/// Each test meters its own entitlement so they can share one Redis.
+fn entitlement_id(test: u8) -> String {
+    format!("0x{}{:02x}", &ENTITLEMENT_ID[2..64], test)
+}
+
+/// Drops cached and metered state for `entitlement_id`.
+async fn reset_usage(entitlement_id: &str) {
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::pipe()
+        .del(format!("ent:{}", entitlement_id))
+        .del(format!("meter:used:{}", entitlement_id))
+        .del(format!("meter:pending:{}", entitlement_id))
+        .del(format!("meter:inflight:{}", entitlement_id))
+        .del(format!("meter:lock:{}", entitlement_id))
+        .srem("meter:dirty", entitlement_id)
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+}
+
+/// The recorded `Entitlement` object, moved to `entitlement_id` with the
+/// given on-chain usage.
+fn entitlement_object(entitlement_id: &str, quota_used: u64) -> Value {
+    let fixture = include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, entitlement_id);
+    let mut response: Value = serde_json::from_str(&fixture).unwrap();
+    response["result"]["data"]["content"]["fields"]["quota_used"] = json!(quota_used.to_string());
+    response["result"].take()
+}
+
+fn entitlement(entitlement_id: &str) -> Entitlement {
+    Entitlement {
+        id: entitlement_id.to_string(),
+        service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+        buyer: "0x0881c07520943bbf13989b92892093c1b50672156fa5f873c22892701cb2e207".to_string(),
+        tier_id: 1,
+        quota_requests: 1000,
+        quota_used: 0,
+        purchased_at: UnixMillis(1729166400000),
+        expires_at: UnixMillis(4102444800000),
+        active: true,
+    }
+}
+
+async fn mount_response(server: &MockServer, rpc_method: &str, params: Value, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": rpc_method, "params": params })))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+fn fixture(body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(body.to_string(), "application/json")
+}
+
+/// Serves the entitlement at `quota_used` and the inputs `consume_entitlement`
+/// needs, answering the execution with `execute`.
+async fn mount_fullnode(server: &MockServer, entitlement_id: &str, quota_used: u64, execute: ResponseTemplate) {
+    let object = entitlement_object(entitlement_id, quota_used);
+    mount_response(server, "sui_getObject", json!([entitlement_id]), rpc_result(object.clone())).await;
+    mount_response(server, "sui_multiGetObjects", json!([[entitlement_id]]), rpc_result(json!([object]))).await;
+    mount_response(
+        server,
+        "sui_multiGetObjects",
+        json!([[VALIDATOR_CAP_ID, entitlement_id]]),
+        fixture(&include_str!("fixtures/sui_multiGetObjects_consume_inputs.json").replace(ENTITLEMENT_ID, entitlement_id)),
+    ).await;
+    mount_response(server, "suix_getCoins", json!([VALIDATOR_ADDRESS]), fixture(include_str!("fixtures/suix_getCoins.json"))).await;
+    mount_response(server, "suix_getReferenceGasPrice", json!([]), fixture(include_str!("fixtures/suix_getReferenceGasPrice.json"))).await;
+    mount_response(server, "sui_executeTransactionBlock", json!([]), execute).await;
+}
+
+fn validator_signer() -> ValidatorSigner {
+    let mut entry = vec![0x00];
+    entry.extend_from_slice(&[0x44; 32]);
+
+    ValidatorSigner {
+        keypair: SuiKeyPair::from_keystore_entry(&BASE64.encode(entry)).unwrap(),
+        validator_cap_id: VALIDATOR_CAP_ID.to_string(),
+        gas_budget: 10_000_000,
+    }
+}
+
+async fn settler(server: &MockServer) -> (Arc<MeteringLedger>, Settler) {
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+    let ledger = Arc::new(MeteringLedger::new(&cache, 1000, Duration::ZERO));
+    let blockchain = SuiBlockchainClient::new(&server.uri(), PACKAGE_ID)
+        .unwrap()
+        .with_signer(validator_signer());
+
+    let settler = Settler::new(ledger.clone(), Arc::new(blockchain), Arc::new(RwLock::new(cache)), 30);
+    (ledger, settler)
+}
+
+/// `consume_entitlement` amounts submitted to the mock fullnode.
+async fn submitted_amounts(server: &MockServer) -> Vec<u64> {
+    server.received_requests().await.unwrap()
+        .iter()
+        .map(|r| r.body_json::<Value>().unwrap())
+        .filter(|body| body["method"] == "sui_executeTransactionBlock")
+        .map(|body| {
+            let tx_bytes = BASE64.decode(body["params"][0].as_str().unwrap()).unwrap();
+            // Third input: `Pure` tag, length 8, little-endian u64.
+            let pure = tx_bytes.windows(2).position(|w| w == [0x00, 0x08]).unwrap() + 2;
+            u64::from_le_bytes(tx_bytes[pure..pure + 8].try_into().unwrap())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_debit_tracks_usage_and_rejects_overdraft() {
+    let id = entitlement_id(1);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    let (ledger, _) = settler(&server).await;
+    let entitlement = entitlement(&id);
+
+    assert_eq!(ledger.debit(&entitlement, 600, SystemClock.now()).await.unwrap(), QuotaDebit::Debited { remaining: 400, pending: 600 });
+    assert_eq!(ledger.debit(&entitlement, 500, SystemClock.now()).await.unwrap(), QuotaDebit::Exceeded { remaining: 400 });
+    assert_eq!(ledger.debit(&entitlement, 400, SystemClock.now()).await.unwrap(), QuotaDebit::Debited { remaining: 0, pending: 1000 });
+
+    assert_eq!(ledger.quota_used(&id).await.unwrap(), Some(1000));
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 1000);
+    assert!(ledger.dirty().await.unwrap().contains(&id));
+}
+
+#[tokio::test]
+async fn test_settle_submits_aggregated_usage_once() {
+    let id = entitlement_id(2);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    mount_fullnode(&server, &id, 0, fixture(include_str!("fixtures/sui_executeTransactionBlock_success.json"))).await;
+    let (ledger, settler) = settler(&server).await;
+
+    ledger.debit(&entitlement(&id), 10, SystemClock.now()).await.unwrap();
+    ledger.debit(&entitlement(&id), 15, SystemClock.now()).await.unwrap();
+
+    let settlement = settler.settle(&id).await.unwrap().unwrap();
+    assert_eq!(settlement.amount, 25);
+    assert_eq!(settlement.digest, SUCCESS_DIGEST);
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 0);
+    assert!(!ledger.dirty().await.unwrap().contains(&id));
+
+    // Nothing left to submit
+    assert!(settler.settle(&id).await.unwrap().is_none());
+    assert_eq!(submitted_amounts(&server).await, vec![25]);
+}
+
+#[tokio::test]
+async fn test_failed_settlement_is_retried() {
+    let id = entitlement_id(3);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    mount_fullnode(&server, &id, 0, ResponseTemplate::new(503)).await;
+    let (ledger, settler) = settler(&server).await;
+
+    ledger.debit(&entitlement(&id), 25, SystemClock.now()).await.unwrap();
+    assert!(settler.settle(&id).await.is_err());
+
+    // The usage stays in flight until the chain says otherwise
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 25);
+    assert!(ledger.dirty().await.unwrap().contains(&id));
+
+    ledger.debit(&entitlement(&id), 5, SystemClock.now()).await.unwrap();
+    server.reset().await;
+    mount_fullnode(&server, &id, 0, fixture(include_str!("fixtures/sui_executeTransactionBlock_success.json"))).await;
+
+    let settlement = settler.settle(&id).await.unwrap().unwrap();
+    assert_eq!(settlement.amount, 30);
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_landed_settlement_is_not_resubmitted() {
+    let id = entitlement_id(4);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    mount_fullnode(&server, &id, 0, ResponseTemplate::new(503)).await;
+    let (ledger, settler) = settler(&server).await;
+
+    ledger.debit(&entitlement(&id), 25, SystemClock.now()).await.unwrap();
+    assert!(settler.settle(&id).await.is_err());
+
+    // The transaction went through despite the error
+    server.reset().await;
+    mount_fullnode(&server, &id, 25, fixture(include_str!("fixtures/sui_executeTransactionBlock_success.json"))).await;
+
+    assert!(settler.settle(&id).await.unwrap().is_none());
+    assert!(submitted_amounts(&server).await.is_empty());
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 0);
+
+    let report = settler.reconcile(std::slice::from_ref(&id)).await.unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].local_quota_used, 25);
+    assert_eq!(report[0].on_chain_quota_used, 25);
+    assert_eq!(report[0].drift, 0);
+}
+
+#[tokio::test]
+async fn test_rejected_settlement_is_reported_as_drift() {
+    let id = entitlement_id(5);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    mount_fullnode(&server, &id, 0, fixture(include_str!("fixtures/sui_executeTransactionBlock_quota_exceeded.json"))).await;
+    let (ledger, settler) = settler(&server).await;
+
+    ledger.debit(&entitlement(&id), 40, SystemClock.now()).await.unwrap();
+    let result = settler.settle(&id).await;
+    assert!(matches!(result, Err(ValidatorError::QuotaExceeded)));
+
+    // Retrying cannot succeed, so the usage is dropped from the queue
+    assert_eq!(ledger.unsettled(&id).await.unwrap(), 0);
+    assert!(!ledger.dirty().await.unwrap().contains(&id));
+
+    let report = settler.reconcile(std::slice::from_ref(&id)).await.unwrap();
+    assert_eq!(report[0].local_quota_used, 40);
+    assert_eq!(report[0].on_chain_quota_used, 0);
+    assert_eq!(report[0].unsettled, 0);
+    assert_eq!(report[0].drift, 40);
+}
+
+#[tokio::test]
+async fn test_settlement_outliving_its_lock_keeps_the_next_holders() {
+    let id = entitlement_id(6);
+    reset_usage(&id).await;
+    let server = MockServer::start().await;
+    let slow = fixture(include_str!("fixtures/sui_executeTransactionBlock_success.json")).set_delay(Duration::from_millis(500));
+    mount_fullnode(&server, &id, 0, slow).await;
+    let (ledger, settler) = settler(&server).await;
+    ledger.debit(&entitlement(&id), 10, SystemClock.now()).await.unwrap();
+
+    let settling = tokio::spawn({
+        let id = id.clone();
+        async move { settler.settle(&id).await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The lock expires and another settler takes it
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    let lock_key = format!("meter:lock:{}", id);
+    redis::cmd("SET").arg(&lock_key).arg("other").query_async::<_, ()>(&mut conn).await.unwrap();
+
+    assert!(settling.await.unwrap().unwrap().is_some());
+    let holder: Option<String> = redis::cmd("GET").arg(&lock_key).query_async(&mut conn).await.unwrap();
+    assert_eq!(holder.as_deref(), Some("other"));
+}
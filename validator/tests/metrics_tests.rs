@@ -0,0 +1,210 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::json;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::Request;
+use validator::clock::{FakeClock, UnixMillis};
+use validator::metrics::{self, Outcome};
+use validator::proto::validator_service_server::ValidatorService;
+use validator::proto::{
+    ConsumeEntitlementRequest, IssueAccessTokenRequest, ValidateEntitlementRequest, ValidateSignatureRequest,
+};
+use validator::signature::{SignatureScheme, SuiKeyPair};
+use validator::{RateLimitStore, ValidatorConfig, ValidatorServiceImpl};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const REDIS_URL: &str = "redis://localhost:6379";
+/// `expires_at` of the recorded entitlement, in milliseconds.
+const EXPIRES_AT: UnixMillis = UnixMillis(4_102_444_800_000);
+
+/// Drops cached and metered state for `entitlement_id`.
+async fn reset_usage(entitlement_id: &str) {
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::pipe()
+        .del(format!("ent:{}", entitlement_id))
+        .del(format!("meter:used:{}", entitlement_id))
+        .del(format!("meter:pending:{}", entitlement_id))
+        .del(format!("meter:inflight:{}", entitlement_id))
+        .del(format!("ratelimit:{}", entitlement_id))
+        .srem("meter:dirty", entitlement_id)
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+}
+
+/// Serves a copy of the recorded entitlement under `entitlement_id`.
+async fn mock_entitlement(entitlement_id: &str) -> MockServer {
+    let fullnode = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [entitlement_id] })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, entitlement_id),
+            "application/json",
+        ))
+        .mount(&fullnode)
+        .await;
+    fullnode
+}
+
+fn metrics_config(fullnode: &MockServer) -> ValidatorConfig {
+    ValidatorConfig {
+        redis_url: REDIS_URL.to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
+        cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
+        rate_limit_window: 60,
+        rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Memory,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
+        grpc_port: 50051,
+        metrics_port: 0,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
+    }
+}
+
+/// Signs `message` with the key that owns the recorded entitlement.
+fn owner_signature(message: &str) -> String {
+    SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32])
+        .unwrap()
+        .sign_personal_message(message.as_bytes())
+        .to_base64()
+}
+
+async fn validate(service: &ValidatorServiceImpl, entitlement_id: &str) {
+    let request = Request::new(ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        signature: String::new(),
+        message: String::new(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
+    });
+    service.validate_entitlement(request).await.unwrap();
+}
+
+async fn consume(service: &ValidatorServiceImpl, entitlement_id: &str, amount: u64) {
+    let request = Request::new(ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        amount,
+        signature: owner_signature("metrics"),
+        message: "metrics".to_string(),
+        signed_request: None,
+    });
+    let _ = service.consume_entitlement(request).await;
+}
+
+/// Value of the sample `name` (with its labels) in Prometheus text.
+fn sample(text: &str, name: &str) -> Option<f64> {
+    text.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn test_counts_calls_by_outcome() {
+    let entitlement_id = format!("{}b1", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    reset_usage(&entitlement_id).await;
+
+    let mut config = metrics_config(&fullnode);
+    let mut token_key = vec![0x00];
+    token_key.extend_from_slice(&[0x66; 32]);
+    config.access_token_key = Some(BASE64.encode(token_key));
+
+    let clock = Arc::new(FakeClock::new(EXPIRES_AT.saturating_sub(Duration::from_secs(60))));
+    let service = ValidatorServiceImpl::with_clock(config, clock.clone()).await.unwrap();
+    let metrics = service.metrics();
+
+    validate(&service, &entitlement_id).await;
+    assert_eq!(metrics.calls("validate_entitlement", Outcome::Valid), 1);
+
+    consume(&service, &entitlement_id, 10).await;
+    consume(&service, &entitlement_id, 2000).await;
+    assert_eq!(metrics.calls("consume_entitlement", Outcome::Valid), 1);
+    assert_eq!(metrics.calls("consume_entitlement", Outcome::QuotaExceeded), 1);
+
+    let request = Request::new(ValidateSignatureRequest {
+        entitlement_id: entitlement_id.clone(),
+        signature: owner_signature("something else"),
+        message: "metrics".to_string(),
+    });
+    service.validate_signature(request).await.unwrap();
+    assert_eq!(metrics.calls("validate_signature", Outcome::BadSignature), 1);
+
+    let request = Request::new(IssueAccessTokenRequest {
+        entitlement_id: entitlement_id.clone(),
+        signed_request: None,
+    });
+    service.issue_access_token(request).await.unwrap();
+    assert_eq!(metrics.calls("issue_access_token", Outcome::BadSignature), 1);
+
+    clock.advance(Duration::from_secs(60));
+    validate(&service, &entitlement_id).await;
+    assert_eq!(metrics.calls("validate_entitlement", Outcome::Expired), 1);
+}
+
+#[tokio::test]
+async fn test_renders_cache_chain_and_settlement_metrics() {
+    let entitlement_id = format!("{}b2", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    reset_usage(&entitlement_id).await;
+    let service = ValidatorServiceImpl::new(metrics_config(&fullnode)).await.unwrap();
+
+    validate(&service, &entitlement_id).await;
+    validate(&service, &entitlement_id).await;
+    consume(&service, &entitlement_id, 5).await;
+
+    let text = service.render_metrics().await.unwrap();
+    assert_eq!(
+        sample(&text, r#"inframint_validator_calls_total{call="validate_entitlement",outcome="valid"}"#),
+        Some(2.0),
+    );
+    assert_eq!(sample(&text, r#"inframint_validator_cache_lookups_total{result="miss"}"#), Some(1.0));
+    assert!(sample(&text, r#"inframint_validator_cache_lookups_total{result="local_hit"}"#).unwrap() >= 1.0);
+    assert!(sample(&text, "inframint_validator_cache_hit_ratio").unwrap() > 0.0);
+    assert!(
+        sample(&text, r#"inframint_validator_chain_rpc_duration_seconds_count{method="sui_getObject"}"#).unwrap() >= 1.0
+    );
+
+    // Other tests share the Redis ledger, so the backlog is at least ours
+    assert!(sample(&text, "inframint_validator_settlement_backlog").unwrap() >= 1.0);
+    assert!(sample(&text, "inframint_validator_settlement_backlog_units").unwrap() >= 5.0);
+}
+
+#[tokio::test]
+async fn test_serves_metrics_over_http() {
+    let entitlement_id = format!("{}b3", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let service = ValidatorServiceImpl::new(metrics_config(&fullnode)).await.unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(metrics::router(service).into_make_service()));
+
+    let response = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    assert!(response.text().await.unwrap().contains("# TYPE inframint_validator_settlement_backlog gauge"));
+}
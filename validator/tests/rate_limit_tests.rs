@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use std::time::Duration;
+use validator::clock::{FakeClock, UnixMillis};
+use validator::rate_limit::{InMemoryRateLimiter, RateLimit, RateLimiter, RedisRateLimiter};
+
+const REDIS_URL: &str = "redis://localhost:6379";
+
+/// Redis keys are shared between runs, so every test uses its own.
+fn key(name: &str) -> String {
+    format!("rate-limit-tests:{}:{}", name, std::process::id())
+}
+
+async fn redis_pttl(key: &str) -> i64 {
+    let client = redis::Client::open(REDIS_URL).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::cmd("PTTL").arg(format!("ratelimit:{}", key)).query_async(&mut conn).await.unwrap()
+}
+
+/// Two requests per 200ms: a token refills every 100ms.
+fn limit() -> RateLimit {
+    RateLimit::per_window(2, Duration::from_millis(200))
+}
+
+async fn admitted(limiter: &dyn RateLimiter, key: &str, requests: usize, limit: RateLimit) -> usize {
+    let mut admitted = 0;
+    for _ in 0..requests {
+        if limiter.check(key, limit).await.unwrap() {
+            admitted += 1;
+        }
+    }
+    admitted
+}
+
+async fn assert_burst_then_refill(limiter: &dyn RateLimiter, key: &str) {
+    assert_eq!(admitted(limiter, key, 5, limit()).await, 2);
+
+    // One emission interval later exactly one more request fits
+    tokio::time::sleep(Duration::from_millis(110)).await;
+    assert_eq!(admitted(limiter, key, 3, limit()).await, 1);
+}
+
+async fn assert_keys_are_independent(limiter: &dyn RateLimiter, a: &str, b: &str) {
+    assert_eq!(admitted(limiter, a, 3, limit()).await, 2);
+    assert_eq!(admitted(limiter, b, 3, limit()).await, 2);
+}
+
+async fn assert_reset_clears_key(limiter: &dyn RateLimiter, key: &str) {
+    assert_eq!(admitted(limiter, key, 3, limit()).await, 2);
+    limiter.reset(key).await.unwrap();
+    assert_eq!(admitted(limiter, key, 3, limit()).await, 2);
+}
+
+async fn assert_burst_above_rate(limiter: &dyn RateLimiter, key: &str) {
+    // 10 per second on average, but 5 may arrive back to back
+    let limit = RateLimit {
+        max_requests: 10,
+        window: Duration::from_secs(1),
+        burst: 5,
+    };
+    assert_eq!(admitted(limiter, key, 10, limit).await, 5);
+}
+
+#[tokio::test]
+async fn test_in_memory_burst_then_refill() {
+    assert_burst_then_refill(&InMemoryRateLimiter::new(), "burst").await;
+}
+
+#[tokio::test]
+async fn test_in_memory_refills_with_the_clock() {
+    let clock = Arc::new(FakeClock::new(UnixMillis(1_729_166_400_000)));
+    let limiter = InMemoryRateLimiter::new().with_clock(clock.clone());
+    assert_eq!(admitted(&limiter, "fake-clock", 5, limit()).await, 2);
+
+    // Nothing refills until the clock moves
+    assert_eq!(admitted(&limiter, "fake-clock", 1, limit()).await, 0);
+    clock.advance(Duration::from_millis(99));
+    assert_eq!(admitted(&limiter, "fake-clock", 1, limit()).await, 0);
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(admitted(&limiter, "fake-clock", 3, limit()).await, 1);
+}
+
+#[tokio::test]
+async fn test_in_memory_keys_are_independent() {
+    assert_keys_are_independent(&InMemoryRateLimiter::new(), "a", "b").await;
+}
+
+#[tokio::test]
+async fn test_in_memory_reset() {
+    assert_reset_clears_key(&InMemoryRateLimiter::new(), "reset").await;
+}
+
+#[tokio::test]
+async fn test_in_memory_burst_allowance() {
+    assert_burst_above_rate(&InMemoryRateLimiter::new(), "burst-allowance").await;
+}
+
+#[tokio::test]
+async fn test_in_memory_evicts_idle_keys() {
+    let limiter = InMemoryRateLimiter::with_sweep_interval(Duration::from_millis(50));
+    for i in 0..100 {
+        limiter.check(&format!("idle-{}", i), limit()).await.unwrap();
+    }
+    assert_eq!(limiter.len().await, 100);
+
+    // Every bucket is full again after 100ms; the next check sweeps them
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    limiter.check("active", limit()).await.unwrap();
+    assert_eq!(limiter.len().await, 1);
+}
+
+#[tokio::test]
+async fn test_redis_burst_then_refill() {
+    let limiter = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    assert_burst_then_refill(&limiter, &key("burst")).await;
+}
+
+#[tokio::test]
+async fn test_redis_keys_are_independent() {
+    let limiter = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    assert_keys_are_independent(&limiter, &key("a"), &key("b")).await;
+}
+
+#[tokio::test]
+async fn test_redis_reset() {
+    let limiter = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    assert_reset_clears_key(&limiter, &key("reset")).await;
+}
+
+#[tokio::test]
+async fn test_redis_burst_allowance() {
+    let limiter = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    assert_burst_above_rate(&limiter, &key("burst-allowance")).await;
+}
+
+#[tokio::test]
+async fn test_redis_limit_is_shared_between_replicas() {
+    let key = key("replicas");
+    let first = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    let second = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+
+    assert!(first.check(&key, limit()).await.unwrap());
+    assert!(second.check(&key, limit()).await.unwrap());
+    assert!(!first.check(&key, limit()).await.unwrap());
+    assert!(!second.check(&key, limit()).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_redis_keys_expire_when_idle() {
+    let limiter = RedisRateLimiter::new(REDIS_URL).await.unwrap();
+    let key = key("expiry");
+
+    limiter.check(&key, limit()).await.unwrap();
+    let ttl = redis_pttl(&key).await;
+    assert!(ttl > 0 && ttl <= 100, "unexpected TTL {}", ttl);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(redis_pttl(&key).await, -2);
+}
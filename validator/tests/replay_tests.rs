@@ -0,0 +1,152 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use validator::blockchain::Entitlement;
+use validator::cache::EntitlementCache;
+use validator::clock::UnixMillis;
+use validator::proto::RequestPayload;
+use validator::replay::{sign_request, NonceStore, SignedRequestError, SignedRequestVerifier, PAYLOAD_VERSION};
+use validator::signature::{SignatureScheme, SuiKeyPair};
+
+const REDIS_URL: &str = "redis://localhost:6379";
+const AUDIENCE: &str = "inframint-validator";
+const NOW: UnixMillis = UnixMillis(1_729_166_400_000);
+
+fn buyer() -> SuiKeyPair {
+    SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap()
+}
+
+fn entitlement() -> Entitlement {
+    Entitlement {
+        id: format!("0x{}", "d5".repeat(32)),
+        service_id: "11111111-1111-1111-1111-111111111111".to_string(),
+        buyer: buyer().address(),
+        tier_id: 1,
+        quota_requests: 1000,
+        quota_used: 0,
+        purchased_at: UnixMillis(1_729_000_000_000),
+        expires_at: UnixMillis(4_102_444_800_000),
+        active: true,
+    }
+}
+
+/// Nonces outlive a run in Redis, so every payload gets a fresh one.
+fn payload(amount: u64) -> RequestPayload {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    RequestPayload {
+        version: PAYLOAD_VERSION,
+        entitlement_id: entitlement().id,
+        service_id: entitlement().service_id,
+        nonce: format!("{}-{}", std::process::id(), nanos),
+        issued_at_ms: NOW.as_millis(),
+        audience: AUDIENCE.to_string(),
+        amount,
+    }
+}
+
+async fn verifier() -> SignedRequestVerifier {
+    let cache = EntitlementCache::new(REDIS_URL, 300).await.unwrap();
+    SignedRequestVerifier::new(NonceStore::new(&cache), AUDIENCE, Duration::from_secs(60))
+}
+
+#[tokio::test]
+async fn test_accepts_signed_request_once() {
+    let verifier = verifier().await;
+    let signed = sign_request(&buyer(), &payload(10));
+
+    let accepted = verifier.verify(&signed, &entitlement(), 10, NOW).await.unwrap();
+    assert_eq!(accepted.amount, 10);
+
+    assert!(matches!(
+        verifier.verify(&signed, &entitlement(), 10, NOW).await,
+        Err(SignedRequestError::Replayed)
+    ));
+}
+
+#[tokio::test]
+async fn test_nonce_is_shared_between_replicas() {
+    let signed = sign_request(&buyer(), &payload(1));
+
+    verifier().await.verify(&signed, &entitlement(), 1, NOW).await.unwrap();
+    assert!(matches!(
+        verifier().await.verify(&signed, &entitlement(), 1, NOW).await,
+        Err(SignedRequestError::Replayed)
+    ));
+}
+
+#[tokio::test]
+async fn test_rejects_requests_outside_freshness_window() {
+    let verifier = verifier().await;
+
+    for issued_at in [NOW.saturating_sub(Duration::from_millis(60_001)), NOW.saturating_add(Duration::from_millis(60_001))] {
+        let signed = sign_request(&buyer(), &RequestPayload { issued_at_ms: issued_at.as_millis(), ..payload(1) });
+        assert!(matches!(
+            verifier.verify(&signed, &entitlement(), 1, NOW).await,
+            Err(SignedRequestError::Stale)
+        ));
+    }
+
+    let signed = sign_request(&buyer(), &RequestPayload {
+        issued_at_ms: NOW.saturating_sub(Duration::from_secs(60)).as_millis(),
+        ..payload(1)
+    });
+    assert!(verifier.verify(&signed, &entitlement(), 1, NOW).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_rejects_requests_for_something_else() {
+    let verifier = verifier().await;
+    let cases = [
+        (RequestPayload { entitlement_id: format!("0x{}", "e6".repeat(32)), ..payload(1) }, "entitlement"),
+        (RequestPayload { service_id: "22222222-2222-2222-2222-222222222222".to_string(), ..payload(1) }, "service"),
+        (RequestPayload { audience: "another-validator".to_string(), ..payload(1) }, "audience"),
+        (payload(2), "amount"),
+    ];
+
+    for (payload, field) in cases {
+        let signed = sign_request(&buyer(), &payload);
+        match verifier.verify(&signed, &entitlement(), 1, NOW).await {
+            Err(SignedRequestError::Mismatch(mismatched)) => assert_eq!(mismatched, field),
+            other => panic!("expected a {} mismatch, got {:?}", field, other.map(|_| ())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_malformed_payloads() {
+    let verifier = verifier().await;
+
+    let signed = sign_request(&buyer(), &RequestPayload { version: 2, ..payload(1) });
+    assert!(matches!(
+        verifier.verify(&signed, &entitlement(), 1, NOW).await,
+        Err(SignedRequestError::UnsupportedVersion(2))
+    ));
+
+    let signed = sign_request(&buyer(), &RequestPayload { nonce: String::new(), ..payload(1) });
+    assert!(matches!(
+        verifier.verify(&signed, &entitlement(), 1, NOW).await,
+        Err(SignedRequestError::InvalidNonce)
+    ));
+
+    let mut signed = sign_request(&buyer(), &payload(1));
+    signed.payload = vec![0xff; 4];
+    assert!(matches!(
+        verifier.verify(&signed, &entitlement(), 1, NOW).await,
+        Err(SignedRequestError::MalformedPayload)
+    ));
+}
+
+#[tokio::test]
+async fn test_rejects_signature_by_anyone_but_the_buyer() {
+    let verifier = verifier().await;
+    let payload = payload(1);
+
+    let stranger = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x55; 32]).unwrap();
+    let signed = sign_request(&stranger, &payload);
+    assert!(matches!(
+        verifier.verify(&signed, &entitlement(), 1, NOW).await,
+        Err(SignedRequestError::InvalidSignature)
+    ));
+
+    // A rejected request does not burn the buyer's nonce
+    let signed = sign_request(&buyer(), &payload);
+    assert!(verifier.verify(&signed, &entitlement(), 1, NOW).await.is_ok());
+}
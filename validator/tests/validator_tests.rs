@@ -1,35 +1,132 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::json;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use validator::{ValidatorServiceImpl, ValidatorConfig};
-use validator::cache::EntitlementCache;
-use validator::blockchain::{SuiBlockchainClient, Entitlement};
-use validator::rate_limit::RateLimiter;
-use validator::error::ValidatorError;
+use std::time::Duration;
+use inframint_types::error_code;
 use tonic::Request;
+use validator::clock::{Clock, FakeClock, SystemClock, UnixMillis};
+use validator::replay::{sign_request, PAYLOAD_VERSION};
+use validator::{RateLimitStore, ValidatorServiceImpl, ValidatorConfig};
+use validator::proto::validator_service_server::ValidatorService;
+use validator::signature::{SignatureScheme, SuiKeyPair};
 use validator::proto::{
-    ValidateEntitlementRequest, ConsumeEntitlementRequest, ValidateSignatureRequest
+    ValidateEntitlementRequest, ConsumeEntitlementRequest, ValidateSignatureRequest,
+    RequestPayload, SignedRequest, IssueAccessTokenRequest,
+    ErrorCode, ValidateEntitlementResponse,
+    BatchValidateEntitlementsRequest, BatchConsumeEntitlementsRequest,
 };
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PACKAGE_ID: &str = "0x9c2a4d6e8f0b1c3d5e7f9a1b3c5d7e9f0a2b4c6d8e0f1a3b5c7d9e1f3a5b7c9d";
+const ENTITLEMENT_ID: &str = "0x4b7e1f2a9c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f";
+const REGISTRY_ID: &str = "0x7a3c5e7f9b1d3f5a7c9e1b3d5f7a9c1e3b5d7f9a1c3e5b7d9f1a3c5e7b9d1f3a";
+/// Service of the recorded entitlement.
+const SERVICE_ID: &str = "11111111-1111-1111-1111-111111111111";
+const VALIDATOR_CAP_ID: &str = "0x6f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+
+/// Signs `message` with the key that owns the recorded entitlement.
+fn owner_signature(message: &str) -> String {
+    SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32])
+        .unwrap()
+        .sign_personal_message(message.as_bytes())
+        .to_base64()
+}
+
+/// Writes a `sui.keystore` holding the validator key and returns its path.
+fn validator_keystore(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("inframint-{}-{}.keystore", name, std::process::id()));
+    let mut entry = vec![0x00];
+    entry.extend_from_slice(&[0x44; 32]);
+    std::fs::write(&path, json!([BASE64.encode(entry)]).to_string()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+/// Drops cached and metered state for `entitlement_id`.
+async fn reset_usage(redis_url: &str, entitlement_id: &str) {
+    let client = redis::Client::open(redis_url).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    redis::pipe()
+        .del(format!("ent:{}", entitlement_id))
+        .del(format!("meter:used:{}", entitlement_id))
+        .del(format!("meter:pending:{}", entitlement_id))
+        .del(format!("meter:inflight:{}", entitlement_id))
+        .del(format!("ratelimit:{}", entitlement_id))
+        .srem("meter:dirty", entitlement_id)
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+}
+
+/// Starts a mock Sui fullnode that serves the recorded `Entitlement` object
+/// and accepts `consume_entitlement` transactions.
+async fn mock_fullnode() -> MockServer {
+    let server = MockServer::start().await;
+
+    let fixtures = [
+        ("sui_getObject", include_str!("fixtures/sui_getObject_entitlement.json")),
+        ("sui_multiGetObjects", include_str!("fixtures/sui_multiGetObjects_consume_inputs.json")),
+        ("suix_getCoins", include_str!("fixtures/suix_getCoins.json")),
+        ("suix_getReferenceGasPrice", include_str!("fixtures/suix_getReferenceGasPrice.json")),
+        ("sui_executeTransactionBlock", include_str!("fixtures/sui_executeTransactionBlock_success.json")),
+    ];
+    for (rpc_method, fixture) in fixtures {
+        let params = if rpc_method == "sui_getObject" { json!([ENTITLEMENT_ID]) } else { json!([]) };
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method, "params": params })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(fixture, "application/json"))
+            .mount(&server)
+            .await;
+    }
+
+    server
+}
 
 #[tokio::test]
 async fn test_validate_entitlement_success() {
     // Setup test environment
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
 
     // Test with mock data
     let request = Request::new(ValidateEntitlementRequest {
-        entitlement_id: "test-entitlement-1".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response = service.validate_entitlement(request).await.unwrap();
@@ -38,14 +135,36 @@ async fn test_validate_entitlement_success() {
 
 #[tokio::test]
 async fn test_validate_entitlement_invalid() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
@@ -54,97 +173,236 @@ async fn test_validate_entitlement_invalid() {
         entitlement_id: "invalid-entitlement".to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
-    let response = service.validate_entitlement(request).await.unwrap();
-    assert!(!response.into_inner().valid);
+    let response = service.validate_entitlement(request).await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.error_code(), ErrorCode::NotFound);
 }
 
 #[tokio::test]
 async fn test_consume_entitlement_success() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: Some(validator_keystore("consume-success")),
+        validator_address: None,
+        validator_cap_id: Some(VALIDATOR_CAP_ID.to_string()),
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
+    // Start from the on-chain quota rather than a previous run's debits
+    reset_usage(&config.redis_url, ENTITLEMENT_ID).await;
+
     let service = ValidatorServiceImpl::new(config).await.unwrap();
 
     let request = Request::new(ConsumeEntitlementRequest {
-        entitlement_id: "test-entitlement-1".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
         amount: 10,
-        signature: "test-signature".to_string(),
+        signature: owner_signature("test-message"),
         message: "test-message".to_string(),
+        signed_request: None,
     });
 
-    let response = service.consume_entitlement(request).await.unwrap();
-    assert!(response.into_inner().success);
-    assert_eq!(response.into_inner().remaining_quota, 990);
+    let response = service.consume_entitlement(request).await.unwrap().into_inner();
+    assert!(response.success);
+    assert_eq!(response.remaining_quota, 990);
 }
 
 #[tokio::test]
 async fn test_consume_entitlement_quota_exceeded() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
 
     let request = Request::new(ConsumeEntitlementRequest {
-        entitlement_id: "test-entitlement-1".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
         amount: 2000, // More than quota
-        signature: "test-signature".to_string(),
+        signature: owner_signature("test-message"),
         message: "test-message".to_string(),
+        signed_request: None,
     });
 
-    let response = service.consume_entitlement(request).await;
-    assert!(response.is_err());
+    let status = service.consume_entitlement(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    assert_eq!(error_code(&status), Some(ErrorCode::QuotaExceeded));
 }
 
 #[tokio::test]
 async fn test_validate_signature() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
 
     let request = Request::new(ValidateSignatureRequest {
-        entitlement_id: "test-entitlement-1".to_string(),
-        signature: "test-signature".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
+        signature: owner_signature("test-message"),
         message: "test-message".to_string(),
     });
 
     let response = service.validate_signature(request).await.unwrap();
     assert!(response.into_inner().valid);
+
+    // Neither is worth retrying
+    let request = Request::new(ValidateSignatureRequest {
+        entitlement_id: ENTITLEMENT_ID.to_string(),
+        signature: "not-a-signature".to_string(),
+        message: "test-message".to_string(),
+    });
+    let response = service.validate_signature(request).await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.error_code(), ErrorCode::BadSignature);
+
+    let missing = format!("0x{:0>64}", "ad");
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [&missing] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "error": { "code": "notExists", "object_id": missing } }
+        })))
+        .mount(&fullnode)
+        .await;
+    let request = Request::new(ValidateSignatureRequest {
+        entitlement_id: missing.clone(),
+        signature: owner_signature("test-message"),
+        message: "test-message".to_string(),
+    });
+    let status = service.validate_signature(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    assert_eq!(error_code(&status), Some(ErrorCode::NotFound));
+
+    // Nor is a legacy-signed request for it
+    let request = Request::new(ConsumeEntitlementRequest {
+        entitlement_id: missing,
+        amount: 1,
+        signature: owner_signature("test-message"),
+        message: "test-message".to_string(),
+        signed_request: None,
+    });
+    let response = service.consume_entitlement(request).await.unwrap().into_inner();
+    assert!(!response.success);
+    assert_eq!(response.error_code(), ErrorCode::NotFound);
 }
 
 #[tokio::test]
 async fn test_rate_limiting() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 1, // 1 second window
         rate_limit_max: 2,   // Max 2 requests
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
@@ -154,6 +412,9 @@ async fn test_rate_limiting() {
         entitlement_id: "rate-limit-test".to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response1 = service.validate_entitlement(request1).await;
@@ -164,6 +425,9 @@ async fn test_rate_limiting() {
         entitlement_id: "rate-limit-test".to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response2 = service.validate_entitlement(request2).await;
@@ -174,6 +438,9 @@ async fn test_rate_limiting() {
         entitlement_id: "rate-limit-test".to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response3 = service.validate_entitlement(request3).await;
@@ -182,23 +449,48 @@ async fn test_rate_limiting() {
 
 #[tokio::test]
 async fn test_caching() {
+    let fullnode = mock_fullnode().await;
     let config = ValidatorConfig {
         redis_url: "redis://localhost:6379".to_string(),
-        sui_rpc_url: "https://sui-testnet.rpc.com".to_string(),
-        contract_address: "0x123".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
         cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
         rate_limit_window: 60,
         rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
         grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
     };
 
     let service = ValidatorServiceImpl::new(config).await.unwrap();
 
     // First request - should hit blockchain
     let request1 = Request::new(ValidateEntitlementRequest {
-        entitlement_id: "cache-test".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response1 = service.validate_entitlement(request1).await.unwrap();
@@ -206,11 +498,563 @@ async fn test_caching() {
 
     // Second request - should hit cache
     let request2 = Request::new(ValidateEntitlementRequest {
-        entitlement_id: "cache-test".to_string(),
+        entitlement_id: ENTITLEMENT_ID.to_string(),
         signature: "".to_string(),
         message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
     });
 
     let response2 = service.validate_entitlement(request2).await.unwrap();
     assert!(response2.into_inner().valid);
 }
+
+#[tokio::test]
+async fn test_tier_rate_limit_overrides_default() {
+    // A copy of the recorded entitlement that no other test caches
+    let entitlement_id = format!("{}01", &ENTITLEMENT_ID[..64]);
+    let fullnode = MockServer::start().await;
+    let fixtures = [
+        ("sui_getObject", json!([entitlement_id]), include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, &entitlement_id)),
+        ("sui_getObject", json!([REGISTRY_ID]), include_str!("fixtures/sui_getObject_registry.json").to_string()),
+        ("sui_devInspectTransactionBlock", json!([]), include_str!("fixtures/sui_devInspectTransactionBlock_tier.json").to_string()),
+    ];
+    for (rpc_method, params, fixture) in fixtures {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method, "params": params })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(fixture, "application/json"))
+            .mount(&fullnode)
+            .await;
+    }
+
+    let config = ValidatorConfig {
+        redis_url: "redis://localhost:6379".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
+        cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms: 0,
+        rate_limit_window: 60,
+        rate_limit_max: 2,   // Global default
+        rate_limit_store: RateLimitStore::Redis,
+        rate_limit_burst_factor: 1,
+        registry_id: Some(REGISTRY_ID.to_string()),
+        grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
+    };
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+
+    // The tier allows 5 requests per second
+    for _ in 0..5 {
+        let request = Request::new(ValidateEntitlementRequest {
+            entitlement_id: entitlement_id.clone(),
+            signature: "".to_string(),
+            message: "".to_string(),
+            signed_request: None,
+            access_token: String::new(),
+            service_id: String::new(),
+        });
+        assert!(service.validate_entitlement(request).await.unwrap().into_inner().valid);
+    }
+
+    let request = Request::new(ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.clone(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
+    });
+    let status = service.validate_entitlement(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    assert_eq!(error_code(&status), Some(ErrorCode::RateLimited));
+}
+
+/// `expires_at` of the recorded entitlement, in milliseconds.
+const EXPIRES_AT: UnixMillis = UnixMillis(4_102_444_800_000);
+
+/// Serves a copy of the recorded entitlement under `entitlement_id`, so
+/// that the test's cached state is its own.
+async fn mock_entitlement(entitlement_id: &str) -> MockServer {
+    let fullnode = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [entitlement_id] })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, entitlement_id),
+            "application/json",
+        ))
+        .mount(&fullnode)
+        .await;
+    fullnode
+}
+
+fn clock_config(fullnode: &MockServer, expiry_grace_period_ms: u64) -> ValidatorConfig {
+    ValidatorConfig {
+        redis_url: "redis://localhost:6379".to_string(),
+        sui_rpc_url: fullnode.uri(),
+        contract_address: PACKAGE_ID.to_string(),
+        cache_ttl: 300,
+        local_cache_capacity: 10_000,
+        local_cache_ttl: 5,
+        event_poll_interval: 2,
+        expiry_grace_period_ms,
+        rate_limit_window: 60,
+        rate_limit_max: 1000,
+        rate_limit_store: RateLimitStore::Memory,
+        rate_limit_burst_factor: 2,
+        registry_id: None,
+        grpc_port: 50051,
+        metrics_port: 9090,
+        validator_id: "inframint-validator".to_string(),
+        signed_request_max_age_ms: 60_000,
+        allow_legacy_signatures: true,
+        access_token_key: None,
+        access_token_ttl: 900,
+        keystore_path: None,
+        validator_address: None,
+        validator_cap_id: None,
+        gas_budget: 10_000_000,
+        settle_interval: 30,
+        settle_threshold: 1000,
+        health_check_interval: 10,
+        max_batch_size: 100,
+    }
+}
+
+async fn validate(service: &ValidatorServiceImpl, entitlement_id: &str) -> ValidateEntitlementResponse {
+    let request = Request::new(ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
+    });
+    service.validate_entitlement(request).await.unwrap().into_inner()
+}
+
+async fn is_valid(service: &ValidatorServiceImpl, entitlement_id: &str) -> bool {
+    validate(service, entitlement_id).await.valid
+}
+
+#[tokio::test]
+async fn test_entitlement_expires_to_the_millisecond() {
+    let entitlement_id = format!("{}02", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let config = clock_config(&fullnode, 0);
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let clock = Arc::new(FakeClock::new(EXPIRES_AT.saturating_sub(Duration::from_secs(1))));
+    let service = ValidatorServiceImpl::with_clock(config, clock.clone()).await.unwrap();
+    assert!(is_valid(&service, &entitlement_id).await);
+
+    // Still cached, but no longer valid
+    clock.advance(Duration::from_millis(999));
+    assert!(is_valid(&service, &entitlement_id).await);
+    clock.advance(Duration::from_millis(1));
+    let response = validate(&service, &entitlement_id).await;
+    assert!(!response.valid);
+    assert_eq!(response.error_code(), ErrorCode::Expired);
+}
+
+#[tokio::test]
+async fn test_expiry_grace_period() {
+    let entitlement_id = format!("{}03", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let config = clock_config(&fullnode, 500);
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let clock = Arc::new(FakeClock::new(EXPIRES_AT.saturating_add(Duration::from_millis(499))));
+    let service = ValidatorServiceImpl::with_clock(config, clock.clone()).await.unwrap();
+    assert!(is_valid(&service, &entitlement_id).await);
+
+    clock.advance(Duration::from_millis(1));
+    assert!(!is_valid(&service, &entitlement_id).await);
+}
+
+#[tokio::test]
+async fn test_debits_within_the_expiry_grace_period() {
+    // Expired by Redis' clock too, so usage counters can't expire with it
+    let expired_at = SystemClock.now().saturating_sub(Duration::from_secs(1));
+    let entitlement_id = format!("{}0b", &ENTITLEMENT_ID[..64]);
+    let fullnode = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [entitlement_id] })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/sui_getObject_entitlement.json")
+                .replace(ENTITLEMENT_ID, &entitlement_id)
+                .replace(&EXPIRES_AT.as_millis().to_string(), &expired_at.as_millis().to_string()),
+            "application/json",
+        ))
+        .mount(&fullnode)
+        .await;
+    let config = clock_config(&fullnode, 60_000);
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+    let consume = || Request::new(ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.clone(),
+        amount: 10,
+        signature: owner_signature("test-message"),
+        message: "test-message".to_string(),
+        signed_request: None,
+    });
+
+    let response = service.consume_entitlement(consume()).await.unwrap().into_inner();
+    assert!(response.success, "{}", response.error);
+    assert_eq!(response.remaining_quota, 990);
+    let response = service.consume_entitlement(consume()).await.unwrap().into_inner();
+    assert_eq!(response.remaining_quota, 980);
+}
+
+fn signed_request(entitlement_id: &str, nonce: &str, amount: u64) -> SignedRequest {
+    let owner = SuiKeyPair::from_secret_bytes(SignatureScheme::Ed25519, &[0x11; 32]).unwrap();
+    sign_request(&owner, &RequestPayload {
+        version: PAYLOAD_VERSION,
+        entitlement_id: entitlement_id.to_string(),
+        service_id: SERVICE_ID.to_string(),
+        nonce: nonce.to_string(),
+        issued_at_ms: SystemClock.now().as_millis(),
+        audience: "inframint-validator".to_string(),
+        amount,
+    })
+}
+
+#[tokio::test]
+async fn test_signed_consume_cannot_be_replayed() {
+    let entitlement_id = format!("{}04", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let config = clock_config(&fullnode, 0);
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+    let nonce = format!("consume-{}-{}", std::process::id(), SystemClock.now().as_millis());
+    let consume = || Request::new(ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.clone(),
+        amount: 10,
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: Some(signed_request(&entitlement_id, &nonce, 10)),
+    });
+
+    let response = service.consume_entitlement(consume()).await.unwrap().into_inner();
+    assert!(response.success);
+    assert_eq!(response.remaining_quota, 990);
+
+    let replayed = service.consume_entitlement(consume()).await.unwrap().into_inner();
+    assert!(!replayed.success);
+    assert_eq!(replayed.error, "Signed request was already used");
+}
+
+#[tokio::test]
+async fn test_legacy_signatures_can_be_disabled() {
+    let entitlement_id = format!("{}05", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let mut config = clock_config(&fullnode, 0);
+    config.allow_legacy_signatures = false;
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+
+    let request = Request::new(ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.clone(),
+        amount: 1,
+        signature: owner_signature("test-message"),
+        message: "test-message".to_string(),
+        signed_request: None,
+    });
+    let response = service.consume_entitlement(request).await.unwrap().into_inner();
+    assert!(!response.success);
+    assert_eq!(response.error, "Signed request required");
+    assert_eq!(response.error_code(), ErrorCode::BadSignature);
+
+    // Unsigned validation stays open
+    assert!(is_valid(&service, &entitlement_id).await);
+
+    let nonce = format!("validate-{}-{}", std::process::id(), SystemClock.now().as_millis());
+    let request = Request::new(ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.clone(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: Some(signed_request(&entitlement_id, &nonce, 0)),
+        access_token: String::new(),
+        service_id: String::new(),
+    });
+    assert!(service.validate_entitlement(request).await.unwrap().into_inner().valid);
+}
+
+#[tokio::test]
+async fn test_access_token_replaces_signatures() {
+    let entitlement_id = format!("{}06", &ENTITLEMENT_ID[..64]);
+    let fullnode = mock_entitlement(&entitlement_id).await;
+    let mut config = clock_config(&fullnode, 0);
+    let mut token_key = vec![0x00];
+    token_key.extend_from_slice(&[0x66; 32]);
+    config.access_token_key = Some(BASE64.encode(token_key));
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config.clone()).await.unwrap();
+
+    let request = Request::new(IssueAccessTokenRequest {
+        entitlement_id: entitlement_id.clone(),
+        signed_request: None,
+    });
+    let refused = service.issue_access_token(request).await.unwrap().into_inner();
+    assert!(!refused.success);
+    assert_eq!(refused.error, "Signed request required");
+
+    let nonce = format!("token-{}-{}", std::process::id(), SystemClock.now().as_millis());
+    let request = Request::new(IssueAccessTokenRequest {
+        entitlement_id: entitlement_id.clone(),
+        signed_request: Some(signed_request(&entitlement_id, &nonce, 0)),
+    });
+    let issued = service.issue_access_token(request).await.unwrap().into_inner();
+    assert!(issued.success, "{}", issued.error);
+    assert!(issued.expires_at > SystemClock.now().as_millis());
+
+    let with_token = |entitlement_id: &str, service_id: &str| Request::new(ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: None,
+        access_token: issued.access_token.clone(),
+        service_id: service_id.to_string(),
+    });
+
+    let response = service.validate_entitlement(with_token(&entitlement_id, SERVICE_ID)).await.unwrap().into_inner();
+    assert!(response.valid);
+    assert_eq!(response.entitlement.unwrap().id, entitlement_id);
+
+    let other = format!("{}07", &ENTITLEMENT_ID[..64]);
+    let response = service.validate_entitlement(with_token(&other, SERVICE_ID)).await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.error, "Access token is for another entitlement");
+
+    let other_service = "22222222-2222-2222-2222-222222222222";
+    let response = service.validate_entitlement(with_token(&entitlement_id, other_service)).await.unwrap().into_inner();
+    assert!(!response.valid);
+    assert_eq!(response.error_code(), ErrorCode::WrongService);
+
+    let status = service.validate_entitlement(with_token(&entitlement_id, "")).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // Another replica loads the entitlement it hasn't cached, and refuses
+    // the token if that fails
+    reset_usage(&config.redis_url, &entitlement_id).await;
+    let replica = ValidatorServiceImpl::new(config.clone()).await.unwrap();
+    let loads = fullnode_methods(&fullnode).await.len();
+    let response = replica.validate_entitlement(with_token(&entitlement_id, SERVICE_ID)).await.unwrap().into_inner();
+    assert!(response.valid);
+    assert_eq!(response.entitlement.unwrap().id, entitlement_id);
+    assert_eq!(fullnode_methods(&fullnode).await[loads..], ["sui_getObject"]);
+
+    let unreachable = MockServer::start().await;
+    config.sui_rpc_url = unreachable.uri();
+    reset_usage(&config.redis_url, &entitlement_id).await;
+    let replica = ValidatorServiceImpl::new(config).await.unwrap();
+    assert!(replica.validate_entitlement(with_token(&entitlement_id, SERVICE_ID)).await.is_err());
+}
+
+/// The recorded entitlement object, as `sui_getObject` returns it.
+fn entitlement_object(entitlement_id: &str) -> serde_json::Value {
+    let response: serde_json::Value = serde_json::from_str(
+        &include_str!("fixtures/sui_getObject_entitlement.json").replace(ENTITLEMENT_ID, entitlement_id),
+    ).unwrap();
+    response["result"].clone()
+}
+
+/// Serves copies of the recorded entitlement under `ids`, except for
+/// `missing_id`, which is deleted, from a single `sui_multiGetObjects` call.
+async fn mock_multi_get(ids: &[&str], missing_id: &str) -> MockServer {
+    let fullnode = MockServer::start().await;
+    let objects: Vec<serde_json::Value> = ids.iter()
+        .map(|id| if *id == missing_id {
+            json!({ "error": { "code": "notExists", "object_id": missing_id } })
+        } else {
+            entitlement_object(id)
+        })
+        .collect();
+
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_multiGetObjects", "params": [ids] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": objects })))
+        .mount(&fullnode)
+        .await;
+    fullnode
+}
+
+async fn fullnode_methods(fullnode: &MockServer) -> Vec<String> {
+    fullnode.received_requests().await.unwrap()
+        .iter()
+        .map(|request| request.body_json::<serde_json::Value>().unwrap()["method"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_batch_validate_loads_entitlements_at_once() {
+    let first = format!("{}08", &ENTITLEMENT_ID[..64]);
+    let second = format!("{}09", &ENTITLEMENT_ID[..64]);
+    let missing = format!("0x{:0>64}", "aa");
+    let fullnode = mock_multi_get(&[&first, &missing, &second], &missing).await;
+    let mut config = clock_config(&fullnode, 0);
+    config.max_batch_size = 4;
+    for id in [&first, &second, &missing] {
+        reset_usage(&config.redis_url, id).await;
+    }
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+
+    let validate = |entitlement_id: &str| ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
+    };
+    let request = Request::new(BatchValidateEntitlementsRequest {
+        requests: vec![validate(&first), validate(&missing), validate(&second), validate(&first)],
+    });
+    let responses = service.batch_validate_entitlements(request).await.unwrap().into_inner().responses;
+
+    let valid: Vec<bool> = responses.iter().map(|response| response.valid).collect();
+    assert_eq!(valid, [true, false, true, true]);
+    assert_eq!(responses[1].error_code(), ErrorCode::NotFound);
+    assert_eq!(responses[2].entitlement.as_ref().unwrap().id, second);
+    assert_eq!(fullnode_methods(&fullnode).await, ["sui_multiGetObjects"]);
+
+    // Loaded entitlements are cached for the requests that follow
+    assert!(is_valid(&service, &first).await);
+    assert_eq!(fullnode_methods(&fullnode).await, ["sui_multiGetObjects"]);
+
+    let request = Request::new(BatchValidateEntitlementsRequest {
+        requests: vec![validate(&first); 5],
+    });
+    let status = service.batch_validate_entitlements(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_batch_consume_answers_each_request() {
+    let entitlement_id = format!("{}0a", &ENTITLEMENT_ID[..64]);
+    let missing = format!("0x{:0>64}", "ab");
+    let fullnode = mock_multi_get(&[&entitlement_id, &missing], &missing).await;
+    let config = clock_config(&fullnode, 0);
+    reset_usage(&config.redis_url, &entitlement_id).await;
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+
+    let nonce = format!("batch-{}-{}", std::process::id(), SystemClock.now().as_millis());
+    let consume = |entitlement_id: &str, nonce: &str, amount: u64| ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        amount,
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: Some(signed_request(entitlement_id, nonce, amount)),
+    };
+    let request = Request::new(BatchConsumeEntitlementsRequest {
+        requests: vec![
+            consume(&entitlement_id, &format!("{}-1", nonce), 10),
+            consume(&entitlement_id, &format!("{}-2", nonce), 2000),
+            consume(&missing, &format!("{}-3", nonce), 1),
+        ],
+    });
+    let responses = service.batch_consume_entitlements(request).await.unwrap().into_inner().responses;
+
+    assert!(responses[0].success, "{}", responses[0].error);
+    assert_eq!(responses[0].remaining_quota, 990);
+    assert!(!responses[1].success);
+    assert_eq!(responses[1].error_code(), ErrorCode::QuotaExceeded);
+    assert!(!responses[2].success);
+    assert_eq!(responses[2].error_code(), ErrorCode::NotFound);
+    assert_eq!(fullnode_methods(&fullnode).await, ["sui_multiGetObjects"]);
+}
+
+#[tokio::test]
+async fn test_batch_refusals_match_single_calls() {
+    let expired = format!("{}0c", &ENTITLEMENT_ID[..64]);
+    let inactive = format!("{}0d", &ENTITLEMENT_ID[..64]);
+    let missing = format!("0x{:0>64}", "ac");
+    let mut expired_object = entitlement_object(&expired);
+    expired_object["data"]["content"]["fields"]["expires_at"] = json!("1729166400001");
+    let mut inactive_object = entitlement_object(&inactive);
+    inactive_object["data"]["content"]["fields"]["active"] = json!(false);
+
+    let fullnode = MockServer::start().await;
+    let objects = json!([expired_object, inactive_object, { "error": { "code": "notExists", "object_id": missing } }]);
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_multiGetObjects", "params": [[&expired, &inactive, &missing]] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": objects })))
+        .mount(&fullnode)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "sui_getObject", "params": [&expired] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": expired_object })))
+        .mount(&fullnode)
+        .await;
+    let mut config = clock_config(&fullnode, 0);
+    config.rate_limit_max = 3;
+    for id in [&expired, &inactive, &missing] {
+        reset_usage(&config.redis_url, id).await;
+    }
+
+    let service = ValidatorServiceImpl::new(config).await.unwrap();
+
+    let validation = |entitlement_id: &str| ValidateEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: None,
+        access_token: String::new(),
+        service_id: String::new(),
+    };
+    let request = Request::new(BatchValidateEntitlementsRequest {
+        requests: vec![validation(&expired), validation(&inactive), validation(&missing)],
+    });
+    let responses = service.batch_validate_entitlements(request).await.unwrap().into_inner().responses;
+    let codes: Vec<ErrorCode> = responses.iter().map(|response| response.error_code()).collect();
+    assert_eq!(codes, [ErrorCode::Expired, ErrorCode::Inactive, ErrorCode::NotFound]);
+    assert_eq!(validate(&service, &expired).await.error_code(), ErrorCode::Expired);
+
+    let nonce = format!("refusals-{}-{}", std::process::id(), SystemClock.now().as_millis());
+    let consume = |entitlement_id: &str, n: u32| ConsumeEntitlementRequest {
+        entitlement_id: entitlement_id.to_string(),
+        amount: 1,
+        signature: "".to_string(),
+        message: "".to_string(),
+        signed_request: Some(signed_request(entitlement_id, &format!("{}-{}", nonce, n), 1)),
+    };
+    let request = Request::new(BatchConsumeEntitlementsRequest {
+        requests: vec![consume(&expired, 1), consume(&inactive, 2), consume(&missing, 3), consume(&missing, 4)],
+    });
+    let responses = service.batch_consume_entitlements(request).await.unwrap().into_inner().responses;
+    let codes: Vec<ErrorCode> = responses.iter().map(|response| response.error_code()).collect();
+    assert_eq!(codes, [ErrorCode::Expired, ErrorCode::Inactive, ErrorCode::NotFound, ErrorCode::NotFound]);
+
+    // Refused items count against the rate limit too: the expired
+    // entitlement was validated twice and consumed once already
+    let request = Request::new(BatchConsumeEntitlementsRequest {
+        requests: vec![consume(&expired, 5), consume(&inactive, 6), consume(&missing, 7)],
+    });
+    let responses = service.batch_consume_entitlements(request).await.unwrap().into_inner().responses;
+    assert_eq!(responses[0].error_code(), ErrorCode::RateLimited);
+    assert_eq!(responses[1].error_code(), ErrorCode::Inactive);
+}